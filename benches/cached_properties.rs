@@ -0,0 +1,122 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Measures reads of cached properties, as done by a status bar polling on every frame.
+//!
+//! Run with `cargo bench --features bench`, which needs `dbus-daemon`. Before each read is
+//! measured by criterion, the heap allocations it makes are counted, and the bench fails if
+//! there are any. A getter served from the cache of zbus waits for the cache to be ready,
+//! then looks the property up by name twice under a lock, while `PowerStatusReceiver::get`
+//! only takes a lock. `Proxy::cached_property_raw` is measured alongside as the floor of a
+//! read from the cache of zbus.
+
+use criterion::{black_box, Criterion};
+use futures::FutureExt;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use upower_dbus::testing::TestUPower;
+use upower_dbus::zvariant::Value;
+use upower_dbus::{mock, BatteryState, DeviceType, Percentage};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Checks that `read` does not allocate, then measures it.
+fn bench<T>(criterion: &mut Criterion, name: &str, mut read: impl FnMut() -> T) {
+    // Initializes anything done on first use.
+    black_box(read());
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..1_000 {
+        black_box(read());
+    }
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    assert_eq!(allocations, 0, "{} allocated", name);
+
+    criterion.bench_function(name, |bencher| bencher.iter(&mut read));
+}
+
+/// Runs the benchmarks on the current thread, inside a tokio runtime if zbus uses tokio.
+fn run<F: Future<Output = upower_dbus::Result<()>>>(benchmarks: F) -> upower_dbus::Result<()> {
+    #[cfg(feature = "tokio")]
+    {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        runtime.block_on(benchmarks)
+    }
+
+    #[cfg(not(feature = "tokio"))]
+    futures::executor::block_on(benchmarks)
+}
+
+fn main() -> upower_dbus::Result<()> {
+    let mut criterion = Criterion::default().configure_from_args();
+
+    run(async {
+        let test = TestUPower::new().await?;
+
+        let mut battery = mock::device(DeviceType::Battery);
+        battery.percentage = Percentage::new(42.0);
+        battery.state = BatteryState::Discharging;
+        battery.native_path = String::from("BAT0");
+        battery.power_supply = true;
+        let path = test.mock.add_device("battery_BAT0", battery).await?;
+
+        let upower = &test.upower;
+        let device = upower.device(path).await?;
+
+        // Waits for the caches of both proxies to be filled.
+        upower.on_battery().await?;
+        device.percentage().await?;
+
+        bench(&mut criterion, "UPower.OnBattery", || {
+            upower.on_battery().now_or_never().unwrap().unwrap()
+        });
+
+        bench(&mut criterion, "Device.Percentage", || {
+            device.percentage().now_or_never().unwrap().unwrap()
+        });
+
+        bench(&mut criterion, "Device.State", || {
+            device.state().now_or_never().unwrap().unwrap()
+        });
+
+        bench(&mut criterion, "is_system_battery", || {
+            device.is_system_battery().now_or_never().unwrap().unwrap()
+        });
+
+        bench(&mut criterion, "cached_property_raw", || {
+            match device.inner().cached_property_raw("Percentage").as_deref() {
+                Some(Value::F64(percentage)) => *percentage,
+                _ => unreachable!(),
+            }
+        });
+
+        let status = upower.watch_power_status().await?;
+        bench(&mut criterion, "PowerStatusReceiver::get", || status.get());
+
+        Ok(())
+    })?;
+
+    criterion.final_summary();
+    Ok(())
+}
@@ -5,13 +5,13 @@ extern crate upower_dbus;
 
 use upower_dbus::UPowerProxy;
 
-fn main() -> zbus::Result<()> {
+fn main() -> upower_dbus::Result<()> {
     futures::executor::block_on(async move {
         let connection = zbus::Connection::system().await?;
 
         let upower = UPowerProxy::new(&connection).await?;
 
-        let device = upower.get_display_device().await?;
+        let device = upower.display_device().await?;
 
         println!("BatteryLevel: {:?}", device.battery_level().await);
         println!("IconName: {:?}", device.icon_name().await);
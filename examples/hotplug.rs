@@ -0,0 +1,28 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+extern crate upower_dbus;
+
+use futures::stream::StreamExt;
+use upower_dbus::{DeviceEvent, UPowerProxy};
+
+fn main() -> upower_dbus::Result<()> {
+    futures::executor::block_on(async move {
+        let connection = zbus::Connection::system().await?;
+
+        let upower = UPowerProxy::new(&connection).await?;
+
+        let mut stream = upower.watch_devices().await?;
+
+        while let Some(event) = stream.next().await {
+            match event? {
+                DeviceEvent::Added(device) => {
+                    println!("Added: {} ({:?})", device.path(), device.type_().await)
+                }
+                DeviceEvent::Removed(path) => println!("Removed: {}", path),
+            }
+        }
+
+        Ok(())
+    })
+}
@@ -0,0 +1,30 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+extern crate upower_dbus;
+
+use futures::stream::StreamExt;
+use upower_dbus::UPowerProxy;
+
+fn main() -> upower_dbus::Result<()> {
+    futures::executor::block_on(async move {
+        let connection = zbus::Connection::system().await?;
+
+        let upower = UPowerProxy::new(&connection).await?;
+
+        if !upower.lid_is_present().await? {
+            println!("This system has no lid");
+            return Ok(());
+        }
+
+        println!("Lid Closed: {:?}", upower.lid_is_closed().await);
+
+        let mut stream = upower.receive_lid_is_closed_changed().await;
+
+        while let Some(event) = stream.next().await {
+            println!("Lid Closed: {:?}", event.get().await);
+        }
+
+        Ok(())
+    })
+}
@@ -6,7 +6,7 @@ extern crate upower_dbus;
 use futures::stream::StreamExt;
 use upower_dbus::UPowerProxy;
 
-fn main() -> zbus::Result<()> {
+fn main() -> upower_dbus::Result<()> {
     futures::executor::block_on(async move {
         let connection = zbus::Connection::system().await?;
 
@@ -0,0 +1,20 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+extern crate upower_dbus;
+
+use upower_dbus::UPowerProxyBlocking;
+
+fn main() -> upower_dbus::Result<()> {
+    let connection = zbus::blocking::Connection::system()?;
+
+    let upower = UPowerProxyBlocking::new(&connection)?;
+
+    println!("On Battery: {:?}", upower.on_battery());
+
+    for event in upower.receive_on_battery_changed() {
+        println!("On Battery: {:?}", event.get());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,177 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use std::cmp::Ordering;
+
+use crate::device::{BatteryState, DeviceType, WarningLevel};
+use crate::icon::fallback_icon_name;
+use crate::snapshot::DeviceSnapshot;
+use crate::units::Percentage;
+
+/// The system batteries combined into one device, along with the readings of each battery.
+///
+/// Returned by `UPowerProxy::aggregate_batteries`. The combined device is computed the way
+/// the daemon computes its display device, so systems with several batteries, such as
+/// ThinkPads with an internal and a removable battery, can show both the total and the
+/// breakdown.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BatteryAggregate {
+    /// The sum of every battery, with its percentage, state and remaining time derived from
+    /// the totals.
+    pub combined: DeviceSnapshot,
+    /// The batteries which were combined, ordered by `NativePath` as by
+    /// `native_path_order`.
+    pub batteries: Vec<DeviceSnapshot>,
+}
+
+impl BatteryAggregate {
+    /// Combines the system batteries in `devices`, ignoring line power and peripherals.
+    pub fn new(devices: Vec<DeviceSnapshot>) -> Self {
+        let mut batteries: Vec<DeviceSnapshot> = devices
+            .into_iter()
+            .filter(|device| device.type_ == DeviceType::Battery && device.power_supply)
+            .collect();
+
+        batteries.sort_by(|a, b| native_path_order(&a.native_path, &b.native_path));
+
+        Self {
+            combined: combine(&batteries),
+            batteries,
+        }
+    }
+
+    /// The first battery which is charging.
+    pub fn charging(&self) -> Option<&DeviceSnapshot> {
+        self.with_state(BatteryState::Charging)
+    }
+
+    /// The first battery which is discharging.
+    ///
+    /// Laptops with two batteries usually drain one at a time, so this is the one in use.
+    pub fn discharging(&self) -> Option<&DeviceSnapshot> {
+        self.with_state(BatteryState::Discharging)
+    }
+
+    fn with_state(&self, state: BatteryState) -> Option<&DeviceSnapshot> {
+        self.batteries.iter().find(|battery| battery.state == state)
+    }
+}
+
+/// Orders devices by `NativePath` with the numbers in their names compared by value, so that
+/// `BAT2` comes before `BAT10`.
+///
+/// The kernel names batteries after their slot, so this order is stable across reboots and
+/// hot-swaps.
+///
+/// ```rust
+/// use std::cmp::Ordering;
+/// use upower_dbus::native_path_order;
+///
+/// assert_eq!(native_path_order("BAT2", "BAT10"), Ordering::Less);
+/// assert_eq!(native_path_order("/sys/class/power_supply/BAT1", "BAT0"), Ordering::Greater);
+/// ```
+pub fn native_path_order(a: &str, b: &str) -> Ordering {
+    fn key(native_path: &str) -> (&str, Option<u64>) {
+        let name = device_label(native_path);
+        let prefix = name.trim_end_matches(|c: char| c.is_ascii_digit());
+        (prefix, name[prefix.len()..].parse().ok())
+    }
+
+    key(a).cmp(&key(b)).then_with(|| a.cmp(b))
+}
+
+/// The last component of a `NativePath`, such as `BAT0` for `/sys/class/power_supply/BAT0`.
+pub(crate) fn device_label(native_path: &str) -> &str {
+    native_path.rsplit('/').next().unwrap_or(native_path)
+}
+
+/// Combines `batteries` into one device.
+fn combine(batteries: &[DeviceSnapshot]) -> DeviceSnapshot {
+    let mut combined = DeviceSnapshot::new(DeviceType::Battery);
+    combined.power_supply = true;
+    combined.is_present = !batteries.is_empty();
+
+    if batteries.is_empty() {
+        return combined;
+    }
+
+    combined.update_time = batteries
+        .iter()
+        .map(|battery| battery.update_time)
+        .max()
+        .unwrap_or(0);
+
+    combined.energy = batteries.iter().map(|battery| battery.energy).sum();
+    combined.energy_full = batteries.iter().map(|battery| battery.energy_full).sum();
+    combined.energy_full_design = batteries
+        .iter()
+        .map(|battery| battery.energy_full_design)
+        .sum();
+    combined.energy_rate = batteries.iter().map(|battery| battery.energy_rate).sum();
+
+    combined.percentage = Percentage::new(if combined.energy_full.0 > 0.0 {
+        combined.energy.0 / combined.energy_full.0 * 100.0
+    } else {
+        batteries
+            .iter()
+            .map(|battery| battery.percentage.get())
+            .sum::<f64>()
+            / batteries.len() as f64
+    });
+
+    let any = |state| batteries.iter().any(|battery| battery.state == state);
+
+    combined.state = if any(BatteryState::Charging) {
+        BatteryState::Charging
+    } else if any(BatteryState::Discharging) {
+        BatteryState::Discharging
+    } else if batteries
+        .iter()
+        .all(|battery| battery.state == BatteryState::FullyCharged)
+    {
+        BatteryState::FullyCharged
+    } else {
+        batteries[0].state
+    };
+
+    estimate_times(&mut combined);
+    combined.warning_level = warning_level(&combined);
+    combined.icon_name = fallback_icon_name(combined.percentage, combined.state);
+    combined
+}
+
+/// Fills in the time to empty or full from the energy and rate.
+pub(crate) fn estimate_times(device: &mut DeviceSnapshot) {
+    let remaining = match device.state {
+        BatteryState::Discharging => device.energy,
+        BatteryState::Charging => device.energy_full - device.energy,
+        _ => return,
+    };
+
+    let seconds = match remaining.time_at(device.energy_rate) {
+        Some(time) => time.as_secs() as i64,
+        None => return,
+    };
+
+    if device.state == BatteryState::Discharging {
+        device.time_to_empty = seconds;
+    } else {
+        device.time_to_full = seconds;
+    }
+}
+
+/// The warning level of a system battery, using the daemon's default thresholds.
+pub(crate) fn warning_level(device: &DeviceSnapshot) -> WarningLevel {
+    if !device.power_supply || device.state != BatteryState::Discharging {
+        WarningLevel::None
+    } else if device.percentage.get() <= 2.0 {
+        WarningLevel::Action
+    } else if device.percentage.get() <= 5.0 {
+        WarningLevel::Critical
+    } else if device.percentage.get() <= 20.0 {
+        WarningLevel::Low
+    } else {
+        WarningLevel::None
+    }
+}
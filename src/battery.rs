@@ -0,0 +1,380 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! An adapter with the API of the `battery` crate, reading from the UPower daemon.
+//!
+//! Projects using `battery::Manager` can switch to this module by changing their imports
+//! from `battery` to `upower_dbus::battery`, then move to `DeviceSnapshot` one reading at a
+//! time. Readings are the same `uom` quantities, re-exported by `units` as by
+//! `battery::units`, and only the errors differ, being this crate's `Error`. The `battery`
+//! crate itself is not a dependency, since a `battery::Battery` can only be created by its
+//! own platform backends.
+//!
+//! ```rust,no_run
+//! use upower_dbus::battery::units::ratio::percent;
+//! use upower_dbus::battery::Manager;
+//!
+//! # fn main() -> upower_dbus::Result<()> {
+//! let manager = Manager::new()?;
+//!
+//! for battery in manager.batteries()? {
+//!     let mut battery = battery?;
+//!     println!("{:?}: {:.0}%", battery.vendor(), battery.state_of_charge().get::<percent>());
+//!
+//!     manager.refresh(&mut battery)?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::fmt;
+use zbus::zvariant::OwnedObjectPath;
+
+use crate::device::{self, BatteryState, DeviceProxyBlocking, DeviceType};
+use crate::error::{Error, Result};
+use crate::snapshot::DeviceSnapshot;
+use crate::upower::UPowerProxyBlocking;
+
+use self::units::electric_potential::volt;
+use self::units::energy::joule;
+use self::units::power::watt;
+use self::units::ratio::ratio;
+use self::units::thermodynamic_temperature::kelvin;
+use self::units::time::second;
+use self::units::{ElectricPotential, Energy, Power, Ratio, ThermodynamicTemperature, Time};
+
+/// The quantities returned by `Battery`, and their units, as `battery::units`.
+pub mod units {
+    pub use uom::si::f32::{
+        ElectricPotential, Energy, Power, Ratio, ThermodynamicTemperature, Time,
+    };
+    pub use uom::si::{electric_potential, energy, power, ratio, thermodynamic_temperature, time};
+}
+
+/// The charging state of a battery, as `battery::State`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum State {
+    Unknown,
+    Charging,
+    Discharging,
+    Empty,
+    Full,
+}
+
+/// The daemon's pending states, in which the battery neither charges nor discharges, are
+/// `Unknown`, as `battery` reports a battery which is not charging.
+impl From<BatteryState> for State {
+    fn from(state: BatteryState) -> Self {
+        match state {
+            BatteryState::Charging => State::Charging,
+            BatteryState::Discharging => State::Discharging,
+            BatteryState::Empty => State::Empty,
+            BatteryState::FullyCharged => State::Full,
+            _ => State::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            State::Unknown => "unknown",
+            State::Charging => "charging",
+            State::Discharging => "discharging",
+            State::Empty => "empty",
+            State::Full => "full",
+        })
+    }
+}
+
+/// The chemistry of a battery, as `battery::Technology`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Technology {
+    Unknown,
+    LithiumIon,
+    LeadAcid,
+    LithiumPolymer,
+    NickelMetalHydride,
+    NickelCadmium,
+    NickelZinc,
+    LithiumIronPhosphate,
+    RechargeableAlkalineManganese,
+}
+
+impl From<device::Technology> for Technology {
+    fn from(technology: device::Technology) -> Self {
+        match technology {
+            device::Technology::LithiumIon => Technology::LithiumIon,
+            device::Technology::LithiumPolymer => Technology::LithiumPolymer,
+            device::Technology::LithiumIronPhosphate => Technology::LithiumIronPhosphate,
+            device::Technology::LeadAcid => Technology::LeadAcid,
+            device::Technology::NickelCadmium => Technology::NickelCadmium,
+            device::Technology::NickelMetalHydride => Technology::NickelMetalHydride,
+            _ => Technology::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for Technology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Technology::Unknown => "unknown",
+            Technology::LithiumIon => "lithium-ion",
+            Technology::LeadAcid => "lead-acid",
+            Technology::LithiumPolymer => "lithium-polymer",
+            Technology::NickelMetalHydride => "nickel-metal-hydride",
+            Technology::NickelCadmium => "nickel-cadmium",
+            Technology::NickelZinc => "nickel-zinc",
+            Technology::LithiumIronPhosphate => "lithium-iron-phosphate",
+            Technology::RechargeableAlkalineManganese => "rechargeable-alkaline-manganese",
+        })
+    }
+}
+
+/// A battery, with the accessors of `battery::Battery`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Battery {
+    snapshot: DeviceSnapshot,
+    path: Option<OwnedObjectPath>,
+}
+
+/// Fails with the snapshot given, if it is not of a battery.
+///
+/// A battery converted from a snapshot has no device to be refreshed from.
+impl TryFrom<DeviceSnapshot> for Battery {
+    type Error = DeviceSnapshot;
+
+    fn try_from(snapshot: DeviceSnapshot) -> std::result::Result<Self, DeviceSnapshot> {
+        if snapshot.type_ != DeviceType::Battery {
+            return Err(snapshot);
+        }
+
+        Ok(Self {
+            snapshot,
+            path: None,
+        })
+    }
+}
+
+impl Battery {
+    fn read(device: &DeviceProxyBlocking<'_>) -> Result<Self> {
+        Ok(Self {
+            snapshot: device.snapshot()?,
+            path: Some(device.path().to_owned().into()),
+        })
+    }
+
+    /// The readings of the battery.
+    pub fn snapshot(&self) -> &DeviceSnapshot {
+        &self.snapshot
+    }
+
+    pub fn vendor(&self) -> Option<&str> {
+        non_empty(&self.snapshot.vendor)
+    }
+
+    pub fn model(&self) -> Option<&str> {
+        non_empty(&self.snapshot.model)
+    }
+
+    pub fn serial_number(&self) -> Option<&str> {
+        non_empty(&self.snapshot.serial)
+    }
+
+    pub fn technology(&self) -> Technology {
+        Technology::from(self.snapshot.technology)
+    }
+
+    pub fn state(&self) -> State {
+        State::from(self.snapshot.state)
+    }
+
+    pub fn cycle_count(&self) -> Option<u32> {
+        self.snapshot
+            .charge_cycles
+            .and_then(|cycles| u32::try_from(cycles).ok())
+    }
+
+    /// The charge, relative to the full energy.
+    pub fn state_of_charge(&self) -> Ratio {
+        Ratio::new::<ratio>((self.snapshot.percentage.get() / 100.0) as f32)
+    }
+
+    /// The energy stored.
+    pub fn energy(&self) -> Energy {
+        Energy::new::<joule>(self.snapshot.energy.as_joules() as f32)
+    }
+
+    /// The energy stored when full.
+    pub fn energy_full(&self) -> Energy {
+        Energy::new::<joule>(self.snapshot.energy_full.as_joules() as f32)
+    }
+
+    /// The energy stored when full by a new battery.
+    pub fn energy_full_design(&self) -> Energy {
+        Energy::new::<joule>(self.snapshot.energy_full_design.as_joules() as f32)
+    }
+
+    /// The rate of energy flowing in or out of the battery.
+    pub fn energy_rate(&self) -> Power {
+        Power::new::<watt>(self.snapshot.energy_rate.0 as f32)
+    }
+
+    pub fn voltage(&self) -> ElectricPotential {
+        ElectricPotential::new::<volt>(self.snapshot.voltage.0 as f32)
+    }
+
+    /// The full energy relative to the design energy, or 1 if the design energy is unknown.
+    pub fn state_of_health(&self) -> Ratio {
+        let health = self
+            .snapshot
+            .battery_health_percent()
+            .map_or(1.0, |health| health / 100.0);
+        Ratio::new::<ratio>(health as f32)
+    }
+
+    pub fn temperature(&self) -> Option<ThermodynamicTemperature> {
+        let celsius = self.snapshot.temperature?;
+        Some(ThermodynamicTemperature::new::<kelvin>(
+            celsius.as_kelvin() as f32
+        ))
+    }
+
+    /// The estimated time until the battery is full.
+    pub fn time_to_full(&self) -> Option<Time> {
+        let remaining = self.snapshot.time_to_full_duration()?;
+        Some(Time::new::<second>(remaining.as_secs_f32()))
+    }
+
+    /// The estimated time until the battery is empty.
+    pub fn time_to_empty(&self) -> Option<Time> {
+        let remaining = self.snapshot.time_to_empty_duration()?;
+        Some(Time::new::<second>(remaining.as_secs_f32()))
+    }
+}
+
+fn non_empty(value: &str) -> Option<&str> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Enumerates and refreshes batteries, as `battery::Manager`.
+#[derive(Clone, Debug)]
+pub struct Manager {
+    upower: UPowerProxyBlocking<'static>,
+}
+
+impl Manager {
+    /// Connects to the daemon on the system bus.
+    pub fn new() -> Result<Self> {
+        Ok(Self::with_proxy(UPowerProxyBlocking::system()?))
+    }
+
+    /// Reads from the daemon behind `upower`, such as one on a private bus.
+    pub fn with_proxy(upower: UPowerProxyBlocking<'static>) -> Self {
+        Self { upower }
+    }
+
+    /// The batteries which power the system, in the order of `UPowerProxy::system_batteries`.
+    pub fn batteries(&self) -> Result<Batteries> {
+        Ok(Batteries {
+            devices: self.upower.system_batteries()?.into_iter(),
+        })
+    }
+
+    /// Reads every value of `battery` again.
+    ///
+    /// Fails with `Error::NoSuchDevice` for a battery converted from a `DeviceSnapshot`.
+    pub fn refresh(&self, battery: &mut Battery) -> Result<()> {
+        let path = battery.path.clone().ok_or(Error::NoSuchDevice)?;
+        *battery = Battery::read(&self.upower.device(path)?)?;
+        Ok(())
+    }
+}
+
+/// The batteries of a `Manager`, each read as it is reached.
+#[derive(Debug)]
+pub struct Batteries {
+    devices: std::vec::IntoIter<DeviceProxyBlocking<'static>>,
+}
+
+impl Iterator for Batteries {
+    type Item = Result<Battery>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.devices.next().map(|device| Battery::read(&device))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.devices.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::units::electric_potential::volt;
+    use super::units::energy::{joule, watt_hour};
+    use super::units::power::watt;
+    use super::units::ratio::{percent, ratio};
+    use super::units::thermodynamic_temperature::kelvin;
+    use super::units::time::second;
+    use super::{Battery, State, Technology};
+    use crate::device::{self, BatteryState, DeviceType};
+    use crate::snapshot::DeviceSnapshot;
+    use crate::units::{Celsius, Percentage, Volts, WattHours, Watts};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn readings_are_in_si_units() {
+        let mut snapshot = DeviceSnapshot::new(DeviceType::Battery);
+        snapshot.percentage = Percentage::new(50.0);
+        snapshot.energy = WattHours(25.0);
+        snapshot.energy_full = WattHours(50.0);
+        snapshot.energy_full_design = WattHours(62.5);
+        snapshot.energy_rate = Watts(12.5);
+        snapshot.voltage = Volts(11.4);
+        snapshot.temperature = Some(Celsius(26.85));
+        snapshot.time_to_empty = 7200;
+        snapshot.state = BatteryState::Discharging;
+        snapshot.technology = device::Technology::LithiumPolymer;
+        snapshot.charge_cycles = Some(-1);
+        snapshot.vendor = "ACME".to_owned();
+
+        let battery = Battery::try_from(snapshot).unwrap();
+        assert_eq!(battery.state_of_charge().get::<ratio>(), 0.5);
+        assert_eq!(battery.state_of_charge().get::<percent>(), 50.0);
+        assert_eq!(battery.energy().get::<joule>(), 90_000.0);
+        assert_eq!(battery.energy().get::<watt_hour>(), 25.0);
+        assert_eq!(battery.energy_full().get::<joule>(), 180_000.0);
+        assert_eq!(battery.energy_full_design().get::<joule>(), 225_000.0);
+        assert_eq!(battery.energy_rate().get::<watt>(), 12.5);
+        assert_eq!(battery.voltage().get::<volt>(), 11.4);
+        assert_eq!(battery.state_of_health().get::<ratio>(), 0.8);
+        let temperature = battery.temperature().unwrap().get::<kelvin>();
+        assert!((temperature - 300.0).abs() < 1e-3);
+        let time_to_empty = battery.time_to_empty().map(|time| time.get::<second>());
+        assert_eq!(time_to_empty, Some(7200.0));
+        assert_eq!(battery.time_to_full(), None);
+        assert_eq!(battery.state(), State::Discharging);
+        assert_eq!(battery.technology(), Technology::LithiumPolymer);
+        assert_eq!(battery.cycle_count(), None);
+        assert_eq!(battery.vendor(), Some("ACME"));
+        assert_eq!(battery.model(), None);
+    }
+
+    #[test]
+    fn only_batteries_convert() {
+        let snapshot = DeviceSnapshot::new(DeviceType::Mouse);
+        assert_eq!(Battery::try_from(snapshot.clone()), Err(snapshot));
+    }
+
+    #[test]
+    fn pending_states_are_unknown() {
+        assert_eq!(State::from(BatteryState::PendingCharge), State::Unknown);
+        assert_eq!(State::from(BatteryState::FullyCharged), State::Full);
+    }
+}
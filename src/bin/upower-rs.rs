@@ -0,0 +1,163 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! A small clone of the `upower` command-line tool, built on this crate's blocking proxies.
+
+use std::convert::TryFrom;
+use std::thread;
+use upower_dbus::{DeviceProxyBlocking, DeviceSnapshot, UPowerProxyBlocking, UPOWER_SERVICE};
+use zbus::blocking::fdo::PropertiesProxy;
+use zbus::blocking::Connection;
+use zbus::zvariant::OwnedObjectPath;
+
+const USAGE: &str =
+    "Usage: upower-rs [-e | --enumerate] [-i | --show-info <path>] [-m | --monitor]";
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+
+    let result = match args.next().as_deref() {
+        Some("-e") | Some("--enumerate") => enumerate(),
+        Some("-i") | Some("--show-info") => match args.next() {
+            Some(path) => show_info(&path),
+            None => usage(),
+        },
+        Some("-m") | Some("--monitor") => monitor(),
+        _ => usage(),
+    };
+
+    if let Err(why) = result {
+        eprintln!("upower-rs: {}", why);
+        std::process::exit(1);
+    }
+}
+
+fn usage() -> upower_dbus::Result<()> {
+    eprintln!("{}", USAGE);
+    std::process::exit(1);
+}
+
+fn enumerate() -> upower_dbus::Result<()> {
+    let connection = Connection::system()?;
+    let upower = UPowerProxyBlocking::new(&connection)?;
+
+    for path in upower.enumerate_devices()? {
+        println!("{}", path.as_str());
+    }
+
+    println!("{}", upower.display_device()?.path().as_str());
+
+    Ok(())
+}
+
+fn show_info(path: &str) -> upower_dbus::Result<()> {
+    let connection = Connection::system()?;
+    let upower = UPowerProxyBlocking::new(&connection)?;
+    let device = upower.device(OwnedObjectPath::try_from(path)?)?;
+
+    print_snapshot(&device.snapshot()?);
+
+    Ok(())
+}
+
+fn print_snapshot(device: &DeviceSnapshot) {
+    let yes_no = |value: bool| if value { "yes" } else { "no" };
+
+    println!("  native-path:          {}", device.native_path);
+    println!("  vendor:               {}", device.vendor);
+    println!("  model:                {}", device.model);
+    println!("  serial:               {}", device.serial);
+    println!("  power supply:         {}", yes_no(device.power_supply));
+    println!("  updated:              {}", device.update_time);
+    println!("  has history:          {}", yes_no(device.has_history));
+    println!("  has statistics:       {}", yes_no(device.has_statistics));
+    println!("  {}", device.type_);
+    println!("    present:             {}", yes_no(device.is_present));
+    println!(
+        "    rechargeable:        {}",
+        yes_no(device.is_rechargeable)
+    );
+    println!("    state:               {}", device.state);
+    println!("    warning-level:       {}", device.warning_level);
+    println!("    energy:              {}", device.energy);
+    println!("    energy-empty:        {}", device.energy_empty);
+    println!("    energy-full:         {}", device.energy_full);
+    println!("    energy-full-design:  {}", device.energy_full_design);
+    println!("    energy-rate:         {}", device.energy_rate);
+    println!("    voltage:             {}", device.voltage);
+    println!("    time to empty:       {} s", device.time_to_empty);
+    println!("    time to full:        {} s", device.time_to_full);
+    println!("    percentage:          {}", device.percentage);
+    println!("    capacity:            {}%", device.capacity);
+    println!("    technology:          {}", device.technology);
+    println!("    icon-name:          '{}'", device.icon_name);
+}
+
+fn monitor() -> upower_dbus::Result<()> {
+    let connection = Connection::system()?;
+    let upower = UPowerProxyBlocking::new(&connection)?;
+
+    println!("Monitoring activity from the power daemon. Press Ctrl+C to cancel.");
+
+    let mut threads = vec![watch_changes(upower.path().to_owned().into(), &connection)];
+
+    for path in upower.enumerate_devices()? {
+        threads.push(watch_changes(path, &connection));
+    }
+
+    let added = upower.receive_device_added()?;
+    let added_connection = connection.clone();
+    threads.push(thread::spawn(move || {
+        for signal in added {
+            if let Ok(args) = signal.args() {
+                println!("device added:     {}", args.device.as_str());
+                watch_changes(args.device.into(), &added_connection);
+            }
+        }
+    }));
+
+    let removed = upower.receive_device_removed()?;
+    threads.push(thread::spawn(move || {
+        for signal in removed {
+            if let Ok(args) = signal.args() {
+                println!("device removed:   {}", args.device.as_str());
+            }
+        }
+    }));
+
+    for thread in threads {
+        let _ = thread.join();
+    }
+
+    Ok(())
+}
+
+fn watch_changes(path: OwnedObjectPath, connection: &Connection) -> thread::JoinHandle<()> {
+    let connection = connection.clone();
+
+    thread::spawn(move || {
+        let properties = match PropertiesProxy::builder(&connection)
+            .destination(UPOWER_SERVICE)
+            .and_then(|builder| builder.path(path.clone()))
+            .and_then(|builder| builder.build())
+        {
+            Ok(properties) => properties,
+            Err(_) => return,
+        };
+
+        let changes = match properties.receive_properties_changed() {
+            Ok(changes) => changes,
+            Err(_) => return,
+        };
+
+        for _ in changes {
+            println!("device changed:   {}", path.as_str());
+
+            if let Ok(device) = DeviceProxyBlocking::new(&connection, path.clone()) {
+                if let Ok(snapshot) = device.snapshot() {
+                    print_snapshot(&snapshot);
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,40 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::stream::{Stream, StreamExt};
+use std::fmt;
+
+/// An iterator over the items of one of the crate's streams, which blocks the thread until
+/// each item arrives.
+///
+/// Returned by the methods of `UPowerProxyBlocking` and `DeviceProxyBlocking` which mirror the
+/// streams of their async counterparts. The items are the same as those of the stream, so
+/// devices are carried as `DeviceProxy`, which converts into a `DeviceProxyBlocking`.
+pub struct BlockingIter<S> {
+    stream: S,
+}
+
+impl<S> BlockingIter<S> {
+    pub(crate) fn new(stream: S) -> Self {
+        Self { stream }
+    }
+
+    /// The stream which the iterator blocks on.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for BlockingIter<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("BlockingIter").field(&self.stream).finish()
+    }
+}
+
+impl<S: Stream + Unpin> Iterator for BlockingIter<S> {
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        zbus::block_on(self.stream.next())
+    }
+}
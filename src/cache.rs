@@ -0,0 +1,189 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::stream::{self, StreamExt};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::Task;
+
+use crate::change::DeviceChange;
+use crate::device::fetch_snapshot;
+use crate::error::{Error, Result};
+use crate::id::DeviceId;
+use crate::snapshot::DeviceSnapshot;
+use crate::upower::UPowerProxy;
+use crate::watch::WatchCell;
+
+/// The latest snapshot of every device, created by `UPowerProxy::device_cache`.
+///
+/// The snapshots are kept current by a task on the executor of the connection, which applies
+/// each `PropertiesChanged` signal and follows devices being added and removed. Reading the
+/// cache never waits on the bus, so it may be done from a render path. The task stops once
+/// every clone of the cache is dropped.
+///
+/// As with `enumerate_devices`, the display device is left out.
+#[derive(Clone)]
+pub struct DeviceCache {
+    devices: Arc<WatchCell<Devices>>,
+    seen: u64,
+    _task: Arc<Task<()>>,
+}
+
+impl fmt::Debug for DeviceCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeviceCache")
+            .field("devices", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl DeviceCache {
+    /// The latest snapshot of the device `id`, if it is present.
+    pub fn get(&self, id: &DeviceId) -> Option<DeviceSnapshot> {
+        self.devices.read(|devices| devices.get(id).cloned())
+    }
+
+    /// The latest snapshot of every device, ordered by their ids.
+    pub fn devices(&self) -> Vec<(DeviceId, DeviceSnapshot)> {
+        self.devices.read(|devices| {
+            devices
+                .iter()
+                .map(|(id, snapshot)| (id.clone(), snapshot.clone()))
+                .collect()
+        })
+    }
+
+    /// The number of devices present.
+    pub fn len(&self) -> usize {
+        self.devices.read(BTreeMap::len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether any device changed since the cache was last marked as seen by `changed`.
+    pub fn has_changed(&self) -> bool {
+        self.devices.has_changed(self.seen)
+    }
+
+    /// Waits until a device changes, is added or is removed, and marks the cache as seen.
+    ///
+    /// Fails with the error which stopped the cache from being updated, such as the
+    /// connection closing.
+    pub async fn changed(&mut self) -> Result<()> {
+        self.devices.changed(&mut self.seen).await
+    }
+
+    pub(crate) async fn spawn(upower: UPowerProxy<'static>) -> Result<Self> {
+        let added = upower
+            .receive_device_added()
+            .await?
+            .map(|signal| Ok(Update::Added(signal.args()?.device.into())));
+
+        let removed = upower
+            .receive_device_removed()
+            .await?
+            .map(|signal| Ok(Update::Removed(signal.args()?.device.into())));
+
+        let changed = upower
+            .receive_all_device_changes()
+            .await?
+            .map(|change| change.map(|(id, change)| Update::Changed(id, Box::new(change))));
+
+        let restarted = upower
+            .receive_restarts()
+            .await?
+            .map(|restarted| restarted.map(|_| Update::Restarted));
+
+        let updates = stream::select_all(vec![
+            added.boxed(),
+            removed.boxed(),
+            changed.boxed(),
+            restarted.boxed(),
+        ]);
+
+        let devices = upower.snapshot_all_devices().await?;
+
+        let devices = Arc::new(WatchCell::new(devices.into_iter().collect()));
+
+        let task = upower.connection().executor().spawn(
+            update(upower.clone(), updates, devices.clone()),
+            "upower-dbus device cache",
+        );
+
+        Ok(Self {
+            devices,
+            seen: 0,
+            _task: Arc::new(task),
+        })
+    }
+}
+
+enum Update {
+    Added(OwnedObjectPath),
+    Removed(OwnedObjectPath),
+    Changed(DeviceId, Box<DeviceChange>),
+    Restarted,
+}
+
+type Devices = BTreeMap<DeviceId, DeviceSnapshot>;
+
+/// Applies every update to the cache, until watching or reading the daemon fails.
+async fn update(
+    upower: UPowerProxy<'static>,
+    mut updates: impl stream::Stream<Item = Result<Update>> + Unpin,
+    devices: Arc<WatchCell<Devices>>,
+) {
+    let why = loop {
+        let result = match updates.next().await {
+            Some(Ok(Update::Added(path))) => {
+                match fetch_snapshot(upower.connection(), upower.destination(), &path).await {
+                    Ok(snapshot) => {
+                        devices.modify(|devices| {
+                            devices.insert(DeviceId::from(path), snapshot);
+                            true
+                        });
+                        Ok(())
+                    }
+                    // The device was removed again before it could be read.
+                    Err(Error::NoSuchDevice) => Ok(()),
+                    Err(why) => Err(why),
+                }
+            }
+            Some(Ok(Update::Removed(path))) => {
+                devices.modify(|devices| devices.remove(&DeviceId::from(path)).is_some());
+                Ok(())
+            }
+            Some(Ok(Update::Changed(id, change))) => {
+                devices.modify(|devices| match devices.get_mut(&id) {
+                    Some(snapshot) => {
+                        change.apply_to(snapshot);
+                        true
+                    }
+                    None => false,
+                });
+                Ok(())
+            }
+            Some(Ok(Update::Restarted)) => upower.snapshot_all_devices().await.map(|all| {
+                devices.modify(|devices| {
+                    *devices = all.into_iter().collect();
+                    true
+                })
+            }),
+            Some(Err(why)) => Err(why),
+            None => Err(Error::Transport(zbus::Error::InputOutput(Arc::new(
+                io::Error::new(io::ErrorKind::UnexpectedEof, "the connection was closed"),
+            )))),
+        };
+
+        if let Err(why) = result {
+            break why;
+        }
+    };
+
+    devices.close(why);
+}
@@ -0,0 +1,181 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitOrAssign};
+
+use crate::device::BatteryLevel;
+use crate::snapshot::DeviceSnapshot;
+
+/// The optional properties which a device reports, returned by `DeviceProxy::capabilities`.
+///
+/// The daemon sends every property of a device, filling in zero or an empty string for those
+/// the hardware lacks, so a property counts as reported when its value differs from that
+/// placeholder. A UI may hide the fields which are not reported.
+///
+/// ```rust
+/// use upower_dbus::{DeviceCapabilities, DeviceSnapshot, DeviceType, Volts};
+///
+/// let mut battery = DeviceSnapshot::new(DeviceType::Battery);
+/// battery.voltage = Volts(12.1);
+///
+/// let capabilities = battery.capabilities();
+/// assert!(capabilities.contains(DeviceCapabilities::VOLTAGE));
+/// assert!(!capabilities.contains(DeviceCapabilities::TEMPERATURE));
+///
+/// // The capacity defaults to 100%, which is only a placeholder without the energies.
+/// assert_eq!(battery.capacity, 100.0);
+/// assert!(!capabilities.contains(DeviceCapabilities::CAPACITY));
+/// ```
+#[derive(Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DeviceCapabilities(u32);
+
+impl DeviceCapabilities {
+    /// A charge in percent, rather than only a coarse `BatteryLevel`.
+    pub const PERCENTAGE: Self = Self(1 << 0);
+    /// A coarse `BatteryLevel`, as reported by some peripherals instead of a percentage.
+    pub const BATTERY_LEVEL: Self = Self(1 << 1);
+    /// The stored and full energy.
+    pub const ENERGY: Self = Self(1 << 2);
+    /// The energy when new, which the health of the battery is derived from.
+    pub const DESIGN_ENERGY: Self = Self(1 << 3);
+    pub const VOLTAGE: Self = Self(1 << 4);
+    pub const TEMPERATURE: Self = Self(1 << 5);
+    pub const CHARGE_CYCLES: Self = Self(1 << 6);
+    /// The full energy relative to the energy when new, which the daemon derives from both,
+    /// leaving the placeholder of 100% when either is unknown.
+    pub const CAPACITY: Self = Self(1 << 7);
+    /// Charge start and end thresholds, which UPower 1.90 and newer can set.
+    pub const CHARGE_THRESHOLDS: Self = Self(1 << 8);
+    pub const HISTORY: Self = Self(1 << 9);
+    pub const STATISTICS: Self = Self(1 << 10);
+    pub const LUMINOSITY: Self = Self(1 << 11);
+    pub const SERIAL: Self = Self(1 << 12);
+
+    const NAMES: [(Self, &'static str); 13] = [
+        (Self::PERCENTAGE, "PERCENTAGE"),
+        (Self::BATTERY_LEVEL, "BATTERY_LEVEL"),
+        (Self::ENERGY, "ENERGY"),
+        (Self::DESIGN_ENERGY, "DESIGN_ENERGY"),
+        (Self::VOLTAGE, "VOLTAGE"),
+        (Self::TEMPERATURE, "TEMPERATURE"),
+        (Self::CHARGE_CYCLES, "CHARGE_CYCLES"),
+        (Self::CAPACITY, "CAPACITY"),
+        (Self::CHARGE_THRESHOLDS, "CHARGE_THRESHOLDS"),
+        (Self::HISTORY, "HISTORY"),
+        (Self::STATISTICS, "STATISTICS"),
+        (Self::LUMINOSITY, "LUMINOSITY"),
+        (Self::SERIAL, "SERIAL"),
+    ];
+
+    /// No capabilities.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// The capabilities as a bitmask.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether every capability of `other` is present.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+
+    /// The capabilities of the device `snapshot` was taken of.
+    pub(crate) fn of(snapshot: &DeviceSnapshot) -> Self {
+        let flags = [
+            (
+                Self::PERCENTAGE,
+                snapshot.is_present && snapshot.battery_level == BatteryLevel::None,
+            ),
+            (
+                Self::BATTERY_LEVEL,
+                !matches!(
+                    snapshot.battery_level,
+                    BatteryLevel::Unknown | BatteryLevel::None
+                ),
+            ),
+            (Self::ENERGY, snapshot.energy_full.0 > 0.0),
+            (Self::DESIGN_ENERGY, snapshot.energy_full_design.0 > 0.0),
+            (Self::VOLTAGE, snapshot.voltage.0 != 0.0),
+            (Self::TEMPERATURE, snapshot.temperature.is_some()),
+            (Self::CHARGE_CYCLES, snapshot.charge_cycles.is_some()),
+            (
+                Self::CAPACITY,
+                snapshot.energy_full.0 > 0.0 && snapshot.energy_full_design.0 > 0.0,
+            ),
+            (
+                Self::CHARGE_THRESHOLDS,
+                snapshot.charge_threshold_supported == Some(true),
+            ),
+            (Self::HISTORY, snapshot.has_history),
+            (Self::STATISTICS, snapshot.has_statistics),
+            (Self::LUMINOSITY, snapshot.luminosity != 0.0),
+            (Self::SERIAL, !snapshot.serial.is_empty()),
+        ];
+
+        flags
+            .iter()
+            .filter(|(_, reported)| *reported)
+            .fold(Self::empty(), |capabilities, (flag, _)| {
+                capabilities | *flag
+            })
+    }
+}
+
+/// Lists the names of the capabilities, such as `DeviceCapabilities(VOLTAGE | SERIAL)`.
+impl fmt::Debug for DeviceCapabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DeviceCapabilities(")?;
+
+        let mut names = Self::NAMES
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| name);
+
+        if let Some(name) = names.next() {
+            f.write_str(name)?;
+            for name in names {
+                write!(f, " | {}", name)?;
+            }
+        }
+
+        f.write_str(")")
+    }
+}
+
+impl BitOr for DeviceCapabilities {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for DeviceCapabilities {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+impl BitAnd for DeviceCapabilities {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+}
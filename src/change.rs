@@ -0,0 +1,399 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::stream::Stream;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use zbus::zvariant::OwnedValue;
+
+use crate::device::{
+    cycles, temperature, BatteryLevel, BatteryState, DeviceType, Technology, WarningLevel,
+};
+use crate::error::{Error, Result};
+use crate::id::DeviceId;
+use crate::snapshot::{take_optional, DeviceSnapshot};
+use crate::units::{Celsius, Percentage, Volts, WattHours, Watts};
+
+/// The properties of a device which changed in a single `PropertiesChanged` signal, with
+/// `None` for those which did not change.
+///
+/// Created by `DeviceProxy::receive_changes`, and by `DeviceSnapshot::decode_lenient` with
+/// `None` for the properties which could not be decoded.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DeviceChange {
+    pub battery_level: Option<BatteryLevel>,
+    pub capacity: Option<f64>,
+    /// `Some(None)` if the number of charge cycles became unknown.
+    pub charge_cycles: Option<Option<i32>>,
+    pub charge_end_threshold: Option<u32>,
+    pub charge_start_threshold: Option<u32>,
+    pub charge_threshold_enabled: Option<bool>,
+    pub charge_threshold_supported: Option<bool>,
+    pub energy: Option<WattHours>,
+    pub energy_empty: Option<WattHours>,
+    pub energy_full: Option<WattHours>,
+    pub energy_full_design: Option<WattHours>,
+    pub energy_rate: Option<Watts>,
+    pub has_history: Option<bool>,
+    pub has_statistics: Option<bool>,
+    pub icon_name: Option<String>,
+    pub is_present: Option<bool>,
+    pub is_rechargeable: Option<bool>,
+    pub luminosity: Option<f64>,
+    pub model: Option<String>,
+    pub native_path: Option<String>,
+    pub online: Option<bool>,
+    pub percentage: Option<Percentage>,
+    pub power_supply: Option<bool>,
+    pub serial: Option<String>,
+    pub state: Option<BatteryState>,
+    pub technology: Option<Technology>,
+    /// `Some(None)` if the hardware stopped reporting a temperature.
+    pub temperature: Option<Option<Celsius>>,
+    pub time_to_empty: Option<i64>,
+    pub time_to_full: Option<i64>,
+    pub type_: Option<DeviceType>,
+    pub update_time: Option<u64>,
+    pub vendor: Option<String>,
+    pub voltage: Option<Volts>,
+    pub warning_level: Option<WarningLevel>,
+}
+
+impl DeviceChange {
+    /// Whether none of the properties changed.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Updates `snapshot` with the values which changed.
+    pub fn apply_to(&self, snapshot: &mut DeviceSnapshot) {
+        macro_rules! apply {
+            ($($field:ident),*) => {
+                $(
+                    if let Some(value) = &self.$field {
+                        snapshot.$field = value.clone();
+                    }
+                )*
+            };
+        }
+
+        apply!(
+            battery_level,
+            capacity,
+            charge_cycles,
+            energy,
+            energy_empty,
+            energy_full,
+            energy_full_design,
+            energy_rate,
+            has_history,
+            has_statistics,
+            icon_name,
+            is_present,
+            is_rechargeable,
+            luminosity,
+            model,
+            native_path,
+            online,
+            percentage,
+            power_supply,
+            serial,
+            state,
+            technology,
+            temperature,
+            time_to_empty,
+            time_to_full,
+            type_,
+            update_time,
+            vendor,
+            voltage,
+            warning_level
+        );
+
+        if let Some(value) = self.charge_end_threshold {
+            snapshot.charge_end_threshold = Some(value);
+        }
+
+        if let Some(value) = self.charge_start_threshold {
+            snapshot.charge_start_threshold = Some(value);
+        }
+
+        if let Some(value) = self.charge_threshold_enabled {
+            snapshot.charge_threshold_enabled = Some(value);
+        }
+
+        if let Some(value) = self.charge_threshold_supported {
+            snapshot.charge_threshold_supported = Some(value);
+        }
+    }
+
+    /// The properties of `newer` which differ from those of `older`, holding the values of
+    /// `newer`.
+    pub fn between(older: &DeviceSnapshot, newer: &DeviceSnapshot) -> Self {
+        let mut change = DeviceChange::default();
+
+        macro_rules! compare {
+            ($($field:ident),*) => {
+                $(
+                    if older.$field != newer.$field {
+                        change.$field = Some(newer.$field.clone());
+                    }
+                )*
+            };
+        }
+
+        compare!(
+            battery_level,
+            capacity,
+            charge_cycles,
+            energy,
+            energy_empty,
+            energy_full,
+            energy_full_design,
+            energy_rate,
+            has_history,
+            has_statistics,
+            icon_name,
+            is_present,
+            is_rechargeable,
+            luminosity,
+            model,
+            native_path,
+            online,
+            percentage,
+            power_supply,
+            serial,
+            state,
+            technology,
+            temperature,
+            time_to_empty,
+            time_to_full,
+            type_,
+            update_time,
+            vendor,
+            voltage,
+            warning_level
+        );
+
+        if older.charge_end_threshold != newer.charge_end_threshold {
+            change.charge_end_threshold = newer.charge_end_threshold;
+        }
+
+        if older.charge_start_threshold != newer.charge_start_threshold {
+            change.charge_start_threshold = newer.charge_start_threshold;
+        }
+
+        if older.charge_threshold_enabled != newer.charge_threshold_enabled {
+            change.charge_threshold_enabled = newer.charge_threshold_enabled;
+        }
+
+        if older.charge_threshold_supported != newer.charge_threshold_supported {
+            change.charge_threshold_supported = newer.charge_threshold_supported;
+        }
+
+        change
+    }
+
+    /// The D-Bus names of the properties which changed, such as `Percentage`.
+    pub fn changed_properties(&self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+
+        macro_rules! names {
+            ($($field:ident => $name:literal),*) => {
+                $(
+                    if self.$field.is_some() {
+                        names.push($name);
+                    }
+                )*
+            };
+        }
+
+        names!(
+            battery_level => "BatteryLevel",
+            capacity => "Capacity",
+            charge_cycles => "ChargeCycles",
+            charge_end_threshold => "ChargeEndThreshold",
+            charge_start_threshold => "ChargeStartThreshold",
+            charge_threshold_enabled => "ChargeThresholdEnabled",
+            charge_threshold_supported => "ChargeThresholdSupported",
+            energy => "Energy",
+            energy_empty => "EnergyEmpty",
+            energy_full => "EnergyFull",
+            energy_full_design => "EnergyFullDesign",
+            energy_rate => "EnergyRate",
+            has_history => "HasHistory",
+            has_statistics => "HasStatistics",
+            icon_name => "IconName",
+            is_present => "IsPresent",
+            is_rechargeable => "IsRechargeable",
+            luminosity => "Luminosity",
+            model => "Model",
+            native_path => "NativePath",
+            online => "Online",
+            percentage => "Percentage",
+            power_supply => "PowerSupply",
+            serial => "Serial",
+            state => "State",
+            technology => "Technology",
+            temperature => "Temperature",
+            time_to_empty => "TimeToEmpty",
+            time_to_full => "TimeToFull",
+            type_ => "Type",
+            update_time => "UpdateTime",
+            vendor => "Vendor",
+            voltage => "Voltage",
+            warning_level => "WarningLevel"
+        );
+
+        names
+    }
+}
+
+/// The difference between two snapshots of a device, created by `DeviceSnapshot::diff`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DeviceDelta {
+    /// The properties which changed, with their newer values.
+    pub changes: DeviceChange,
+    /// The change in charge, in percentage points.
+    pub percentage: f64,
+    /// The change in stored energy.
+    pub energy: WattHours,
+    /// The older and newer state, if the state changed.
+    pub state: Option<(BatteryState, BatteryState)>,
+}
+
+impl DeviceDelta {
+    pub(crate) fn new(older: &DeviceSnapshot, newer: &DeviceSnapshot) -> Self {
+        Self {
+            changes: DeviceChange::between(older, newer),
+            percentage: newer.percentage.get() - older.percentage.get(),
+            energy: newer.energy - older.energy,
+            state: if older.state != newer.state {
+                Some((older.state, newer.state))
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Whether nothing changed besides `UpdateTime`, which changes on every reading of the
+    /// device, so that a redraw may be skipped.
+    pub fn is_empty(&self) -> bool {
+        DeviceChange {
+            update_time: None,
+            ..self.changes.clone()
+        }
+        .is_empty()
+    }
+}
+
+/// Lists the properties which changed, with the change in charge, energy and state.
+impl fmt::Display for DeviceDelta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.changes.changed_properties().join(", "))?;
+
+        if self.changes.percentage.is_some() {
+            write!(f, "; percentage {:+.1}", self.percentage)?;
+        }
+
+        if self.changes.energy.is_some() {
+            write!(f, "; energy {:+.2} Wh", self.energy.0)?;
+        }
+
+        if let Some((older, newer)) = self.state {
+            write!(f, "; state {} -> {}", older, newer)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl TryFrom<HashMap<String, OwnedValue>> for DeviceChange {
+    type Error = Error;
+
+    fn try_from(mut properties: HashMap<String, OwnedValue>) -> Result<Self> {
+        let properties = &mut properties;
+
+        Ok(DeviceChange {
+            battery_level: take_optional(properties, "BatteryLevel")?,
+            capacity: take_optional(properties, "Capacity")?,
+            charge_cycles: take_optional(properties, "ChargeCycles")?.map(cycles),
+            charge_end_threshold: take_optional(properties, "ChargeEndThreshold")?,
+            charge_start_threshold: take_optional(properties, "ChargeStartThreshold")?,
+            charge_threshold_enabled: take_optional(properties, "ChargeThresholdEnabled")?,
+            charge_threshold_supported: take_optional(properties, "ChargeThresholdSupported")?,
+            energy: take_optional(properties, "Energy")?,
+            energy_empty: take_optional(properties, "EnergyEmpty")?,
+            energy_full: take_optional(properties, "EnergyFull")?,
+            energy_full_design: take_optional(properties, "EnergyFullDesign")?,
+            energy_rate: take_optional(properties, "EnergyRate")?,
+            has_history: take_optional(properties, "HasHistory")?,
+            has_statistics: take_optional(properties, "HasStatistics")?,
+            icon_name: take_optional(properties, "IconName")?,
+            is_present: take_optional(properties, "IsPresent")?,
+            is_rechargeable: take_optional(properties, "IsRechargeable")?,
+            luminosity: take_optional(properties, "Luminosity")?,
+            model: take_optional(properties, "Model")?,
+            native_path: take_optional(properties, "NativePath")?,
+            online: take_optional(properties, "Online")?,
+            percentage: take_optional(properties, "Percentage")?,
+            power_supply: take_optional(properties, "PowerSupply")?,
+            serial: take_optional(properties, "Serial")?,
+            state: take_optional(properties, "State")?,
+            technology: take_optional(properties, "Technology")?,
+            temperature: take_optional(properties, "Temperature")?.map(temperature),
+            time_to_empty: take_optional(properties, "TimeToEmpty")?,
+            time_to_full: take_optional(properties, "TimeToFull")?,
+            type_: take_optional(properties, "Type")?,
+            update_time: take_optional(properties, "UpdateTime")?,
+            vendor: take_optional(properties, "Vendor")?,
+            voltage: take_optional(properties, "Voltage")?,
+            warning_level: take_optional(properties, "WarningLevel")?,
+        })
+    }
+}
+
+/// A stream of `DeviceChange`s, created by `DeviceProxy::receive_changes`.
+pub struct DeviceChangeStream<'a> {
+    pub(crate) inner: Pin<Box<dyn Stream<Item = Result<DeviceChange>> + Send + 'a>>,
+}
+
+impl fmt::Debug for DeviceChangeStream<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeviceChangeStream").finish_non_exhaustive()
+    }
+}
+
+impl Stream for DeviceChangeStream<'_> {
+    type Item = Result<DeviceChange>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+/// A change to the properties of one of several devices.
+type IdentifiedChange = (DeviceId, DeviceChange);
+
+/// A stream of the changes of every device, created by `UPowerProxy::receive_all_device_changes`.
+pub struct AllDeviceChangesStream<'a> {
+    pub(crate) inner: Pin<Box<dyn Stream<Item = Result<IdentifiedChange>> + Send + 'a>>,
+}
+
+impl fmt::Debug for AllDeviceChangesStream<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AllDeviceChangesStream")
+            .finish_non_exhaustive()
+    }
+}
+
+impl Stream for AllDeviceChangesStream<'_> {
+    type Item = Result<(DeviceId, DeviceChange)>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
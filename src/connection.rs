@@ -0,0 +1,23 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use async_lock::OnceCell;
+
+use crate::error::Result;
+
+static SYSTEM: OnceCell<zbus::Connection> = OnceCell::new();
+
+/// A connection to the system bus, shared by every caller in the process.
+///
+/// The connection is established on first use. Cloning a `zbus::Connection` is cheap, as
+/// clones share the same underlying socket.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug"))]
+pub async fn system_connection() -> Result<zbus::Connection> {
+    let connection = SYSTEM.get_or_try_init(zbus::Connection::system).await?;
+    Ok(connection.clone())
+}
+
+/// The blocking equivalent of [`system_connection`].
+pub fn system_connection_blocking() -> Result<zbus::blocking::Connection> {
+    zbus::block_on(system_connection()).map(zbus::blocking::Connection::from)
+}
@@ -0,0 +1,79 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::future::{self, Either};
+use futures_util::stream::{self, Stream, StreamExt};
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::error::Result;
+use crate::timer;
+
+/// Waits for `values` to be quiet for `delay` before emitting the latest of them.
+///
+/// Values equal to the one last emitted, starting with `initial`, are dropped, so that a
+/// value which bounces back within `delay` is never reported.
+pub(crate) fn debounce<'a, S, T>(
+    values: S,
+    delay: Duration,
+    initial: Option<T>,
+) -> impl Stream<Item = Result<T>> + Send + 'a
+where
+    S: Stream<Item = Result<T>> + Send + 'a,
+    T: PartialEq + Clone + Send + 'a,
+{
+    let values = Box::pin(values);
+
+    stream::unfold(
+        (values, initial),
+        move |(mut values, mut last)| async move {
+            loop {
+                let mut latest = match values.next().await? {
+                    Ok(value) => value,
+                    Err(why) => return Some((Err(why), (values, last))),
+                };
+
+                loop {
+                    let quiet = timer::sleep(delay);
+                    futures_util::pin_mut!(quiet);
+
+                    match future::select(values.next(), quiet).await {
+                        Either::Left((Some(Ok(value)), _)) => latest = value,
+                        Either::Left((Some(Err(why)), _)) => {
+                            return Some((Err(why), (values, last)))
+                        }
+                        Either::Left((None, _)) | Either::Right(_) => break,
+                    }
+                }
+
+                if last.as_ref() != Some(&latest) {
+                    last = Some(latest.clone());
+                    return Some((Ok(latest), (values, last)));
+                }
+            }
+        },
+    )
+}
+
+/// A stream of `OnBattery` values, created by
+/// `UPowerProxy::receive_on_battery_changed_debounced`.
+pub struct DebouncedOnBatteryStream<'a> {
+    pub(crate) inner: Pin<Box<dyn Stream<Item = Result<bool>> + Send + 'a>>,
+}
+
+impl fmt::Debug for DebouncedOnBatteryStream<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DebouncedOnBatteryStream")
+            .finish_non_exhaustive()
+    }
+}
+
+impl Stream for DebouncedOnBatteryStream<'_> {
+    type Item = Result<bool>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
@@ -1,46 +1,623 @@
 // Copyright 2021 System76 <info@system76.com>
 // SPDX-License-Identifier: MPL-2.0
 
-use serde_repr::{Deserialize_repr, Serialize_repr};
-use zbus::dbus_proxy;
-use zbus::zvariant::OwnedValue;
+use futures_util::future;
+use futures_util::stream::{self, Stream, StreamExt};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use zbus::fdo::PropertiesProxy;
+use zbus::names::BusName;
+use zbus::zvariant::{self, ObjectPath, OwnedValue, Signature, Type, Value};
+use zbus::{dbus_proxy, CacheProperties};
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Deserialize_repr, Serialize_repr, OwnedValue)]
-#[repr(u32)]
+use crate::blocking::BlockingIter;
+use crate::capabilities::DeviceCapabilities;
+use crate::change::{DeviceChange, DeviceChangeStream};
+use crate::error::{Error, Result};
+use crate::estimator::{TimeEstimator, TimeRemainingStream};
+use crate::icon::device_fallback_icon;
+use crate::id::DeviceId;
+use crate::level::{property_values, BatteryLevelStream, WarningLevelStream};
+use crate::names::DEVICE_INTERFACE;
+use crate::properties::{
+    cached, cached_str, changed_properties, property_pairs, PropertiesChangedStream,
+};
+use crate::snapshot::{DecodeWarning, DeviceSnapshot};
+use crate::units::{Celsius, Percentage, Volts, WattHours, Watts};
+use crate::upower::{UPowerProxy, UPowerProxyBlocking};
+use crate::ups::{UpsEventStream, UpsStatus, UpsWatcher};
+use crate::watcher::{BatteryAlertStream, BatteryWatcher};
+
+/// Implements `as_str`, `Display` and `FromStr` for an enum, using the names found in
+/// `upower --dump` output.
+macro_rules! impl_names {
+    ($name:ident { $($variant:ident => $string:literal,)* } $(Other => $other:literal)?) => {
+        impl $name {
+            /// The name used by the `upower` command-line tool.
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $($name::$variant => $string,)*
+                    $($name::Other(_) => $other,)?
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseEnumError;
+
+            fn from_str(string: &str) -> Result<Self, Self::Err> {
+                match string {
+                    $($string => Ok($name::$variant),)*
+                    _ => Err(ParseEnumError {
+                        kind: stringify!($name),
+                        value: string.to_owned(),
+                    }),
+                }
+            }
+        }
+    };
+}
+
+/// Implements the conversions of an enum from and to the `u32` sent by the daemon.
+///
+/// Values without a variant are kept in `Other` when decoding messages, so that a newer daemon
+/// does not break property reads and streams, while `TryFrom<u32>` rejects them.
+macro_rules! impl_raw {
+    ($name:ident { $($value:literal => $variant:ident,)* }) => {
+        impl $name {
+            /// Converts a value sent by the daemon, keeping unrecognized values in `Other`.
+            pub fn from_raw(value: u32) -> Self {
+                match value {
+                    $($value => $name::$variant,)*
+                    value => $name::Other(value),
+                }
+            }
+        }
+
+        impl TryFrom<u32> for $name {
+            type Error = Error;
+
+            /// Fails with `Error::InvalidEnumValue` if the value has no variant.
+            fn try_from(value: u32) -> Result<Self> {
+                match $name::from_raw(value) {
+                    $name::Other(value) => Err(Error::InvalidEnumValue(value)),
+                    known => Ok(known),
+                }
+            }
+        }
+
+        impl From<$name> for u32 {
+            fn from(value: $name) -> u32 {
+                match value {
+                    $($name::$variant => $value,)*
+                    $name::Other(value) => value,
+                }
+            }
+        }
+
+        impl Type for $name {
+            fn signature() -> Signature<'static> {
+                u32::signature()
+            }
+        }
+
+        impl TryFrom<OwnedValue> for $name {
+            type Error = zvariant::Error;
+
+            fn try_from(value: OwnedValue) -> Result<Self, Self::Error> {
+                u32::try_from(value).map($name::from_raw)
+            }
+        }
+
+        impl<'a> TryFrom<Value<'a>> for $name {
+            type Error = zvariant::Error;
+
+            fn try_from(value: Value<'a>) -> Result<Self, Self::Error> {
+                u32::try_from(value).map($name::from_raw)
+            }
+        }
+
+        impl From<$name> for OwnedValue {
+            fn from(value: $name) -> OwnedValue {
+                OwnedValue::from(u32::from(value))
+            }
+        }
+
+        impl From<$name> for Value<'_> {
+            fn from(value: $name) -> Self {
+                Value::U32(u32::from(value))
+            }
+        }
+    };
+}
+
+/// Implements `Serialize` and `Deserialize` for an enum of `impl_raw`, using the names of
+/// `impl_names`.
+///
+/// Values kept in `Other` are written as their number, and numbers are read as by `from_raw`,
+/// so that no value is lost in a round trip.
+#[cfg(feature = "serde")]
+macro_rules! impl_serde {
+    ($name:ident) => {
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                match *self {
+                    $name::Other(value) => serializer.serialize_u32(value),
+                    known => serializer.serialize_str(known.as_str()),
+                }
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct Visitor;
+
+                impl serde::de::Visitor<'_> for Visitor {
+                    type Value = $name;
+
+                    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                        f.write_str(concat!("a ", stringify!($name), " name or number"))
+                    }
+
+                    fn visit_str<E: serde::de::Error>(self, name: &str) -> Result<$name, E> {
+                        name.parse().map_err(E::custom)
+                    }
+
+                    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<$name, E> {
+                        u32::try_from(value)
+                            .map($name::from_raw)
+                            .map_err(|_| E::custom(concat!("invalid ", stringify!($name))))
+                    }
+
+                    fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<$name, E> {
+                        u32::try_from(value)
+                            .map($name::from_raw)
+                            .map_err(|_| E::custom(concat!("invalid ", stringify!($name))))
+                    }
+                }
+
+                deserializer.deserialize_any(Visitor)
+            }
+        }
+    };
+}
+
+/// Implements the conversions of an enum from and to the string sent by the daemon, using the
+/// names of `impl_names`.
+macro_rules! impl_string_value {
+    ($name:ident) => {
+        impl TryFrom<OwnedValue> for $name {
+            type Error = zvariant::Error;
+
+            fn try_from(value: OwnedValue) -> Result<Self, Self::Error> {
+                $name::try_from(Value::from(value))
+            }
+        }
+
+        impl<'a> TryFrom<Value<'a>> for $name {
+            type Error = zvariant::Error;
+
+            fn try_from(value: Value<'a>) -> Result<Self, Self::Error> {
+                let name = <&str>::try_from(&value)?;
+                name.parse()
+                    .map_err(|why: ParseEnumError| zvariant::Error::Message(why.to_string()))
+            }
+        }
+
+        impl From<$name> for Value<'_> {
+            fn from(value: $name) -> Self {
+                Value::from(value.as_str())
+            }
+        }
+
+        impl From<$name> for OwnedValue {
+            fn from(value: $name) -> OwnedValue {
+                OwnedValue::from(Value::from(value))
+            }
+        }
+    };
+}
+
+/// The error returned when parsing an unrecognized enum name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseEnumError {
+    kind: &'static str,
+    value: String,
+}
+
+impl fmt::Display for ParseEnumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {}: {:?}", self.kind, self.value)
+    }
+}
+
+impl std::error::Error for ParseEnumError {}
+
+/// The charging state of a device, as reported by the `State` property.
+///
+/// Like the other enums of the daemon, it implements `zvariant::Type` and the conversions
+/// from and to `Value` and `OwnedValue`, so it can be used directly in property maps. With the
+/// `serde` feature, it is serialized by the name returned by `as_str`.
+///
+/// ```
+/// use std::convert::TryFrom;
+/// use upower_dbus::zvariant::{OwnedValue, Value};
+/// use upower_dbus::BatteryState;
+///
+/// let value = OwnedValue::from(Value::U32(1));
+/// assert_eq!(BatteryState::try_from(value).unwrap(), BatteryState::Charging);
+/// assert_eq!(Value::from(BatteryState::Discharging), Value::U32(2));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum BatteryState {
-    Unknown = 0,
-    Charging = 1,
-    Discharging = 2,
-    Empty = 3,
-    FullyCharged = 4,
-    PendingCharge = 5,
-    PendingDischarge = 6,
-}
-
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Deserialize_repr, Serialize_repr, OwnedValue)]
-#[repr(u32)]
-pub enum BatteryType {
-    Unknown = 0,
-    LinePower = 1,
-    Battery = 2,
-    Ups = 3,
-    Monitor = 4,
-    Mouse = 5,
-    Keyboard = 6,
-    Pda = 7,
-    Phone = 8,
-}
-
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Deserialize_repr, Serialize_repr, OwnedValue)]
-#[repr(u32)]
+    Unknown,
+    Charging,
+    Discharging,
+    Empty,
+    FullyCharged,
+    PendingCharge,
+    PendingDischarge,
+    /// A value added by a newer daemon, which this version does not recognize.
+    Other(u32),
+}
+
+impl_raw!(BatteryState {
+    0 => Unknown,
+    1 => Charging,
+    2 => Discharging,
+    3 => Empty,
+    4 => FullyCharged,
+    5 => PendingCharge,
+    6 => PendingDischarge,
+});
+
+impl_names!(BatteryState {
+    Unknown => "unknown",
+    Charging => "charging",
+    Discharging => "discharging",
+    Empty => "empty",
+    FullyCharged => "fully-charged",
+    PendingCharge => "pending-charge",
+    PendingDischarge => "pending-discharge",
+} Other => "unknown");
+
+#[cfg(feature = "serde")]
+impl_serde!(BatteryState);
+
+/// The kind of a device, as reported by the `Type` property.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DeviceType {
+    Unknown,
+    LinePower,
+    Battery,
+    Ups,
+    Monitor,
+    Mouse,
+    Keyboard,
+    Pda,
+    Phone,
+    MediaPlayer,
+    Tablet,
+    Computer,
+    GamingInput,
+    Pen,
+    Touchpad,
+    Modem,
+    Network,
+    Headset,
+    Speakers,
+    Headphones,
+    Video,
+    OtherAudio,
+    RemoteControl,
+    Printer,
+    Scanner,
+    Camera,
+    Wearable,
+    Toy,
+    BluetoothGeneric,
+    /// A value added by a newer daemon, which this version does not recognize.
+    Other(u32),
+}
+
+impl_raw!(DeviceType {
+    0 => Unknown,
+    1 => LinePower,
+    2 => Battery,
+    3 => Ups,
+    4 => Monitor,
+    5 => Mouse,
+    6 => Keyboard,
+    7 => Pda,
+    8 => Phone,
+    9 => MediaPlayer,
+    10 => Tablet,
+    11 => Computer,
+    12 => GamingInput,
+    13 => Pen,
+    14 => Touchpad,
+    15 => Modem,
+    16 => Network,
+    17 => Headset,
+    18 => Speakers,
+    19 => Headphones,
+    20 => Video,
+    21 => OtherAudio,
+    22 => RemoteControl,
+    23 => Printer,
+    24 => Scanner,
+    25 => Camera,
+    26 => Wearable,
+    27 => Toy,
+    28 => BluetoothGeneric,
+});
+
+impl_names!(DeviceType {
+    Unknown => "unknown",
+    LinePower => "line-power",
+    Battery => "battery",
+    Ups => "ups",
+    Monitor => "monitor",
+    Mouse => "mouse",
+    Keyboard => "keyboard",
+    Pda => "pda",
+    Phone => "phone",
+    MediaPlayer => "media-player",
+    Tablet => "tablet",
+    Computer => "computer",
+    GamingInput => "gaming-input",
+    Pen => "pen",
+    Touchpad => "touchpad",
+    Modem => "modem",
+    Network => "network",
+    Headset => "headset",
+    Speakers => "speakers",
+    Headphones => "headphones",
+    Video => "video",
+    OtherAudio => "other-audio",
+    RemoteControl => "remote-control",
+    Printer => "printer",
+    Scanner => "scanner",
+    Camera => "camera",
+    Wearable => "wearable",
+    Toy => "toy",
+    BluetoothGeneric => "bluetooth-generic",
+} Other => "unknown");
+
+#[cfg(feature = "serde")]
+impl_serde!(DeviceType);
+
+#[deprecated(note = "renamed to `DeviceType`")]
+pub type BatteryType = DeviceType;
+
+/// The warning level of a device, as reported by the `WarningLevel` property.
+///
+/// Variants are ordered by severity, so levels may be compared directly:
+/// `level >= WarningLevel::Low`. Unrecognized levels are ordered by their value, above
+/// `Action`. An `Other` holding the value of a known level, which `from_raw` never returns,
+/// is ordered just after that level, so that `cmp` agrees with `==`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum WarningLevel {
+    Unknown,
+    None,
+    Discharging,
+    Low,
+    Critical,
+    Action,
+    /// A value added by a newer daemon, which this version does not recognize.
+    Other(u32),
+}
+
+impl_raw!(WarningLevel {
+    0 => Unknown,
+    1 => None,
+    2 => Discharging,
+    3 => Low,
+    4 => Critical,
+    5 => Action,
+});
+
+impl PartialOrd for WarningLevel {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for WarningLevel {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let is_other = |level: &Self| matches!(level, WarningLevel::Other(_));
+        u32::from(*self)
+            .cmp(&u32::from(*other))
+            .then_with(|| is_other(self).cmp(&is_other(other)))
+    }
+}
+
+impl_names!(WarningLevel {
+    Unknown => "unknown",
+    None => "none",
+    Discharging => "discharging",
+    Low => "low",
+    Critical => "critical",
+    Action => "action",
+} Other => "unknown");
+
+#[cfg(feature = "serde")]
+impl_serde!(WarningLevel);
+
+/// The chemistry of a battery, as reported by the `Technology` property.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Technology {
+    Unknown,
+    LithiumIon,
+    LithiumPolymer,
+    LithiumIronPhosphate,
+    LeadAcid,
+    NickelCadmium,
+    NickelMetalHydride,
+    /// A value added by a newer daemon, which this version does not recognize.
+    Other(u32),
+}
+
+impl_raw!(Technology {
+    0 => Unknown,
+    1 => LithiumIon,
+    2 => LithiumPolymer,
+    3 => LithiumIronPhosphate,
+    4 => LeadAcid,
+    5 => NickelCadmium,
+    6 => NickelMetalHydride,
+});
+
+impl_names!(Technology {
+    Unknown => "unknown",
+    LithiumIon => "lithium-ion",
+    LithiumPolymer => "lithium-polymer",
+    LithiumIronPhosphate => "lithium-iron-phosphate",
+    LeadAcid => "lead-acid",
+    NickelCadmium => "nickel-cadmium",
+    NickelMetalHydride => "nickel-metal-hydride",
+} Other => "unknown");
+
+#[cfg(feature = "serde")]
+impl_serde!(Technology);
+
+/// The coarse battery level of a device, as reported by the `BatteryLevel` property.
+///
+/// Devices which only report a few discrete levels, such as wireless peripherals, use this
+/// instead of a precise percentage. Devices which do report a percentage use `None`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum BatteryLevel {
-    Unknown = 0,
-    None = 1,
-    Low = 3,
-    Critical = 4,
-    Normal = 6,
-    High = 7,
-    Full = 8,
+    Unknown,
+    None,
+    Low,
+    Critical,
+    Normal,
+    High,
+    Full,
+    /// A value added by a newer daemon, which this version does not recognize.
+    Other(u32),
+}
+
+impl_raw!(BatteryLevel {
+    0 => Unknown,
+    1 => None,
+    3 => Low,
+    4 => Critical,
+    6 => Normal,
+    7 => High,
+    8 => Full,
+});
+
+impl_names!(BatteryLevel {
+    Unknown => "unknown",
+    None => "none",
+    Low => "low",
+    Critical => "critical",
+    Normal => "normal",
+    High => "high",
+    Full => "full",
+} Other => "unknown");
+
+#[cfg(feature = "serde")]
+impl_serde!(BatteryLevel);
+
+/// The kind of history requested from `DeviceProxy::get_history`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum HistoryKind {
+    /// The energy rate, in W.
+    Rate,
+    /// The charge level, in percent.
+    Charge,
+}
+
+impl_names!(HistoryKind {
+    Rate => "rate",
+    Charge => "charge",
+});
+
+impl_string_value!(HistoryKind);
+
+/// A single entry returned by `DeviceProxy::get_history`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HistoryRecord {
+    /// UNIX timestamp, in seconds.
+    pub time: u32,
+    /// The value of the requested `HistoryKind`.
+    pub value: f64,
+    /// The state of the device at the time of the record.
+    pub state: BatteryState,
+}
+
+impl HistoryRecord {
+    /// The time of the record.
+    pub fn system_time(&self) -> SystemTime {
+        timestamp(u64::from(self.time))
+    }
+
+    /// The time of the record, as a `chrono` datetime.
+    #[cfg(feature = "chrono")]
+    pub fn time_utc(&self) -> chrono::DateTime<chrono::Utc> {
+        self.system_time().into()
+    }
+
+    /// The time of the record, as a `time` datetime in UTC.
+    #[cfg(feature = "time")]
+    pub fn time_offset(&self) -> time::OffsetDateTime {
+        self.system_time().into()
+    }
+
+    fn from_message((time, value, state): (u32, f64, u32)) -> Self {
+        Self {
+            time,
+            value,
+            state: BatteryState::from_raw(state),
+        }
+    }
+}
+
+/// The kind of statistics requested from `DeviceProxy::get_statistics`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum StatisticsKind {
+    Charging,
+    Discharging,
+}
+
+impl_names!(StatisticsKind {
+    Charging => "charging",
+    Discharging => "discharging",
+});
+
+impl_string_value!(StatisticsKind);
+
+/// A single entry returned by `DeviceProxy::get_statistics`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StatisticsRecord {
+    /// The value of the percentage point, usually in seconds.
+    pub value: f64,
+    /// The accuracy of the prediction, in percent.
+    pub accuracy: f64,
+}
+
+impl StatisticsRecord {
+    fn from_message((value, accuracy): (f64, f64)) -> Self {
+        Self { value, accuracy }
+    }
 }
 
 #[dbus_proxy(
@@ -49,45 +626,115 @@ pub enum BatteryLevel {
     assume_defaults = false
 )]
 trait Device {
+    /// BatteryLevel property
     #[dbus_proxy(property)]
     fn battery_level(&self) -> zbus::Result<BatteryLevel>;
 
+    /// Capacity property
+    ///
+    /// The capacity of the battery relative to its design capacity, in percent. Unlike
+    /// `battery_health_percent`, this is computed by the daemon.
     #[dbus_proxy(property)]
     fn capacity(&self) -> zbus::Result<f64>;
 
+    /// ChargeCycles property
+    ///
+    /// The number of charge cycles, or -1 if unknown. See `charge_cycles` for a typed accessor.
+    #[dbus_proxy(property, name = "ChargeCycles")]
+    fn charge_cycles_raw(&self) -> zbus::Result<i32>;
+
+    /// ChargeEndThreshold property
+    ///
+    /// The percentage at which the battery stops charging when thresholds are enabled.
+    #[dbus_proxy(property)]
+    fn charge_end_threshold(&self) -> zbus::Result<u32>;
+
+    /// ChargeStartThreshold property
+    ///
+    /// The percentage below which the battery starts charging when thresholds are enabled.
+    #[dbus_proxy(property)]
+    fn charge_start_threshold(&self) -> zbus::Result<u32>;
+
+    /// ChargeThresholdEnabled property
+    #[dbus_proxy(property)]
+    fn charge_threshold_enabled(&self) -> zbus::Result<bool>;
+
+    /// ChargeThresholdSupported property
+    #[dbus_proxy(property)]
+    fn charge_threshold_supported(&self) -> zbus::Result<bool>;
+
+    /// EnableChargeThreshold method
+    ///
+    /// Requires UPower 1.90 or newer, and a device whose `charge_threshold_supported` is `true`.
+    fn enable_charge_threshold(&self, enabled: bool) -> zbus::Result<()>;
+
+    #[dbus_proxy(property)]
+    fn energy(&self) -> zbus::Result<WattHours>;
+
     #[dbus_proxy(property)]
-    fn energy(&self) -> zbus::Result<f64>;
+    fn energy_empty(&self) -> zbus::Result<WattHours>;
 
+    /// EnergyFull property
     #[dbus_proxy(property)]
-    fn energy_empty(&self) -> zbus::Result<f64>;
+    fn energy_full(&self) -> zbus::Result<WattHours>;
 
+    /// EnergyFullDesign property
     #[dbus_proxy(property)]
-    fn energy_full(&self) -> zbus::Result<f64>;
+    fn energy_full_design(&self) -> zbus::Result<WattHours>;
 
+    /// EnergyRate property
+    ///
+    /// Positive both while charging and discharging, see `power_flow` for a signed rate.
     #[dbus_proxy(property)]
-    fn energy_full_design(&self) -> zbus::Result<f64>;
+    fn energy_rate(&self) -> zbus::Result<Watts>;
 
+    /// HasHistory property
+    ///
+    /// Whether `get_history` is supported by the device.
     #[dbus_proxy(property)]
     fn has_history(&self) -> zbus::Result<bool>;
 
+    /// HasStatistics property
+    ///
+    /// Whether `get_statistics` is supported by the device.
     #[dbus_proxy(property)]
     fn has_statistics(&self) -> zbus::Result<bool>;
 
+    /// IconName property
+    ///
+    /// May be empty on some hardware. See `icon_name_or_fallback`.
     #[dbus_proxy(property)]
     fn icon_name(&self) -> zbus::Result<String>;
 
+    /// IsPresent property
+    ///
+    /// Whether a battery is inserted. Empty battery bays on multi-bay laptops report `false`.
     #[dbus_proxy(property)]
     fn is_present(&self) -> zbus::Result<bool>;
 
+    /// IsRechargeable property
+    ///
+    /// Whether the battery can be recharged.
     #[dbus_proxy(property)]
     fn is_rechargeable(&self) -> zbus::Result<bool>;
 
+    /// Luminosity property
+    ///
+    /// The ambient light level reported by light sensors, in lux. Changes may be watched with
+    /// `receive_luminosity_changed`.
     #[dbus_proxy(property)]
     fn luminosity(&self) -> zbus::Result<f64>;
 
+    /// Model property
+    ///
+    /// Empty if the hardware does not report it.
     #[dbus_proxy(property)]
     fn model(&self) -> zbus::Result<String>;
 
+    /// NativePath property
+    ///
+    /// The sysfs name or path of the device, such as `BAT0`. See
+    /// `UPowerProxy::device_by_native_path` for the reverse lookup.
     #[dbus_proxy(property)]
     fn native_path(&self) -> zbus::Result<String>;
 
@@ -95,28 +742,897 @@ trait Device {
     fn online(&self) -> zbus::Result<bool>;
 
     #[dbus_proxy(property)]
-    fn percentage(&self) -> zbus::Result<f64>;
+    fn percentage(&self) -> zbus::Result<Percentage>;
 
+    /// PowerSupply property
+    ///
+    /// Whether the device powers the system, as opposed to a peripheral with its own battery.
     #[dbus_proxy(property)]
     fn power_supply(&self) -> zbus::Result<bool>;
 
+    /// Refresh method
+    ///
+    /// Asks the daemon to poll the device instead of waiting for its next scheduled update.
     fn refresh(&self) -> zbus::Result<()>;
 
+    /// Serial property
+    ///
+    /// Empty if the hardware does not report it.
     #[dbus_proxy(property)]
     fn serial(&self) -> zbus::Result<String>;
 
+    /// State property
     #[dbus_proxy(property)]
     fn state(&self) -> zbus::Result<BatteryState>;
 
+    /// Temperature property
+    ///
+    /// The temperature of the device, or 0 if unknown. See `temperature` for a typed accessor.
+    #[dbus_proxy(property, name = "Temperature")]
+    fn temperature_raw(&self) -> zbus::Result<f64>;
+
+    /// Technology property
+    #[dbus_proxy(property)]
+    fn technology(&self) -> zbus::Result<Technology>;
+
+    /// TimeToEmpty property
+    ///
+    /// The estimated time until the device is empty, in seconds, or 0 if unknown.
     #[dbus_proxy(property)]
-    fn temperature(&self) -> zbus::Result<f64>;
+    fn time_to_empty(&self) -> zbus::Result<i64>;
 
+    /// TimeToFull property
+    ///
+    /// The estimated time until the device is fully charged, in seconds, or 0 if unknown.
+    #[dbus_proxy(property)]
+    fn time_to_full(&self) -> zbus::Result<i64>;
+
+    /// Type property
     #[dbus_proxy(property, name = "Type")]
-    fn type_(&self) -> zbus::Result<BatteryType>;
+    fn type_(&self) -> zbus::Result<DeviceType>;
+
+    /// UpdateTime property
+    ///
+    /// The UNIX timestamp, in seconds, of when the daemon last read the device.
+    #[dbus_proxy(property)]
+    fn update_time(&self) -> zbus::Result<u64>;
 
+    /// Vendor property
+    ///
+    /// Empty if the hardware does not report it.
     #[dbus_proxy(property)]
     fn vendor(&self) -> zbus::Result<String>;
 
+    /// Voltage property
     #[dbus_proxy(property)]
-    fn voltage(&self) -> zbus::Result<f64>;
+    fn voltage(&self) -> zbus::Result<Volts>;
+
+    /// WarningLevel property
+    #[dbus_proxy(property)]
+    fn warning_level(&self) -> zbus::Result<WarningLevel>;
+}
+
+/// Computes the wear level of a battery from its full and design energy, in percent.
+pub(crate) fn battery_health(energy_full: WattHours, energy_full_design: WattHours) -> Option<f64> {
+    if energy_full_design.0 > 0.0 {
+        Some(energy_full.0 / energy_full_design.0 * 100.0)
+    } else {
+        None
+    }
+}
+
+/// Converts a time estimate in seconds to a `Duration`, where 0 or less means unknown.
+pub(crate) fn estimate(seconds: i64) -> Option<Duration> {
+    if seconds > 0 {
+        Some(Duration::from_secs(seconds as u64))
+    } else {
+        None
+    }
+}
+
+/// Converts a UNIX timestamp in seconds to a `SystemTime`.
+pub(crate) fn timestamp(seconds: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(seconds)
+}
+
+/// Converts a charge cycle count, where -1 means unknown, to an `Option`.
+pub(crate) fn cycles(count: i32) -> Option<i32> {
+    if count >= 0 {
+        Some(count)
+    } else {
+        None
+    }
+}
+
+/// Converts a temperature, where 0 means unreported, to an `Option`.
+pub(crate) fn temperature(celsius: f64) -> Option<Celsius> {
+    if celsius != 0.0 {
+        Some(Celsius(celsius))
+    } else {
+        None
+    }
+}
+
+/// Signs the unsigned `EnergyRate` by the direction of the flow: positive into the battery
+/// while charging, negative out of it while discharging, and zero in any other state.
+pub(crate) fn power_flow(energy_rate: Watts, state: BatteryState) -> Watts {
+    match state {
+        BatteryState::Charging => energy_rate,
+        BatteryState::Discharging => -energy_rate,
+        _ => Watts(0.0),
+    }
+}
+
+/// Whether a device is a battery powering the system, rather than that of a peripheral.
+///
+/// Peripherals may report `DeviceType::Battery` too, such as some wireless keyboards, but they
+/// neither supply the system nor are named after the `BAT` nodes of the kernel.
+pub(crate) fn is_system_battery(type_: DeviceType, power_supply: bool, native_path: &str) -> bool {
+    type_ == DeviceType::Battery && power_supply && native_path.starts_with("BAT")
+}
+
+/// `is_system_battery`, from the properties in the cache of `proxy` if all of them are there.
+fn cached_is_system_battery(proxy: &zbus::Proxy<'_>) -> Option<bool> {
+    let type_ = cached(proxy, "Type")?;
+    let power_supply = cached(proxy, "PowerSupply")?;
+    cached_str(proxy.cached_property_raw("NativePath"), |native_path| {
+        is_system_battery(type_, power_supply, native_path)
+    })
+}
+
+impl DeviceProxy<'static> {
+    /// Creates a proxy for the display device, on the shared system bus connection from
+    /// [`system_connection`](crate::system_connection).
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug"))]
+    pub async fn display() -> Result<Self> {
+        UPowerProxy::system().await?.display_device().await
+    }
+}
+
+impl<'a> DeviceProxy<'a> {
+    /// Creates a proxy for the device at `path` which reads properties from the daemon on
+    /// every call, rather than caching them.
+    ///
+    /// Useful right after `refresh`, when cached values may be stale. The
+    /// `receive_*_changed` streams are unavailable on such a proxy.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub async fn new_uncached<P>(connection: &zbus::Connection, path: P) -> Result<Self>
+    where
+        P: TryInto<ObjectPath<'a>>,
+        P::Error: Into<zbus::Error>,
+    {
+        let proxy = Self::builder(connection)
+            .path(path)?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+        Ok(proxy)
+    }
+
+    /// A builder for the device at `path`, served by `destination` rather than
+    /// `org.freedesktop.UPower`.
+    ///
+    /// Useful against a private bus or a renamed service, such as in tests and sandboxes.
+    pub fn builder_for<D, P>(
+        connection: &zbus::Connection,
+        destination: D,
+        path: P,
+    ) -> Result<zbus::ProxyBuilder<'a, Self>>
+    where
+        D: TryInto<BusName<'a>>,
+        D::Error: Into<zbus::Error>,
+        P: TryInto<ObjectPath<'a>>,
+        P::Error: Into<zbus::Error>,
+    {
+        Ok(Self::builder(connection)
+            .destination(destination)?
+            .path(path)?)
+    }
+
+    /// The identity of the device, which compares equal for every proxy of the same device.
+    pub fn id(&self) -> DeviceId {
+        DeviceId::from(self.path().to_owned())
+    }
+
+    /// Watches the time until the device is empty or full, smoothed by `estimator`.
+    ///
+    /// An estimate is emitted for the current state of the device, and after every change to
+    /// its properties, whenever one can be made.
+    pub async fn receive_time_remaining(
+        &self,
+        mut estimator: TimeEstimator,
+    ) -> Result<TimeRemainingStream<'a>> {
+        let estimates = self.snapshots().await?.filter_map(move |snapshot| {
+            future::ready(match snapshot {
+                Ok(snapshot) => estimator.update(&snapshot).map(Ok),
+                Err(why) => Some(Err(why)),
+            })
+        });
+
+        Ok(TimeRemainingStream {
+            inner: Box::pin(estimates),
+        })
+    }
+
+    /// Watches the charge of the device for readings crossing the thresholds of `watcher`.
+    pub async fn receive_battery_alerts(
+        &self,
+        mut watcher: BatteryWatcher,
+    ) -> Result<BatteryAlertStream<'a>> {
+        let alerts = self
+            .snapshots()
+            .await?
+            .map(move |snapshot| match snapshot {
+                Ok(snapshot) => watcher.update(&snapshot).into_iter().map(Ok).collect(),
+                Err(why) => vec![Err(why)],
+            })
+            .flat_map(stream::iter);
+
+        Ok(BatteryAlertStream {
+            inner: Box::pin(alerts),
+        })
+    }
+
+    /// Watches a UPS for switches between line and battery power, and for its runtime running
+    /// low, as configured by `watcher`.
+    pub async fn receive_ups_events(&self, mut watcher: UpsWatcher) -> Result<UpsEventStream<'a>> {
+        let events = self
+            .snapshots()
+            .await?
+            .map(move |snapshot| match snapshot {
+                Ok(snapshot) => watcher.update(&snapshot).into_iter().map(Ok).collect(),
+                Err(why) => vec![Err(why)],
+            })
+            .flat_map(stream::iter);
+
+        Ok(UpsEventStream {
+            inner: Box::pin(events),
+        })
+    }
+
+    /// Watches the properties of the device through a single stream, with each item holding
+    /// the values which changed in one `PropertiesChanged` signal.
+    pub async fn receive_changes(&self) -> Result<DeviceChangeStream<'a>> {
+        let changes = changed_properties(
+            self.connection(),
+            self.destination(),
+            self.path(),
+            self.interface(),
+        )
+        .await?
+        .filter_map(|properties| {
+            future::ready(match properties.and_then(DeviceChange::try_from) {
+                Ok(change) if change.is_empty() => None,
+                change => Some(change),
+            })
+        });
+
+        Ok(DeviceChangeStream {
+            inner: Box::pin(changes),
+        })
+    }
+
+    /// Watches the properties of the device through a single subscription, yielding the name
+    /// and new value of each property as it changes, for dispatching by hand.
+    ///
+    /// Values are as sent by the daemon, see `receive_changes` for decoded values.
+    pub async fn receive_properties_changed(&self) -> Result<PropertiesChangedStream<'a>> {
+        let changes = changed_properties(
+            self.connection(),
+            self.destination(),
+            self.path(),
+            self.interface(),
+        )
+        .await?;
+
+        Ok(property_pairs(changes))
+    }
+
+    /// Like `receive_warning_level_changed`, but yields the `WarningLevel` itself, starting
+    /// with the current level and skipping repeats of the previous one.
+    ///
+    /// As with every property stream, a listener which falls behind only sees the latest level.
+    pub async fn watch_warning_level(&self) -> Result<WarningLevelStream<'a>> {
+        let changes = self.receive_warning_level_changed().await;
+        let initial = self.warning_level().await?;
+
+        Ok(WarningLevelStream {
+            inner: Box::pin(property_values(initial, changes)),
+        })
+    }
+
+    /// Like `receive_battery_level_changed`, but yields the `BatteryLevel` itself, starting
+    /// with the current level and skipping repeats of the previous one.
+    ///
+    /// Peripherals such as mice and headsets which only report a coarse level use this instead
+    /// of `Percentage`. The level is `BatteryLevel::None` for devices which report a percentage.
+    pub async fn watch_battery_level(&self) -> Result<BatteryLevelStream<'a>> {
+        let changes = self.receive_battery_level_changed().await;
+        let initial = self.battery_level().await?;
+
+        Ok(BatteryLevelStream {
+            inner: Box::pin(property_values(initial, changes)),
+        })
+    }
+
+    /// A snapshot of the device now, and after every change to its properties.
+    async fn snapshots(&self) -> Result<impl Stream<Item = Result<DeviceSnapshot>> + Send + 'a> {
+        let changes = PropertiesProxy::builder(self.connection())
+            .destination(self.destination().to_owned())?
+            .path(self.path().to_owned())?
+            .build()
+            .await?
+            .receive_properties_changed()
+            .await?;
+
+        let device = self.clone();
+        Ok(stream::once(future::ready(()))
+            .chain(changes.map(drop))
+            .then(move |()| {
+                let device = device.clone();
+                async move { device.snapshot().await }
+            }))
+    }
+
+    /// GetHistory method
+    ///
+    /// Returns up to `resolution` records covering the last `timespan` seconds.
+    pub async fn get_history(
+        &self,
+        kind: impl Into<HistoryKind>,
+        timespan: u32,
+        resolution: u32,
+    ) -> zbus::Result<Vec<HistoryRecord>> {
+        let kind = kind.into();
+        let records: Vec<(u32, f64, u32)> = self
+            .inner()
+            .call("GetHistory", &(kind.as_str(), timespan, resolution))
+            .await?;
+
+        Ok(records
+            .into_iter()
+            .map(HistoryRecord::from_message)
+            .collect())
+    }
+
+    /// GetStatistics method
+    pub async fn get_statistics(
+        &self,
+        kind: impl Into<StatisticsKind>,
+    ) -> zbus::Result<Vec<StatisticsRecord>> {
+        let kind = kind.into();
+        let records: Vec<(f64, f64)> = self
+            .inner()
+            .call("GetStatistics", &(kind.as_str(),))
+            .await?;
+
+        Ok(records
+            .into_iter()
+            .map(StatisticsRecord::from_message)
+            .collect())
+    }
+
+    /// Like `get_history`, but fails with `Error::UnsupportedProperty` if the device has no
+    /// history.
+    pub async fn history(
+        &self,
+        kind: impl Into<HistoryKind>,
+        timespan: u32,
+        resolution: u32,
+    ) -> Result<Vec<HistoryRecord>> {
+        if !self.has_history().await? {
+            return Err(Error::UnsupportedProperty);
+        }
+
+        Ok(self.get_history(kind, timespan, resolution).await?)
+    }
+
+    /// Like `get_statistics`, but fails with `Error::UnsupportedProperty` if the device has no
+    /// statistics.
+    pub async fn statistics(
+        &self,
+        kind: impl Into<StatisticsKind>,
+    ) -> Result<Vec<StatisticsRecord>> {
+        if !self.has_statistics().await? {
+            return Err(Error::UnsupportedProperty);
+        }
+
+        Ok(self.get_statistics(kind).await?)
+    }
+
+    /// Whether the device is an uninterruptible power supply.
+    pub async fn is_ups(&self) -> Result<bool> {
+        Ok(self.type_().await? == DeviceType::Ups)
+    }
+
+    /// The rate of energy flowing into the battery, which is negative while discharging, and
+    /// zero unless charging or discharging.
+    pub async fn power_flow(&self) -> Result<Watts> {
+        let (energy_rate, state) = future::try_join(self.energy_rate(), self.state()).await?;
+        Ok(power_flow(energy_rate, state))
+    }
+
+    /// Whether the device is a battery of the system, such as that of a laptop, rather than
+    /// a battery of a peripheral.
+    ///
+    /// This is a heuristic: the device must be a `DeviceType::Battery` which supplies the
+    /// system, with a `NativePath` starting with `BAT`.
+    pub async fn is_system_battery(&self) -> Result<bool> {
+        // Status bars poll this, so the properties are read in place when cached.
+        if let Some(is_system) = cached_is_system_battery(self.inner()) {
+            return Ok(is_system);
+        }
+
+        let (type_, power_supply) = future::try_join(self.type_(), self.power_supply()).await?;
+        Ok(is_system_battery(
+            type_,
+            power_supply,
+            &self.native_path().await?,
+        ))
+    }
+
+    /// The properties of the device which matter for monitoring a UPS.
+    pub async fn ups_status(&self) -> Result<UpsStatus> {
+        Ok(UpsStatus::from(&self.snapshot().await?))
+    }
+
+    /// The number of charge cycles of the battery, if known.
+    pub async fn charge_cycles(&self) -> Result<Option<i32>> {
+        Ok(cycles(self.charge_cycles_raw().await?))
+    }
+
+    /// The current full energy of the battery relative to its design energy, in percent.
+    ///
+    /// Returns `None` if the device does not report a design energy.
+    pub async fn battery_health_percent(&self) -> Result<Option<f64>> {
+        let (full, design) =
+            future::try_join(self.energy_full(), self.energy_full_design()).await?;
+        Ok(battery_health(full, design))
+    }
+
+    /// The estimated time until the device is empty, if known.
+    pub async fn time_to_empty_duration(&self) -> Result<Option<Duration>> {
+        Ok(estimate(self.time_to_empty().await?))
+    }
+
+    /// The estimated time until the device is fully charged, if known.
+    pub async fn time_to_full_duration(&self) -> Result<Option<Duration>> {
+        Ok(estimate(self.time_to_full().await?))
+    }
+
+    /// The icon name reported by the daemon, or one computed from the kind, percentage and
+    /// state if it is empty.
+    pub async fn icon_name_or_fallback(&self) -> Result<String> {
+        let icon_name = self.icon_name().await?;
+        if !icon_name.is_empty() {
+            return Ok(icon_name);
+        }
+
+        let (type_, percentage, state) =
+            future::try_join3(self.type_(), self.percentage(), self.state()).await?;
+        Ok(device_fallback_icon(type_, state, percentage))
+    }
+
+    /// The temperature of the device, if reported by the hardware.
+    pub async fn temperature(&self) -> Result<Option<Celsius>> {
+        Ok(temperature(self.temperature_raw().await?))
+    }
+
+    /// When the daemon last read the device.
+    pub async fn last_updated(&self) -> Result<SystemTime> {
+        Ok(timestamp(self.update_time().await?))
+    }
+
+    /// Fetches every property of the device with a single `GetAll` call.
+    pub async fn snapshot(&self) -> Result<DeviceSnapshot> {
+        fetch_snapshot(self.connection(), self.destination(), self.path()).await
+    }
+
+    /// Like `snapshot`, but leaves properties which fail to decode as `None`, as by
+    /// `DeviceSnapshot::decode_lenient`.
+    pub async fn snapshot_lenient(&self) -> Result<(DeviceChange, Vec<DecodeWarning>)> {
+        let properties =
+            fetch_properties(self.connection(), self.destination(), self.path()).await?;
+        Ok(DeviceSnapshot::decode_lenient(properties))
+    }
+
+    /// The optional properties which the device reports, read with a single `GetAll` call.
+    pub async fn capabilities(&self) -> Result<DeviceCapabilities> {
+        Ok(self.snapshot().await?.capabilities())
+    }
+}
+
+/// Fetches every property of the device at `path` with a single `GetAll` call, without
+/// creating a proxy.
+pub(crate) async fn fetch_snapshot(
+    connection: &zbus::Connection,
+    destination: &BusName<'_>,
+    path: &ObjectPath<'_>,
+) -> Result<DeviceSnapshot> {
+    DeviceSnapshot::try_from(fetch_properties(connection, destination, path).await?)
+}
+
+/// Reads a single property of the device at `path`, without a proxy.
+pub(crate) async fn fetch_property(
+    connection: &zbus::Connection,
+    destination: &BusName<'_>,
+    path: &ObjectPath<'_>,
+    name: &str,
+) -> Result<OwnedValue> {
+    let reply = connection
+        .call_method(
+            Some(destination),
+            path,
+            Some("org.freedesktop.DBus.Properties"),
+            "Get",
+            &(DEVICE_INTERFACE, name),
+        )
+        .await?;
+
+    Ok(reply.body()?)
+}
+
+/// Like `fetch_snapshot`, but returns the properties as they were sent by the daemon.
+#[cfg_attr(feature = "tracing", tracing::instrument(
+    level = "debug",
+    skip(connection, destination),
+    fields(path = %path.as_str())
+))]
+pub(crate) async fn fetch_properties(
+    connection: &zbus::Connection,
+    destination: &BusName<'_>,
+    path: &ObjectPath<'_>,
+) -> Result<HashMap<String, OwnedValue>> {
+    let reply = connection
+        .call_method(
+            Some(destination),
+            path,
+            Some("org.freedesktop.DBus.Properties"),
+            "GetAll",
+            &(DEVICE_INTERFACE,),
+        )
+        .await?;
+
+    Ok(reply.body()?)
+}
+
+impl DeviceProxyBlocking<'static> {
+    /// Creates a proxy for the display device, on the shared system bus connection from
+    /// [`system_connection_blocking`](crate::system_connection_blocking).
+    pub fn display() -> Result<Self> {
+        UPowerProxyBlocking::system()?.display_device()
+    }
+}
+
+impl<'a> DeviceProxyBlocking<'a> {
+    /// Creates a proxy for the device at `path` which reads properties from the daemon on
+    /// every call, rather than caching them.
+    ///
+    /// Useful right after `refresh`, when cached values may be stale. The
+    /// `receive_*_changed` streams are unavailable on such a proxy.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn new_uncached<P>(connection: &zbus::blocking::Connection, path: P) -> Result<Self>
+    where
+        P: TryInto<ObjectPath<'a>>,
+        P::Error: Into<zbus::Error>,
+    {
+        let proxy = Self::builder(connection)
+            .path(path)?
+            .cache_properties(CacheProperties::No)
+            .build()?;
+        Ok(proxy)
+    }
+
+    /// A builder for the device at `path`, served by `destination` rather than
+    /// `org.freedesktop.UPower`.
+    ///
+    /// Useful against a private bus or a renamed service, such as in tests and sandboxes.
+    pub fn builder_for<D, P>(
+        connection: &zbus::blocking::Connection,
+        destination: D,
+        path: P,
+    ) -> Result<zbus::blocking::ProxyBuilder<'a, Self>>
+    where
+        D: TryInto<BusName<'a>>,
+        D::Error: Into<zbus::Error>,
+        P: TryInto<ObjectPath<'a>>,
+        P::Error: Into<zbus::Error>,
+    {
+        Ok(Self::builder(connection)
+            .destination(destination)?
+            .path(path)?)
+    }
+
+    /// The identity of the device, which compares equal for every proxy of the same device.
+    pub fn id(&self) -> DeviceId {
+        DeviceId::from(self.path().to_owned())
+    }
+
+    /// GetHistory method
+    ///
+    /// Returns up to `resolution` records covering the last `timespan` seconds.
+    pub fn get_history(
+        &self,
+        kind: impl Into<HistoryKind>,
+        timespan: u32,
+        resolution: u32,
+    ) -> zbus::Result<Vec<HistoryRecord>> {
+        let kind = kind.into();
+        let records: Vec<(u32, f64, u32)> = self
+            .inner()
+            .call("GetHistory", &(kind.as_str(), timespan, resolution))?;
+
+        Ok(records
+            .into_iter()
+            .map(HistoryRecord::from_message)
+            .collect())
+    }
+
+    /// GetStatistics method
+    pub fn get_statistics(
+        &self,
+        kind: impl Into<StatisticsKind>,
+    ) -> zbus::Result<Vec<StatisticsRecord>> {
+        let kind = kind.into();
+        let records: Vec<(f64, f64)> = self.inner().call("GetStatistics", &(kind.as_str(),))?;
+        Ok(records
+            .into_iter()
+            .map(StatisticsRecord::from_message)
+            .collect())
+    }
+
+    /// Like `get_history`, but fails with `Error::UnsupportedProperty` if the device has no
+    /// history.
+    pub fn history(
+        &self,
+        kind: impl Into<HistoryKind>,
+        timespan: u32,
+        resolution: u32,
+    ) -> Result<Vec<HistoryRecord>> {
+        if !self.has_history()? {
+            return Err(Error::UnsupportedProperty);
+        }
+
+        Ok(self.get_history(kind, timespan, resolution)?)
+    }
+
+    /// Like `get_statistics`, but fails with `Error::UnsupportedProperty` if the device has no
+    /// statistics.
+    pub fn statistics(&self, kind: impl Into<StatisticsKind>) -> Result<Vec<StatisticsRecord>> {
+        if !self.has_statistics()? {
+            return Err(Error::UnsupportedProperty);
+        }
+
+        Ok(self.get_statistics(kind)?)
+    }
+
+    /// Whether the device is an uninterruptible power supply.
+    pub fn is_ups(&self) -> Result<bool> {
+        Ok(self.type_()? == DeviceType::Ups)
+    }
+
+    /// The rate of energy flowing into the battery, which is negative while discharging, and
+    /// zero unless charging or discharging.
+    pub fn power_flow(&self) -> Result<Watts> {
+        Ok(power_flow(self.energy_rate()?, self.state()?))
+    }
+
+    /// Whether the device is a battery of the system, such as that of a laptop, rather than
+    /// a battery of a peripheral.
+    ///
+    /// This is a heuristic: the device must be a `DeviceType::Battery` which supplies the
+    /// system, with a `NativePath` starting with `BAT`.
+    pub fn is_system_battery(&self) -> Result<bool> {
+        // Status bars poll this, so the properties are read in place when cached.
+        if let Some(is_system) = cached_is_system_battery(self.inner().inner()) {
+            return Ok(is_system);
+        }
+
+        let (type_, power_supply) = (self.type_()?, self.power_supply()?);
+        Ok(is_system_battery(type_, power_supply, &self.native_path()?))
+    }
+
+    /// The properties of the device which matter for monitoring a UPS.
+    pub fn ups_status(&self) -> Result<UpsStatus> {
+        Ok(UpsStatus::from(&self.snapshot()?))
+    }
+
+    /// The number of charge cycles of the battery, if known.
+    pub fn charge_cycles(&self) -> Result<Option<i32>> {
+        Ok(cycles(self.charge_cycles_raw()?))
+    }
+
+    /// The current full energy of the battery relative to its design energy, in percent.
+    ///
+    /// Returns `None` if the device does not report a design energy.
+    pub fn battery_health_percent(&self) -> Result<Option<f64>> {
+        Ok(battery_health(
+            self.energy_full()?,
+            self.energy_full_design()?,
+        ))
+    }
+
+    /// Fetches every property of the device with a single `GetAll` call.
+    pub fn snapshot(&self) -> Result<DeviceSnapshot> {
+        DeviceSnapshot::try_from(self.properties()?)
+    }
+
+    /// Like `snapshot`, but leaves properties which fail to decode as `None`, as by
+    /// `DeviceSnapshot::decode_lenient`.
+    pub fn snapshot_lenient(&self) -> Result<(DeviceChange, Vec<DecodeWarning>)> {
+        Ok(DeviceSnapshot::decode_lenient(self.properties()?))
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        level = "debug",
+        skip(self),
+        fields(path = %self.path().as_str())
+    ))]
+    fn properties(&self) -> Result<HashMap<String, OwnedValue>> {
+        let reply = self.connection().call_method(
+            Some(self.destination()),
+            self.path(),
+            Some("org.freedesktop.DBus.Properties"),
+            "GetAll",
+            &(self.interface(),),
+        )?;
+
+        Ok(reply.body()?)
+    }
+
+    /// The optional properties which the device reports, read with a single `GetAll` call.
+    pub fn capabilities(&self) -> Result<DeviceCapabilities> {
+        Ok(self.snapshot()?.capabilities())
+    }
+
+    /// Like `DeviceProxy::receive_time_remaining`, blocking on each estimate.
+    pub fn receive_time_remaining(
+        &self,
+        estimator: TimeEstimator,
+    ) -> Result<BlockingIter<TimeRemainingStream<'a>>> {
+        zbus::block_on(self.asynchronous().receive_time_remaining(estimator)).map(BlockingIter::new)
+    }
+
+    /// Like `DeviceProxy::receive_battery_alerts`, blocking on each alert.
+    pub fn receive_battery_alerts(
+        &self,
+        watcher: BatteryWatcher,
+    ) -> Result<BlockingIter<BatteryAlertStream<'a>>> {
+        zbus::block_on(self.asynchronous().receive_battery_alerts(watcher)).map(BlockingIter::new)
+    }
+
+    /// Like `DeviceProxy::receive_ups_events`, blocking on each event.
+    pub fn receive_ups_events(
+        &self,
+        watcher: UpsWatcher,
+    ) -> Result<BlockingIter<UpsEventStream<'a>>> {
+        zbus::block_on(self.asynchronous().receive_ups_events(watcher)).map(BlockingIter::new)
+    }
+
+    /// Like `DeviceProxy::receive_changes`, blocking on each change.
+    pub fn receive_changes(&self) -> Result<BlockingIter<DeviceChangeStream<'a>>> {
+        zbus::block_on(self.asynchronous().receive_changes()).map(BlockingIter::new)
+    }
+
+    /// Like `DeviceProxy::receive_properties_changed`, blocking on each property.
+    pub fn receive_properties_changed(&self) -> Result<BlockingIter<PropertiesChangedStream<'a>>> {
+        zbus::block_on(self.asynchronous().receive_properties_changed()).map(BlockingIter::new)
+    }
+
+    /// Like `DeviceProxy::watch_warning_level`, blocking on each level.
+    pub fn watch_warning_level(&self) -> Result<BlockingIter<WarningLevelStream<'a>>> {
+        zbus::block_on(self.asynchronous().watch_warning_level()).map(BlockingIter::new)
+    }
+
+    /// Like `DeviceProxy::watch_battery_level`, blocking on each level.
+    pub fn watch_battery_level(&self) -> Result<BlockingIter<BatteryLevelStream<'a>>> {
+        zbus::block_on(self.asynchronous().watch_battery_level()).map(BlockingIter::new)
+    }
+
+    /// The async proxy sharing the connection and cache of this one.
+    fn asynchronous(&self) -> DeviceProxy<'a> {
+        DeviceProxy::from(self.inner().inner().clone())
+    }
+}
+
+impl<'a> From<DeviceProxy<'a>> for DeviceProxyBlocking<'a> {
+    /// Converts the devices carried by streams, such as those of `DeviceEvent::Added`, for
+    /// use with `zbus::blocking`.
+    fn from(device: DeviceProxy<'a>) -> Self {
+        Self::from(device.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(any(feature = "chrono", feature = "time"))]
+    use super::HistoryRecord;
+    use super::WarningLevel;
+    #[cfg(feature = "serde")]
+    use super::{BatteryState, DeviceType, HistoryKind};
+    #[cfg(feature = "serde")]
+    use serde::de::value::{Error, StrDeserializer, U32Deserializer};
+    #[cfg(feature = "serde")]
+    use serde::Deserialize;
+    use std::cmp::Ordering;
+
+    #[test]
+    fn warning_levels_order_by_severity() {
+        assert!(WarningLevel::None < WarningLevel::Low);
+        assert!(WarningLevel::Action < WarningLevel::Other(6));
+        assert!(WarningLevel::Other(6) < WarningLevel::Other(7));
+
+        // An `Other` colliding with a known level differs from it, and so must not be equal
+        // to it under `cmp` either.
+        assert_ne!(WarningLevel::Other(3), WarningLevel::Low);
+        assert_eq!(
+            WarningLevel::Other(3).cmp(&WarningLevel::Low),
+            Ordering::Greater
+        );
+        assert!(WarningLevel::Other(3) < WarningLevel::Critical);
+        assert_eq!(
+            WarningLevel::Other(3).cmp(&WarningLevel::Other(3)),
+            Ordering::Equal
+        );
+    }
+
+    #[cfg(any(feature = "chrono", feature = "time"))]
+    fn record() -> HistoryRecord {
+        HistoryRecord {
+            time: 1_700_000_000,
+            value: 42.0,
+            state: super::BatteryState::Discharging,
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn history_time_as_chrono() {
+        let time = record().time_utc();
+        assert_eq!(time.timestamp(), 1_700_000_000);
+        assert_eq!(time.to_rfc3339(), "2023-11-14T22:13:20+00:00");
+    }
+
+    #[test]
+    #[cfg(feature = "time")]
+    fn history_time_as_time() {
+        let time = record().time_offset();
+        assert_eq!(time.unix_timestamp(), 1_700_000_000);
+        assert_eq!(time.offset(), time::UtcOffset::UTC);
+        assert_eq!(
+            time.date(),
+            time::Date::from_calendar_date(2023, time::Month::November, 14).unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn enums_deserialize_from_names_and_numbers() {
+        let name = |name| StrDeserializer::<Error>::new(name);
+        let number = |number| U32Deserializer::<Error>::new(number);
+
+        assert_eq!(
+            BatteryState::deserialize(name("pending-charge")),
+            Ok(BatteryState::PendingCharge)
+        );
+        assert_eq!(
+            BatteryState::deserialize(number(2)),
+            Ok(BatteryState::Discharging)
+        );
+        assert_eq!(
+            DeviceType::deserialize(number(99)),
+            Ok(DeviceType::Other(99))
+        );
+        assert_eq!(
+            WarningLevel::deserialize(name("critical")),
+            Ok(WarningLevel::Critical)
+        );
+        assert_eq!(
+            HistoryKind::deserialize(name("charge")),
+            Ok(HistoryKind::Charge)
+        );
+
+        assert!(BatteryState::deserialize(name("Charging")).is_err());
+        assert!(HistoryKind::deserialize(name("Rate")).is_err());
+    }
 }
@@ -0,0 +1,111 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+/// The result type returned by this crate's helpers.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// An error from communicating with the daemon.
+///
+/// Errors replied by the bus are classified where possible, so that callers can tell a missing
+/// daemon apart from a failed call. Everything else is a `Transport` error.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The daemon is not running, or is not installed.
+    ServiceNotRunning,
+    /// The device does not exist, or has been removed.
+    NoSuchDevice,
+    /// The device of a `Device` handle has been removed since the handle was created.
+    DeviceGone,
+    /// The daemon or device does not support the property or capability.
+    UnsupportedProperty,
+    /// The daemon reported a value that does not map to any known enum variant.
+    InvalidEnumValue(u32),
+    /// The daemon left out a property which it always sends, such as in a `GetAll` reply.
+    MissingProperty(&'static str),
+    /// The daemon reported a `DaemonVersion` which could not be parsed, given as reported.
+    InvalidVersion(String),
+    /// The daemon did not reply within the time given to `with_timeout`.
+    Timeout,
+    /// The bus call failed.
+    Transport(zbus::Error),
+    /// Reading a power supply from sysfs failed.
+    Io(Arc<io::Error>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ServiceNotRunning => f.write_str("the UPower daemon is not running"),
+            Error::NoSuchDevice => f.write_str("no such device"),
+            Error::DeviceGone => f.write_str("the device has been removed"),
+            Error::UnsupportedProperty => f.write_str("property not supported by the device"),
+            Error::InvalidEnumValue(value) => write!(f, "invalid enum value: {}", value),
+            Error::MissingProperty(name) => write!(f, "missing property: {}", name),
+            Error::InvalidVersion(version) => write!(f, "invalid UPower version: {:?}", version),
+            Error::Timeout => f.write_str("the UPower daemon did not reply in time"),
+            Error::Transport(why) => write!(f, "D-Bus call failed: {}", why),
+            Error::Io(why) => write!(f, "I/O error: {}", why),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Transport(why) => Some(why),
+            Error::Io(why) => Some(why.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<zbus::Error> for Error {
+    fn from(error: zbus::Error) -> Self {
+        if let zbus::Error::MethodError(name, message, _) = &error {
+            match name.as_str() {
+                "org.freedesktop.DBus.Error.ServiceUnknown"
+                | "org.freedesktop.DBus.Error.NameHasNoOwner" => return Error::ServiceNotRunning,
+                "org.freedesktop.DBus.Error.UnknownObject"
+                | "org.freedesktop.DBus.Error.UnknownInterface" => return Error::NoSuchDevice,
+                "org.freedesktop.DBus.Error.UnknownProperty" => return Error::UnsupportedProperty,
+                // GLib services reply with `InvalidArgs` for unknown properties.
+                "org.freedesktop.DBus.Error.InvalidArgs"
+                    if message
+                        .as_deref()
+                        .is_some_and(|message| message.starts_with("No such property")) =>
+                {
+                    return Error::UnsupportedProperty
+                }
+                _ => (),
+            }
+        }
+
+        match error {
+            zbus::Error::Unsupported => Error::UnsupportedProperty,
+            error => Error::Transport(error),
+        }
+    }
+}
+
+impl From<zbus::fdo::Error> for Error {
+    fn from(error: zbus::fdo::Error) -> Self {
+        Error::from(zbus::Error::from(error))
+    }
+}
+
+impl From<zbus::zvariant::Error> for Error {
+    fn from(error: zbus::zvariant::Error) -> Self {
+        Error::Transport(zbus::Error::Variant(error))
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(Arc::new(error))
+    }
+}
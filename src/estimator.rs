@@ -0,0 +1,117 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::stream::Stream;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::device::BatteryState;
+use crate::error::Result;
+use crate::snapshot::DeviceSnapshot;
+
+/// Estimates the time until a device is empty or full from an exponentially smoothed energy
+/// rate, which is far steadier than the daemon's `TimeToEmpty` and `TimeToFull`.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use upower_dbus::{BatteryState, DeviceSnapshot, DeviceType, TimeEstimator, WattHours, Watts};
+///
+/// let mut battery = DeviceSnapshot::new(DeviceType::Battery);
+/// battery.state = BatteryState::Discharging;
+/// battery.energy = WattHours(30.0);
+/// battery.energy_rate = Watts(10.0);
+///
+/// let mut estimator = TimeEstimator::new(0.5);
+/// assert_eq!(estimator.update(&battery), Some(Duration::from_secs(3 * 3600)));
+///
+/// // A momentary spike only moves the estimate halfway.
+/// battery.energy_rate = Watts(30.0);
+/// assert_eq!(estimator.update(&battery), Some(Duration::from_secs(3 * 3600 / 2)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct TimeEstimator {
+    smoothing: f64,
+    rate: Option<f64>,
+    state: BatteryState,
+}
+
+impl Default for TimeEstimator {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_SMOOTHING)
+    }
+}
+
+impl TimeEstimator {
+    /// The weight given to each new sample by `TimeEstimator::default`.
+    pub const DEFAULT_SMOOTHING: f64 = 0.2;
+
+    /// Creates an estimator which gives each new energy rate a weight of `smoothing`, between
+    /// 0 and 1. Lower values give steadier estimates, which react more slowly.
+    pub fn new(smoothing: f64) -> Self {
+        Self {
+            smoothing: smoothing.clamp(f64::EPSILON, 1.0),
+            rate: None,
+            state: BatteryState::Unknown,
+        }
+    }
+
+    /// Forgets every previous sample.
+    pub fn reset(&mut self) {
+        self.rate = None;
+    }
+
+    /// Adds a reading of the device, returning the time until it is empty while discharging,
+    /// or full while charging.
+    ///
+    /// Returns `None` when neither applies, or the device does not report its energy. The
+    /// samples are discarded whenever the device switches between charging and discharging.
+    pub fn update(&mut self, device: &DeviceSnapshot) -> Option<Duration> {
+        if device.state != self.state {
+            self.state = device.state;
+            self.reset();
+        }
+
+        let remaining = match device.state {
+            BatteryState::Discharging => device.energy.0,
+            BatteryState::Charging => (device.energy_full - device.energy).0,
+            _ => return None,
+        };
+
+        let energy_rate = device.energy_rate.0;
+        if energy_rate > 0.0 {
+            self.rate = Some(match self.rate {
+                Some(rate) => rate + self.smoothing * (energy_rate - rate),
+                None => energy_rate,
+            });
+        }
+
+        match self.rate {
+            Some(rate) if remaining > 0.0 => Some(Duration::from_secs(
+                (remaining / rate * 3600.0).round() as u64,
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// A stream of smoothed time estimates, created by `DeviceProxy::receive_time_remaining`.
+pub struct TimeRemainingStream<'a> {
+    pub(crate) inner: Pin<Box<dyn Stream<Item = Result<Duration>> + Send + 'a>>,
+}
+
+impl fmt::Debug for TimeRemainingStream<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TimeRemainingStream")
+            .finish_non_exhaustive()
+    }
+}
+
+impl Stream for TimeRemainingStream<'_> {
+    type Item = Result<Duration>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
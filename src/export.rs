@@ -0,0 +1,135 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use std::io::{self, Write};
+
+use crate::device::{HistoryRecord, StatisticsRecord};
+
+/// Writes the records returned by `get_history` and `get_statistics` to files.
+///
+/// ```rust
+/// use upower_dbus::{BatteryState, ExportRecords, HistoryRecord};
+///
+/// let history = vec![HistoryRecord { time: 1700000000, value: 81.5, state: BatteryState::Discharging }];
+///
+/// let mut csv = Vec::new();
+/// history.to_csv(&mut csv).unwrap();
+/// assert_eq!(csv, b"time,value,state\n1700000000,81.5,discharging\n");
+///
+/// # #[cfg(feature = "serde")]
+/// assert_eq!(history.to_json(), r#"[{"time":1700000000,"value":81.5,"state":"discharging"}]"#);
+/// ```
+pub trait ExportRecords {
+    /// Writes the records as CSV, preceded by a header row.
+    fn to_csv<W: Write>(&self, writer: W) -> io::Result<()>;
+
+    /// Formats the records as a JSON array of objects, through their `Serialize` impls.
+    ///
+    /// Values which are not finite are written as `null`.
+    #[cfg(feature = "serde")]
+    fn to_json(&self) -> String;
+}
+
+impl ExportRecords for [HistoryRecord] {
+    fn to_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "time,value,state")?;
+        for record in self {
+            writeln!(writer, "{},{},{}", record.time, record.value, record.state)?;
+        }
+        writer.flush()
+    }
+
+    #[cfg(feature = "serde")]
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("records are serializable")
+    }
+}
+
+impl ExportRecords for [StatisticsRecord] {
+    fn to_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "value,accuracy")?;
+        for record in self {
+            writeln!(writer, "{},{}", record.value, record.accuracy)?;
+        }
+        writer.flush()
+    }
+
+    #[cfg(feature = "serde")]
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("records are serializable")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExportRecords;
+    use crate::device::{BatteryState, HistoryRecord, StatisticsRecord};
+
+    #[test]
+    fn history_records() {
+        let history = [
+            HistoryRecord {
+                time: 1_700_000_000,
+                value: 81.5,
+                state: BatteryState::Discharging,
+            },
+            HistoryRecord {
+                time: 1_700_000_060,
+                value: f64::NAN,
+                state: BatteryState::FullyCharged,
+            },
+        ];
+
+        let mut csv = Vec::new();
+        history.to_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "time,value,state\n1700000000,81.5,discharging\n1700000060,NaN,fully-charged\n"
+        );
+
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            history.to_json(),
+            concat!(
+                r#"[{"time":1700000000,"value":81.5,"state":"discharging"},"#,
+                r#"{"time":1700000060,"value":null,"state":"fully-charged"}]"#
+            )
+        );
+    }
+
+    #[test]
+    fn statistics_records() {
+        let statistics = [
+            StatisticsRecord {
+                value: 300.0,
+                accuracy: 95.25,
+            },
+            StatisticsRecord {
+                value: f64::INFINITY,
+                accuracy: 0.0,
+            },
+        ];
+
+        let mut csv = Vec::new();
+        statistics.to_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "value,accuracy\n300,95.25\ninf,0\n"
+        );
+
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            statistics.to_json(),
+            r#"[{"value":300.0,"accuracy":95.25},{"value":null,"accuracy":0.0}]"#
+        );
+    }
+
+    #[test]
+    fn no_records() {
+        let mut csv = Vec::new();
+        <[StatisticsRecord]>::to_csv(&[], &mut csv).unwrap();
+        assert_eq!(csv, b"value,accuracy\n");
+        #[cfg(feature = "serde")]
+        assert_eq!(<[HistoryRecord]>::to_json(&[]), "[]");
+    }
+}
@@ -0,0 +1,96 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use std::future::Future;
+use std::time::Duration;
+use zbus::blocking::Connection;
+
+use crate::device::{BatteryState, DeviceProxy};
+use crate::error::{Error, Result};
+use crate::timeout::with_timeout;
+use crate::units::Percentage;
+use crate::upower::UPowerProxy;
+
+/// A simple blocking interface to the daemon, which owns its own system bus connection.
+///
+/// ```rust,no_run
+/// use upower_dbus::UPower;
+///
+/// fn main() -> upower_dbus::Result<()> {
+///     let upower = UPower::new(1000)?;
+///     println!("On Battery: {}", upower.on_battery()?);
+///     println!("Battery: {}", upower.get_percentage()?);
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct UPower {
+    connection: Connection,
+    upower: UPowerProxy<'static>,
+    display: DeviceProxy<'static>,
+    timeout: Duration,
+}
+
+impl UPower {
+    /// Connects to the system bus, failing any call which takes more than `timeout`
+    /// milliseconds.
+    pub fn new(timeout: u64) -> Result<Self> {
+        let timeout = Duration::from_millis(timeout);
+        let connection = Connection::system()?;
+
+        let (upower, display) = zbus::block_on(with_timeout(timeout, async {
+            let upower = UPowerProxy::new(connection.inner()).await?;
+            let display = upower.display_device().await?;
+            Result::<_>::Ok((upower, display))
+        }))?;
+
+        Ok(Self {
+            connection,
+            upower,
+            display,
+            timeout,
+        })
+    }
+
+    /// The connection to the system bus.
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Whether the system is running on battery power.
+    pub fn on_battery(&self) -> Result<bool> {
+        self.call(self.upower.on_battery())
+    }
+
+    /// Whether the lid is closed.
+    pub fn lid_is_closed(&self) -> Result<bool> {
+        self.call(self.upower.lid_is_closed())
+    }
+
+    /// The charge of the display device, in percent.
+    pub fn get_percentage(&self) -> Result<Percentage> {
+        self.call(self.display.percentage())
+    }
+
+    /// The state of the display device.
+    pub fn get_state(&self) -> Result<BatteryState> {
+        self.call(self.display.state())
+    }
+
+    /// The estimated time until the display device is empty, if known.
+    pub fn get_time_to_empty(&self) -> Result<Option<Duration>> {
+        self.call(self.display.time_to_empty_duration())
+    }
+
+    /// The estimated time until the display device is fully charged, if known.
+    pub fn get_time_to_full(&self) -> Result<Option<Duration>> {
+        self.call(self.display.time_to_full_duration())
+    }
+
+    fn call<T, E>(&self, future: impl Future<Output = Result<T, E>>) -> Result<T>
+    where
+        Error: From<E>,
+    {
+        zbus::block_on(with_timeout(self.timeout, future))
+    }
+}
@@ -0,0 +1,572 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Records the state and signals of a UPower daemon to a JSON fixture, and replays them
+//! through a `MockUPower`.
+//!
+//! Properties are stored as the daemon sent them, each with its D-Bus type, so a fixture
+//! captured on real hardware reproduces the same values in the mock.
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use upower_dbus::fixture::Fixture;
+//! use upower_dbus::mock::MockUPower;
+//! use upower_dbus::UPowerProxy;
+//!
+//! # fn main() -> upower_dbus::Result<()> {
+//! # futures::executor::block_on(async move {
+//! // Capture a minute of activity from the real daemon.
+//! let upower = UPowerProxy::system().await?;
+//! let fixture = Fixture::record(&upower, Duration::from_secs(60)).await?;
+//! std::fs::write("unplugged.json", fixture.to_json())?;
+//!
+//! // Later, replay it on a private bus.
+//! let fixture = Fixture::from_json(&std::fs::read_to_string("unplugged.json")?)?;
+//! let server = zbus::ConnectionBuilder::address("unix:path=/tmp/test-bus")?.build().await?;
+//! let mock = MockUPower::new(&server).await?;
+//! fixture.load(&mock).await?;
+//! fixture.play(&mock).await
+//! # })
+//! # }
+//! ```
+
+use futures_util::future::{self, Either};
+use futures_util::stream::StreamExt;
+use serde_json::{json, Map, Value as Json};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io;
+use std::time::{Duration, Instant};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+use zbus::{MatchRule, MessageStream, MessageType};
+
+use crate::device::fetch_properties;
+use crate::error::{Error, Result};
+use crate::mock::MockUPower;
+use crate::names::{
+    DEVICES_PATH, DEVICE_INTERFACE, DISPLAY_DEVICE_PATH, UPOWER_INTERFACE, UPOWER_PATH,
+};
+use crate::snapshot::DeviceSnapshot;
+use crate::timer;
+use crate::upower::UPowerProxy;
+
+/// The name of the display device in a fixture.
+const DISPLAY_DEVICE: &str = "DisplayDevice";
+
+/// The state of a daemon, and the changes to it which followed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Fixture {
+    pub on_battery: bool,
+    pub lid_is_closed: bool,
+    /// The properties of each device, by the last element of its object path. The display
+    /// device is named `DisplayDevice`.
+    pub devices: Vec<(String, HashMap<String, OwnedValue>)>,
+    pub events: Vec<FixtureEvent>,
+}
+
+/// A change to the daemon, and how long after the previous one it happened.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FixtureEvent {
+    pub delay: Duration,
+    pub change: FixtureChange,
+}
+
+/// A change to the daemon, as seen through its signals.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FixtureChange {
+    OnBattery(bool),
+    LidIsClosed(bool),
+    DeviceAdded(String, HashMap<String, OwnedValue>),
+    DeviceRemoved(String),
+    /// The properties of a device which changed, with their new values.
+    DeviceChanged(String, HashMap<String, OwnedValue>),
+}
+
+impl FixtureChange {
+    /// Makes the change to `mock`, emitting the signals the daemon emitted.
+    pub async fn apply(&self, mock: &MockUPower) -> Result<()> {
+        match self {
+            FixtureChange::OnBattery(on_battery) => mock.set_on_battery(*on_battery).await?,
+            FixtureChange::LidIsClosed(closed) => mock.set_lid_is_closed(*closed).await?,
+            FixtureChange::DeviceAdded(name, properties) => {
+                let device = DeviceSnapshot::try_from(properties.clone())?;
+                mock.add_device(name, device).await?;
+            }
+            FixtureChange::DeviceRemoved(name) => {
+                let path = device_path(name)?;
+                mock.remove_device(&path).await?;
+            }
+            FixtureChange::DeviceChanged(name, properties) => {
+                let path = device_path(name)?;
+                mock.set_device_properties(&path, properties.clone())
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Fixture {
+    /// Reads the current state of the daemon, then records its signals for `duration`.
+    pub async fn record(upower: &UPowerProxy<'_>, duration: Duration) -> Result<Self> {
+        let connection = upower.connection();
+        let destination = upower.destination();
+
+        let rule = MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .sender(destination.clone())?
+            .path_namespace(UPOWER_PATH)?
+            .build();
+        let mut signals = MessageStream::for_match_rule(rule, connection, None).await?;
+
+        let mut fixture = Fixture {
+            on_battery: upower.on_battery().await?,
+            lid_is_closed: upower.lid_is_closed().await?,
+            ..Fixture::default()
+        };
+
+        let display = ObjectPath::from_static_str_unchecked(DISPLAY_DEVICE_PATH);
+        let mut paths = upower.enumerate_devices().await?;
+        paths.push(display.into());
+
+        for path in paths {
+            let properties = fetch_properties(connection, destination, &path).await?;
+            fixture.devices.push((device_name(&path), properties));
+        }
+
+        let deadline = timer::sleep(duration);
+        futures_util::pin_mut!(deadline);
+        let mut last = Instant::now();
+
+        loop {
+            let message = match future::select(signals.next(), deadline.as_mut()).await {
+                Either::Left((Some(message), _)) => message?,
+                _ => break,
+            };
+
+            let header = message.header()?;
+            let path = match header.path()? {
+                Some(path) => path.to_owned(),
+                None => continue,
+            };
+
+            let changes = match header.member()?.map(|member| member.as_str()) {
+                Some("PropertiesChanged") => {
+                    let (interface, properties, _): (
+                        String,
+                        HashMap<String, OwnedValue>,
+                        Vec<String>,
+                    ) = message.body()?;
+                    properties_changed(&path, &interface, properties)
+                }
+                Some("DeviceAdded") => {
+                    let device: OwnedObjectPath = message.body()?;
+                    let properties = fetch_properties(connection, destination, &device).await?;
+                    vec![FixtureChange::DeviceAdded(device_name(&device), properties)]
+                }
+                Some("DeviceRemoved") => {
+                    let device: OwnedObjectPath = message.body()?;
+                    vec![FixtureChange::DeviceRemoved(device_name(&device))]
+                }
+                _ => Vec::new(),
+            };
+
+            for change in changes {
+                let now = Instant::now();
+                fixture.events.push(FixtureEvent {
+                    delay: now - last,
+                    change,
+                });
+                last = now;
+            }
+        }
+
+        Ok(fixture)
+    }
+
+    /// Sets up `mock` with the state the fixture starts from.
+    pub async fn load(&self, mock: &MockUPower) -> Result<()> {
+        mock.set_on_battery(self.on_battery).await?;
+        mock.set_lid_is_closed(self.lid_is_closed).await?;
+
+        for (name, properties) in &self.devices {
+            if name == DISPLAY_DEVICE {
+                let display = ObjectPath::from_static_str_unchecked(DISPLAY_DEVICE_PATH);
+                mock.set_device_properties(&display, properties.clone())
+                    .await?;
+            } else {
+                let device = DeviceSnapshot::try_from(properties.clone())?;
+                mock.add_device(name, device).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Makes every change to `mock` in turn, waiting the recorded delay before each.
+    ///
+    /// For tests which should not wait, use `FixtureChange::apply` on each event instead.
+    pub async fn play(&self, mock: &MockUPower) -> Result<()> {
+        for event in &self.events {
+            timer::sleep(event.delay).await;
+            event.change.apply(mock).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Formats the fixture as JSON.
+    pub fn to_json(&self) -> String {
+        let devices = self
+            .devices
+            .iter()
+            .map(|(name, properties)| (name.clone(), properties_to_json(properties)))
+            .collect::<Map<_, _>>();
+
+        let events = self
+            .events
+            .iter()
+            .map(|event| {
+                let (key, change) = match &event.change {
+                    FixtureChange::OnBattery(value) => ("on_battery", json!(value)),
+                    FixtureChange::LidIsClosed(value) => ("lid_is_closed", json!(value)),
+                    FixtureChange::DeviceRemoved(name) => ("device_removed", json!(name)),
+                    FixtureChange::DeviceAdded(name, properties) => {
+                        ("device_added", device_to_json(name, properties))
+                    }
+                    FixtureChange::DeviceChanged(name, properties) => {
+                        ("device_changed", device_to_json(name, properties))
+                    }
+                };
+
+                let mut event_json = Map::new();
+                let delay = u64::try_from(event.delay.as_millis()).unwrap_or(u64::MAX);
+                event_json.insert("delay_ms".to_owned(), json!(delay));
+                event_json.insert(key.to_owned(), change);
+                Json::Object(event_json)
+            })
+            .collect::<Vec<_>>();
+
+        json!({
+            "on_battery": self.on_battery,
+            "lid_is_closed": self.lid_is_closed,
+            "devices": devices,
+            "events": events,
+        })
+        .to_string()
+    }
+
+    /// Parses a fixture written by `to_json`.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let json: Json = serde_json::from_str(json).map_err(invalid)?;
+
+        let mut fixture = Fixture {
+            on_battery: json["on_battery"].as_bool().unwrap_or(false),
+            lid_is_closed: json["lid_is_closed"].as_bool().unwrap_or(false),
+            ..Fixture::default()
+        };
+
+        if let Some(devices) = json["devices"].as_object() {
+            for (name, properties) in devices {
+                fixture
+                    .devices
+                    .push((name.clone(), properties_from_json(properties)?));
+            }
+        }
+
+        if let Some(events) = json["events"].as_array() {
+            for event in events {
+                let delay = match &event["delay_ms"] {
+                    Json::Null => 0,
+                    delay => delay.as_u64().ok_or_else(|| invalid("delay_ms"))?,
+                };
+
+                let change = if let Some(value) = event["on_battery"].as_bool() {
+                    FixtureChange::OnBattery(value)
+                } else if let Some(value) = event["lid_is_closed"].as_bool() {
+                    FixtureChange::LidIsClosed(value)
+                } else if let Some(name) = event["device_removed"].as_str() {
+                    FixtureChange::DeviceRemoved(name.to_owned())
+                } else if let Some(device) = event.get("device_added") {
+                    let (name, properties) = device_from_json(device)?;
+                    FixtureChange::DeviceAdded(name, properties)
+                } else if let Some(device) = event.get("device_changed") {
+                    let (name, properties) = device_from_json(device)?;
+                    FixtureChange::DeviceChanged(name, properties)
+                } else {
+                    return Err(invalid("unknown event"));
+                };
+
+                fixture.events.push(FixtureEvent {
+                    delay: Duration::from_millis(delay),
+                    change,
+                });
+            }
+        }
+
+        Ok(fixture)
+    }
+}
+
+/// The changes described by a `PropertiesChanged` signal from `path`.
+fn properties_changed(
+    path: &ObjectPath<'_>,
+    interface: &str,
+    mut properties: HashMap<String, OwnedValue>,
+) -> Vec<FixtureChange> {
+    if interface == DEVICE_INTERFACE {
+        return vec![FixtureChange::DeviceChanged(device_name(path), properties)];
+    }
+
+    if interface != UPOWER_INTERFACE || path.as_str() != UPOWER_PATH {
+        return Vec::new();
+    }
+
+    let mut changes = Vec::new();
+    let mut take = |name| {
+        properties
+            .remove(name)
+            .and_then(|value| bool::try_from(value).ok())
+    };
+
+    if let Some(on_battery) = take("OnBattery") {
+        changes.push(FixtureChange::OnBattery(on_battery));
+    }
+
+    if let Some(lid_is_closed) = take("LidIsClosed") {
+        changes.push(FixtureChange::LidIsClosed(lid_is_closed));
+    }
+
+    changes
+}
+
+fn device_name(path: &ObjectPath<'_>) -> String {
+    let path = path.as_str();
+    path.rsplit('/').next().unwrap_or(path).to_owned()
+}
+
+fn device_path(name: &str) -> Result<OwnedObjectPath> {
+    Ok(OwnedObjectPath::try_from(format!(
+        "{}/{}",
+        DEVICES_PATH, name
+    ))?)
+}
+
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(why: E) -> Error {
+    Error::from(io::Error::new(io::ErrorKind::InvalidData, why))
+}
+
+fn device_to_json(name: &str, properties: &HashMap<String, OwnedValue>) -> Json {
+    json!({
+        "name": name,
+        "properties": properties_to_json(properties),
+    })
+}
+
+fn device_from_json(json: &Json) -> Result<(String, HashMap<String, OwnedValue>)> {
+    let name = match json["name"].as_str() {
+        Some(name) => name.to_owned(),
+        None => return Err(invalid("device without a name")),
+    };
+
+    let properties = match json.get("properties") {
+        Some(properties) => properties_from_json(properties)?,
+        None => HashMap::new(),
+    };
+
+    Ok((name, properties))
+}
+
+/// Writes each property as a `[signature, value]` pair, in sorted order. Properties of types
+/// the daemon does not use are skipped.
+fn properties_to_json(properties: &HashMap<String, OwnedValue>) -> Json {
+    let mut names: Vec<&String> = properties.keys().collect();
+    names.sort();
+
+    let members = names
+        .into_iter()
+        .filter_map(|name| {
+            let (signature, value) = match &*properties[name] {
+                Value::Bool(value) => ("b", json!(value)),
+                Value::U8(value) => ("y", json!(value)),
+                Value::I16(value) => ("n", json!(value)),
+                Value::U16(value) => ("q", json!(value)),
+                Value::I32(value) => ("i", json!(value)),
+                Value::U32(value) => ("u", json!(value)),
+                Value::I64(value) => ("x", json!(value)),
+                Value::U64(value) => ("t", json!(value)),
+                // Non-finite values are written as null.
+                Value::F64(value) => ("d", json!(value)),
+                Value::Str(value) => ("s", json!(value.as_str())),
+                Value::ObjectPath(value) => ("o", json!(value.as_str())),
+                _ => return None,
+            };
+
+            Some((name.clone(), json!([signature, value])))
+        })
+        .collect();
+
+    Json::Object(members)
+}
+
+fn properties_from_json(json: &Json) -> Result<HashMap<String, OwnedValue>> {
+    let members = json
+        .as_object()
+        .ok_or_else(|| invalid("properties are not an object"))?;
+
+    let mut properties = HashMap::new();
+
+    for (name, pair) in members {
+        let (signature, value) = match pair.as_array().map(Vec::as_slice) {
+            Some([Json::String(signature), value]) => (signature.as_str(), value),
+            _ => return Err(invalid(format!("malformed property {}", name))),
+        };
+
+        let malformed = || invalid(format!("malformed {} property {}", signature, name));
+
+        macro_rules! parse {
+            ($type:ty, $as:ident) => {{
+                let value = value
+                    .$as()
+                    .and_then(|value| <$type>::try_from(value).ok())
+                    .ok_or_else(malformed)?;
+                Value::from(value)
+            }};
+        }
+
+        let value = match signature {
+            "b" => Value::Bool(value.as_bool().ok_or_else(malformed)?),
+            "y" => parse!(u8, as_u64),
+            "n" => parse!(i16, as_i64),
+            "q" => parse!(u16, as_u64),
+            "i" => parse!(i32, as_i64),
+            "u" => parse!(u32, as_u64),
+            "x" => parse!(i64, as_i64),
+            "t" => parse!(u64, as_u64),
+            "d" => match value {
+                Json::Null => Value::F64(f64::NAN),
+                value => Value::F64(value.as_f64().ok_or_else(malformed)?),
+            },
+            "s" => Value::from(value.as_str().ok_or_else(malformed)?.to_owned()),
+            "o" => {
+                let path = value.as_str().ok_or_else(malformed)?;
+                Value::from(ObjectPath::try_from(path.to_owned())?)
+            }
+            _ => return Err(malformed()),
+        };
+
+        properties.insert(name.clone(), OwnedValue::from(value));
+    }
+
+    Ok(properties)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Fixture, FixtureChange, FixtureEvent, DISPLAY_DEVICE};
+    use std::collections::HashMap;
+    use std::convert::TryFrom;
+    use std::time::Duration;
+    use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+
+    /// A property of every signature a fixture stores.
+    fn every_signature() -> HashMap<String, OwnedValue> {
+        let path =
+            ObjectPath::from_static_str_unchecked("/org/freedesktop/UPower/devices/battery_BAT0");
+
+        properties(&[
+            ("Bool", Value::Bool(true)),
+            ("Byte", Value::U8(200)),
+            ("Int16", Value::I16(-300)),
+            ("Uint16", Value::U16(60_000)),
+            ("Int32", Value::I32(-70_000)),
+            ("Uint32", Value::U32(4_000_000_000)),
+            ("Int64", Value::I64(-9_000_000_000)),
+            ("Uint64", Value::U64(u64::MAX)),
+            ("Double", Value::F64(42.25)),
+            ("String", Value::from("Li-ion \"quoted\" \u{1F50B}")),
+            ("ObjectPath", Value::from(path)),
+        ])
+    }
+
+    fn properties(entries: &[(&str, Value<'static>)]) -> HashMap<String, OwnedValue> {
+        entries
+            .iter()
+            .map(|(name, value)| ((*name).to_owned(), OwnedValue::from(value.clone())))
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let fixture = Fixture {
+            on_battery: true,
+            lid_is_closed: false,
+            devices: vec![
+                ("battery_BAT0".to_owned(), every_signature()),
+                (
+                    DISPLAY_DEVICE.to_owned(),
+                    properties(&[("Percentage", Value::F64(57.0)), ("Type", Value::U32(2))]),
+                ),
+            ],
+            events: vec![
+                FixtureEvent {
+                    delay: Duration::from_millis(1500),
+                    change: FixtureChange::OnBattery(false),
+                },
+                FixtureEvent {
+                    delay: Duration::from_millis(20),
+                    change: FixtureChange::LidIsClosed(true),
+                },
+                FixtureEvent {
+                    delay: Duration::ZERO,
+                    change: FixtureChange::DeviceAdded("mouse_0".to_owned(), every_signature()),
+                },
+                FixtureEvent {
+                    delay: Duration::from_millis(3),
+                    change: FixtureChange::DeviceChanged(
+                        DISPLAY_DEVICE.to_owned(),
+                        properties(&[("Percentage", Value::F64(56.5))]),
+                    ),
+                },
+                FixtureEvent {
+                    delay: Duration::from_millis(4),
+                    change: FixtureChange::DeviceRemoved("mouse_0".to_owned()),
+                },
+            ],
+        };
+
+        let json = fixture.to_json();
+        assert_eq!(Fixture::from_json(&json).unwrap(), fixture);
+    }
+
+    #[test]
+    fn non_finite_doubles_are_null() {
+        let fixture = Fixture {
+            devices: vec![(
+                DISPLAY_DEVICE.to_owned(),
+                properties(&[("Temperature", Value::F64(f64::INFINITY))]),
+            )],
+            ..Fixture::default()
+        };
+
+        let json = fixture.to_json();
+        assert!(json.contains(r#""Temperature":["d",null]"#), "{}", json);
+
+        let parsed = Fixture::from_json(&json).unwrap();
+        let value = f64::try_from(&parsed.devices[0].1["Temperature"]).unwrap();
+        assert!(value.is_nan());
+    }
+
+    #[test]
+    fn malformed_properties_are_rejected() {
+        let json = r#"{"devices": {"battery_BAT0": {"Percentage": ["y", 300]}}}"#;
+        assert!(Fixture::from_json(json).is_err());
+
+        let json = r#"{"devices": {"battery_BAT0": {"Percentage": "d"}}}"#;
+        assert!(Fixture::from_json(json).is_err());
+
+        let json = r#"{"events": [{"delay_ms": 5, "unknown": true}]}"#;
+        assert!(Fixture::from_json(json).is_err());
+    }
+}
@@ -0,0 +1,290 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::stream::StreamExt;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use zbus::names::BusName;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath};
+use zbus::{CacheProperties, Task};
+
+use crate::device::{DeviceProxy, DeviceProxyBlocking};
+use crate::error::{Error, Result};
+use crate::id::DeviceId;
+use crate::names::{DISPLAY_DEVICE_PATH, UPOWER_SERVICE};
+use crate::snapshot::DeviceSnapshot;
+use crate::upower::UPowerProxy;
+use crate::watch::WatchCell;
+
+/// An owned handle to a device, holding its connection, destination and path.
+///
+/// Unlike a proxy, a handle carries no lifetime and no property cache, so it is cheap to
+/// clone, store in structs and move into spawned tasks. Proxies are created from it on demand.
+///
+/// A handle notices the device being removed, through a task on the executor of the
+/// connection which watches for `DeviceRemoved` until every clone of the handle is dropped.
+/// From then on, `is_alive` returns `false`, `closed` resolves, and creating a proxy fails
+/// with `Error::DeviceGone`.
+///
+/// ```rust,no_run
+/// use upower_dbus::{Device, UPowerProxy};
+///
+/// # async fn run() -> upower_dbus::Result<()> {
+/// let upower = UPowerProxy::system().await?;
+/// let devices: Vec<Device> = upower.devices().await?.iter().map(Device::from).collect();
+///
+/// for device in devices {
+///     std::thread::spawn(move || -> upower_dbus::Result<()> {
+///         let proxy = device.blocking_proxy()?;
+///         println!("{}: {}", device.path().as_str(), proxy.percentage()?);
+///         Ok(())
+///     });
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Device {
+    connection: zbus::Connection,
+    destination: BusName<'static>,
+    path: OwnedObjectPath,
+    removal: Arc<Removal>,
+}
+
+impl Device {
+    /// A handle to the device at `path` of the daemon at `UPOWER_SERVICE`.
+    pub fn new<P: Into<OwnedObjectPath>>(connection: zbus::Connection, path: P) -> Self {
+        Self::with_destination(
+            connection,
+            BusName::from_static_str(UPOWER_SERVICE).expect("valid bus name"),
+            path,
+        )
+    }
+
+    /// A handle to the device at `path` of the daemon served by `destination`.
+    pub fn with_destination<P: Into<OwnedObjectPath>>(
+        connection: zbus::Connection,
+        destination: BusName<'static>,
+        path: P,
+    ) -> Self {
+        let path = path.into();
+        let removal = Removal::spawn(&connection, &destination, &path);
+
+        Self {
+            connection,
+            destination,
+            path,
+            removal: Arc::new(removal),
+        }
+    }
+
+    /// The connection the device is reached through.
+    pub fn connection(&self) -> &zbus::Connection {
+        &self.connection
+    }
+
+    /// The bus name of the daemon serving the device.
+    pub fn destination(&self) -> &BusName<'static> {
+        &self.destination
+    }
+
+    /// The object path of the device.
+    pub fn path(&self) -> &ObjectPath<'static> {
+        &self.path
+    }
+
+    /// The identity of the device.
+    pub fn id(&self) -> DeviceId {
+        DeviceId::from(self.path.clone())
+    }
+
+    /// Whether the device is still present, as far as the handle knows.
+    ///
+    /// A removal is only noticed once the daemon has announced it, so a `true` here does not
+    /// promise that the next call succeeds.
+    pub fn is_alive(&self) -> bool {
+        !self.removal.gone.read(|gone| *gone)
+    }
+
+    /// Resolves once the device has been removed.
+    ///
+    /// Never resolves for the display device, which lasts as long as the daemon.
+    pub async fn closed(&self) {
+        let mut seen = 0;
+
+        // The cell is never closed, as the task only ever marks the device as gone.
+        while self.is_alive() {
+            let _ = self.removal.gone.changed(&mut seen).await;
+        }
+    }
+
+    /// Creates a proxy for the device.
+    pub async fn proxy(&self) -> Result<DeviceProxy<'static>> {
+        self.check_alive()?;
+
+        let device = DeviceProxy::builder(&self.connection)
+            .destination(self.destination.clone())?
+            .path(self.path.clone())?
+            .build()
+            .await?;
+        Ok(device)
+    }
+
+    /// Creates a blocking proxy for the device.
+    pub fn blocking_proxy(&self) -> Result<DeviceProxyBlocking<'static>> {
+        self.check_alive()?;
+
+        let connection = zbus::blocking::Connection::from(self.connection.clone());
+        let device = DeviceProxyBlocking::builder(&connection)
+            .destination(self.destination.clone())?
+            .path(self.path.clone())?
+            .build()?;
+        Ok(device)
+    }
+
+    /// Fetches every property of the device with a single `GetAll` call.
+    pub async fn snapshot(&self) -> Result<DeviceSnapshot> {
+        match self.proxy().await?.snapshot().await {
+            // The removal may be announced while the call is in flight.
+            Err(Error::NoSuchDevice) if !self.is_alive() => Err(Error::DeviceGone),
+            result => result,
+        }
+    }
+
+    fn check_alive(&self) -> Result<()> {
+        if self.is_alive() {
+            Ok(())
+        } else {
+            Err(Error::DeviceGone)
+        }
+    }
+}
+
+/// Devices are equal if they have the same path on the same daemon.
+impl PartialEq for Device {
+    fn eq(&self, other: &Self) -> bool {
+        self.destination == other.destination && self.path == other.path
+    }
+}
+
+impl Eq for Device {}
+
+impl Hash for Device {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.destination.hash(state);
+        self.path.hash(state);
+    }
+}
+
+impl From<&DeviceProxy<'_>> for Device {
+    fn from(device: &DeviceProxy<'_>) -> Self {
+        Self::with_destination(
+            device.connection().clone(),
+            device.destination().to_owned(),
+            device.path().to_owned(),
+        )
+    }
+}
+
+impl From<DeviceProxy<'_>> for Device {
+    fn from(device: DeviceProxy<'_>) -> Self {
+        Self::from(&device)
+    }
+}
+
+impl From<&DeviceProxyBlocking<'_>> for Device {
+    fn from(device: &DeviceProxyBlocking<'_>) -> Self {
+        Self::with_destination(
+            device.connection().inner().clone(),
+            device.destination().to_owned(),
+            device.path().to_owned(),
+        )
+    }
+}
+
+impl From<DeviceProxyBlocking<'_>> for Device {
+    fn from(device: DeviceProxyBlocking<'_>) -> Self {
+        Self::from(&device)
+    }
+}
+
+/// Watches for the removal of a device, on behalf of every clone of its handle.
+struct Removal {
+    gone: Arc<WatchCell<bool>>,
+    _task: Task<()>,
+}
+
+impl fmt::Debug for Removal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Removal")
+            .field("gone", &self.gone.read(|gone| *gone))
+            .finish_non_exhaustive()
+    }
+}
+
+impl Removal {
+    fn spawn(
+        connection: &zbus::Connection,
+        destination: &BusName<'static>,
+        path: &OwnedObjectPath,
+    ) -> Self {
+        let gone = Arc::new(WatchCell::new(false));
+
+        let task = connection.executor().spawn(
+            watch_removal(
+                connection.clone(),
+                destination.clone(),
+                path.clone(),
+                gone.clone(),
+            ),
+            "upower-dbus device removal",
+        );
+
+        Self { gone, _task: task }
+    }
+}
+
+/// Marks the device as gone once the daemon removes it.
+///
+/// The device is looked up after subscribing to `DeviceRemoved`, so that a removal before the
+/// subscription is not missed. If the daemon cannot be watched, the device is assumed to live.
+async fn watch_removal(
+    connection: zbus::Connection,
+    destination: BusName<'static>,
+    path: OwnedObjectPath,
+    gone: Arc<WatchCell<bool>>,
+) {
+    if path.as_str() == DISPLAY_DEVICE_PATH {
+        return;
+    }
+
+    let removed = async {
+        let upower = UPowerProxy::builder(&connection)
+            .destination(destination)?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+
+        let mut signals = upower.receive_device_removed().await?;
+
+        if !upower.enumerate_devices().await?.contains(&path) {
+            return Ok(true);
+        }
+
+        while let Some(signal) = signals.next().await {
+            if signal.args()?.device == *path {
+                return Ok(true);
+            }
+        }
+
+        Result::<_>::Ok(false)
+    };
+
+    if let Ok(true) = removed.await {
+        gone.modify(|gone| {
+            *gone = true;
+            true
+        });
+    }
+}
@@ -0,0 +1,48 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::stream::Stream;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use zbus::zvariant::OwnedObjectPath;
+
+use crate::device::DeviceProxy;
+use crate::error::Result;
+use crate::id::DeviceId;
+
+/// A device being connected to or disconnected from the system.
+#[derive(Clone, Debug)]
+pub enum DeviceEvent {
+    Added(DeviceProxy<'static>),
+    Removed(OwnedObjectPath),
+}
+
+impl DeviceEvent {
+    /// The identity of the device which was added or removed.
+    pub fn id(&self) -> DeviceId {
+        match self {
+            DeviceEvent::Added(device) => device.id(),
+            DeviceEvent::Removed(path) => DeviceId::from(path.clone()),
+        }
+    }
+}
+
+/// A stream of `DeviceEvent`s, created by `UPowerProxy::watch_devices`.
+pub struct DeviceStream<'a> {
+    pub(crate) inner: Pin<Box<dyn Stream<Item = Result<DeviceEvent>> + Send + 'a>>,
+}
+
+impl fmt::Debug for DeviceStream<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeviceStream").finish_non_exhaustive()
+    }
+}
+
+impl Stream for DeviceStream<'_> {
+    type Item = Result<DeviceEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
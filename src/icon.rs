@@ -0,0 +1,186 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::device::{BatteryState, DeviceType};
+use crate::units::Percentage;
+
+/// Computes a `battery-level-XX-symbolic` icon name from a percentage and state.
+///
+/// Used as a fallback when the daemon reports an empty `IconName`, which happens on some
+/// hardware.
+///
+/// ```
+/// use upower_dbus::{fallback_icon_name, BatteryState, Percentage};
+///
+/// assert_eq!(
+///     fallback_icon_name(Percentage::new(47.0), BatteryState::Charging),
+///     "battery-level-50-charging-symbolic"
+/// );
+/// ```
+pub fn fallback_icon_name(percentage: Percentage, state: BatteryState) -> String {
+    let level = ((percentage.get() / 10.0).round() * 10.0) as u8;
+
+    match state {
+        BatteryState::FullyCharged => String::from("battery-level-100-charged-symbolic"),
+        BatteryState::Charging | BatteryState::PendingCharge => {
+            format!("battery-level-{}-charging-symbolic", level)
+        }
+        _ => format!("battery-level-{}-symbolic", level),
+    }
+}
+
+/// The icon names of a kind of device, from full to empty.
+type Levels = [&'static str; 5];
+
+const BATTERY: Levels = [
+    "battery-full",
+    "battery-good",
+    "battery-low",
+    "battery-caution",
+    "battery-empty",
+];
+
+const BATTERY_CHARGING: Levels = [
+    "battery-full-charging",
+    "battery-good-charging",
+    "battery-low-charging",
+    "battery-caution-charging",
+    "battery-empty-charging",
+];
+
+const MOUSE: Levels = [
+    "input-mouse-battery-full",
+    "input-mouse-battery-good",
+    "input-mouse-battery-low",
+    "input-mouse-battery-caution",
+    "input-mouse-battery-empty",
+];
+
+const KEYBOARD: Levels = [
+    "input-keyboard-battery-full",
+    "input-keyboard-battery-good",
+    "input-keyboard-battery-low",
+    "input-keyboard-battery-caution",
+    "input-keyboard-battery-empty",
+];
+
+const GAMING_INPUT: Levels = [
+    "input-gaming-battery-full",
+    "input-gaming-battery-good",
+    "input-gaming-battery-low",
+    "input-gaming-battery-caution",
+    "input-gaming-battery-empty",
+];
+
+const TABLET: Levels = [
+    "input-tablet-battery-full",
+    "input-tablet-battery-good",
+    "input-tablet-battery-low",
+    "input-tablet-battery-caution",
+    "input-tablet-battery-empty",
+];
+
+const TOUCHPAD: Levels = [
+    "input-touchpad-battery-full",
+    "input-touchpad-battery-good",
+    "input-touchpad-battery-low",
+    "input-touchpad-battery-caution",
+    "input-touchpad-battery-empty",
+];
+
+const PHONE: Levels = [
+    "phone-battery-full",
+    "phone-battery-good",
+    "phone-battery-low",
+    "phone-battery-caution",
+    "phone-battery-empty",
+];
+
+const HEADSET: Levels = [
+    "audio-headset-battery-full",
+    "audio-headset-battery-good",
+    "audio-headset-battery-low",
+    "audio-headset-battery-caution",
+    "audio-headset-battery-empty",
+];
+
+const HEADPHONES: Levels = [
+    "audio-headphones-battery-full",
+    "audio-headphones-battery-good",
+    "audio-headphones-battery-low",
+    "audio-headphones-battery-caution",
+    "audio-headphones-battery-empty",
+];
+
+/// Selects a freedesktop icon name for a device of kind `kind`, by its charge.
+///
+/// Peripherals get icons of their own kind, such as `input-mouse-battery-low`, while batteries
+/// of the system get `battery-*` icons, with `-charging` variants. Append `-symbolic` for the
+/// symbolic variant.
+///
+/// ```
+/// use upower_dbus::{icon_for, BatteryState, DeviceType, Percentage};
+///
+/// assert_eq!(
+///     icon_for(DeviceType::Mouse, BatteryState::Discharging, Percentage::new(15.0)),
+///     "input-mouse-battery-caution"
+/// );
+/// assert_eq!(
+///     icon_for(DeviceType::Battery, BatteryState::Charging, Percentage::new(60.0)),
+///     "battery-good-charging"
+/// );
+/// ```
+pub fn icon_for(kind: DeviceType, state: BatteryState, percentage: Percentage) -> &'static str {
+    let levels = match (kind, peripheral_levels(kind)) {
+        (DeviceType::LinePower, _) => return "ac-adapter",
+        (_, Some(levels)) => levels,
+        (_, None) => match state {
+            BatteryState::FullyCharged => return "battery-full-charged",
+            BatteryState::Charging | BatteryState::PendingCharge => &BATTERY_CHARGING,
+            _ => &BATTERY,
+        },
+    };
+
+    let percentage = percentage.get();
+    let level = if state == BatteryState::FullyCharged || percentage >= 80.0 {
+        0
+    } else if percentage >= 50.0 {
+        1
+    } else if percentage >= 20.0 {
+        2
+    } else if percentage >= 5.0 {
+        3
+    } else {
+        4
+    };
+
+    levels[level]
+}
+
+/// The icons of a kind of peripheral, if it has icons of its own.
+fn peripheral_levels(kind: DeviceType) -> Option<&'static Levels> {
+    Some(match kind {
+        DeviceType::Mouse => &MOUSE,
+        DeviceType::Keyboard => &KEYBOARD,
+        DeviceType::GamingInput => &GAMING_INPUT,
+        DeviceType::Tablet | DeviceType::Pen => &TABLET,
+        DeviceType::Touchpad => &TOUCHPAD,
+        DeviceType::Phone => &PHONE,
+        DeviceType::Headset => &HEADSET,
+        DeviceType::Headphones => &HEADPHONES,
+        _ => return None,
+    })
+}
+
+/// The icon name used when the daemon reports none: that of `icon_for` for a peripheral, and
+/// that of `fallback_icon_name` for anything else.
+pub(crate) fn device_fallback_icon(
+    kind: DeviceType,
+    state: BatteryState,
+    percentage: Percentage,
+) -> String {
+    match peripheral_levels(kind) {
+        Some(_) => String::from(icon_for(kind, state, percentage)),
+        None => fallback_icon_name(percentage, state),
+    }
+}
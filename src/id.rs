@@ -0,0 +1,101 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use std::cmp::Ordering;
+use std::fmt;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath};
+
+use crate::device::{DeviceProxy, DeviceProxyBlocking};
+
+/// Identifies a device by its object path, for use as a key in maps of per-device state.
+///
+/// The same id is produced by the proxy of a device and by the signals which announce its
+/// removal, so state can be dropped when the device goes away.
+///
+/// ```rust
+/// use std::collections::HashMap;
+/// use std::convert::TryFrom;
+/// use upower_dbus::DeviceId;
+/// use zbus::zvariant::OwnedObjectPath;
+///
+/// let path = OwnedObjectPath::try_from("/org/freedesktop/UPower/devices/battery_BAT0").unwrap();
+///
+/// let mut seen = HashMap::new();
+/// seen.insert(DeviceId::from(path.clone()), 1);
+/// assert_eq!(seen.get(&DeviceId::from(path)), Some(&1));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DeviceId(pub OwnedObjectPath);
+
+impl DeviceId {
+    /// The object path of the device.
+    pub fn path(&self) -> &ObjectPath<'static> {
+        &self.0
+    }
+
+    /// The object path of the device, as a string.
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl fmt::Display for DeviceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl PartialOrd for DeviceId {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Orders devices by their object paths.
+impl Ord for DeviceId {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl From<OwnedObjectPath> for DeviceId {
+    fn from(path: OwnedObjectPath) -> Self {
+        DeviceId(path)
+    }
+}
+
+impl From<ObjectPath<'_>> for DeviceId {
+    fn from(path: ObjectPath<'_>) -> Self {
+        DeviceId(path.into_owned().into())
+    }
+}
+
+impl From<DeviceId> for OwnedObjectPath {
+    fn from(id: DeviceId) -> Self {
+        id.0
+    }
+}
+
+impl PartialEq<OwnedObjectPath> for DeviceId {
+    fn eq(&self, other: &OwnedObjectPath) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<ObjectPath<'_>> for DeviceId {
+    fn eq(&self, other: &ObjectPath<'_>) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl From<&DeviceProxy<'_>> for DeviceId {
+    fn from(device: &DeviceProxy<'_>) -> Self {
+        device.id()
+    }
+}
+
+impl From<&DeviceProxyBlocking<'_>> for DeviceId {
+    fn from(device: &DeviceProxyBlocking<'_>) -> Self {
+        device.id()
+    }
+}
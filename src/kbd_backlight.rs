@@ -0,0 +1,243 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::stream::{Stream, StreamExt};
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use zbus::dbus_proxy;
+
+use crate::blocking::BlockingIter;
+use crate::error::Result;
+use crate::units::Percentage;
+use crate::upower::UPowerProxy;
+
+#[dbus_proxy(
+    interface = "org.freedesktop.UPower.KbdBacklight",
+    default_service = "org.freedesktop.UPower",
+    default_path = "/org/freedesktop/UPower/KbdBacklight"
+)]
+trait KbdBacklight {
+    /// GetBrightness method
+    fn get_brightness(&self) -> zbus::Result<i32>;
+
+    /// GetMaxBrightness method
+    fn get_max_brightness(&self) -> zbus::Result<i32>;
+
+    /// SetBrightness method
+    fn set_brightness(&self, value: i32) -> zbus::Result<()>;
+
+    /// BrightnessChanged signal
+    #[dbus_proxy(signal)]
+    fn brightness_changed(&self, value: i32) -> zbus::Result<()>;
+
+    /// BrightnessChangedWithSource signal
+    ///
+    /// `source` is either `"external"` or `"internal"`, where internal changes are those
+    /// triggered by the firmware, such as a hotkey.
+    #[dbus_proxy(signal)]
+    fn brightness_changed_with_source(&self, value: i32, source: &str) -> zbus::Result<()>;
+}
+
+impl<'a> KbdBacklightProxy<'a> {
+    /// The brightness relative to the maximum brightness of the device.
+    pub async fn brightness_percent(&self) -> Result<Percentage> {
+        let brightness = self.get_brightness().await?;
+        Ok(percent(brightness, self.get_max_brightness().await?))
+    }
+
+    /// Sets the brightness to `percent` of the maximum, rounded to the nearest step the
+    /// device supports.
+    pub async fn set_brightness_percent(&self, percent: Percentage) -> Result<()> {
+        let max = self.get_max_brightness().await?;
+        let brightness = KbdBacklightLevel::Fraction(percent.get() / 100.0).resolve(max);
+        Ok(self.set_brightness(brightness).await?)
+    }
+
+    /// Like `receive_brightness_changed`, but yields the brightness relative to the maximum
+    /// brightness, which is read once when the stream is created.
+    pub async fn receive_brightness_percent_changed(&self) -> Result<KbdBrightnessStream<'a>> {
+        let max = self.get_max_brightness().await?;
+        let changes = self
+            .receive_brightness_changed()
+            .await?
+            .map(move |signal| Ok(percent(signal.args()?.value, max)));
+
+        Ok(KbdBrightnessStream {
+            inner: Box::pin(changes),
+        })
+    }
+}
+
+impl<'a> KbdBacklightProxyBlocking<'a> {
+    /// The brightness relative to the maximum brightness of the device.
+    pub fn brightness_percent(&self) -> Result<Percentage> {
+        Ok(percent(self.get_brightness()?, self.get_max_brightness()?))
+    }
+
+    /// Sets the brightness to `percent` of the maximum, rounded to the nearest step the
+    /// device supports.
+    pub fn set_brightness_percent(&self, percent: Percentage) -> Result<()> {
+        let max = self.get_max_brightness()?;
+        let brightness = KbdBacklightLevel::Fraction(percent.get() / 100.0).resolve(max);
+        Ok(self.set_brightness(brightness)?)
+    }
+
+    /// Like `KbdBacklightProxy::receive_brightness_percent_changed`, blocking on each
+    /// brightness.
+    pub fn receive_brightness_percent_changed(
+        &self,
+    ) -> Result<BlockingIter<KbdBrightnessStream<'a>>> {
+        let backlight = KbdBacklightProxy::from(self.inner().inner().clone());
+        zbus::block_on(backlight.receive_brightness_percent_changed()).map(BlockingIter::new)
+    }
+}
+
+/// The percentage of `max` which `brightness` is, or zero for a device without steps.
+fn percent(brightness: i32, max: i32) -> Percentage {
+    if max > 0 {
+        Percentage::new(f64::from(brightness) / f64::from(max) * 100.0)
+    } else {
+        Percentage::new(0.0)
+    }
+}
+
+/// A stream of keyboard backlight brightnesses, created by
+/// `KbdBacklightProxy::receive_brightness_percent_changed`.
+pub struct KbdBrightnessStream<'a> {
+    pub(crate) inner: Pin<Box<dyn Stream<Item = Result<Percentage>> + Send + 'a>>,
+}
+
+impl fmt::Debug for KbdBrightnessStream<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KbdBrightnessStream")
+            .finish_non_exhaustive()
+    }
+}
+
+impl Stream for KbdBrightnessStream<'_> {
+    type Item = Result<Percentage>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+/// A keyboard backlight brightness, relative to the maximum reported by the device.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum KbdBacklightLevel {
+    Off,
+    Max,
+    /// A fraction of the maximum brightness, from 0 to 1.
+    Fraction(f64),
+    /// A raw brightness value, clamped to the range supported by the device.
+    Value(i32),
+}
+
+impl KbdBacklightLevel {
+    /// The raw brightness for a device with a maximum brightness of `max`.
+    pub fn resolve(self, max: i32) -> i32 {
+        let max = max.max(0);
+        match self {
+            KbdBacklightLevel::Off => 0,
+            KbdBacklightLevel::Max => max,
+            KbdBacklightLevel::Fraction(fraction) => {
+                (fraction.clamp(0.0, 1.0) * f64::from(max)).round() as i32
+            }
+            KbdBacklightLevel::Value(value) => value.clamp(0, max),
+        }
+    }
+}
+
+/// Sets the keyboard backlight when the system switches between AC and battery power.
+///
+/// ```rust,no_run
+/// use upower_dbus::{KbdBacklightAdjuster, KbdBacklightLevel, KbdBacklightProxy, UPowerProxy};
+///
+/// # fn main() -> upower_dbus::Result<()> {
+/// # futures::executor::block_on(async move {
+/// let connection = zbus::Connection::system().await?;
+/// let upower = UPowerProxy::new(&connection).await?;
+/// let backlight = KbdBacklightProxy::new(&connection).await?;
+///
+/// // Dim to a third on battery, and restore the previous brightness on AC.
+/// KbdBacklightAdjuster::new(Some(KbdBacklightLevel::Fraction(0.33)), None)
+///     .run(&upower, &backlight)
+///     .await
+/// # })
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct KbdBacklightAdjuster {
+    on_battery: Option<KbdBacklightLevel>,
+    on_ac: Option<KbdBacklightLevel>,
+    saved: Option<i32>,
+}
+
+impl KbdBacklightAdjuster {
+    /// Creates an adjuster which sets `on_battery` when switching to battery, and `on_ac`
+    /// when switching to AC.
+    ///
+    /// If `on_battery` is `None`, the brightness is left alone on battery. If `on_ac` is
+    /// `None`, the brightness from before switching to battery is restored.
+    pub fn new(on_battery: Option<KbdBacklightLevel>, on_ac: Option<KbdBacklightLevel>) -> Self {
+        Self {
+            on_battery,
+            on_ac,
+            saved: None,
+        }
+    }
+
+    /// Applies the level for the given power state.
+    pub async fn apply(
+        &mut self,
+        backlight: &KbdBacklightProxy<'_>,
+        on_battery: bool,
+    ) -> Result<()> {
+        let brightness = if on_battery {
+            let level = match self.on_battery {
+                Some(level) => level,
+                None => return Ok(()),
+            };
+
+            if self.saved.is_none() {
+                self.saved = Some(backlight.get_brightness().await?);
+            }
+
+            level.resolve(backlight.get_max_brightness().await?)
+        } else {
+            let saved = self.saved.take();
+            match (self.on_ac, saved) {
+                (Some(level), _) => level.resolve(backlight.get_max_brightness().await?),
+                (None, Some(saved)) => saved,
+                (None, None) => return Ok(()),
+            }
+        };
+
+        Ok(backlight.set_brightness(brightness).await?)
+    }
+
+    /// Applies the level for the current power state, and again after every change to it.
+    ///
+    /// Runs until watching the power state fails.
+    pub async fn run(
+        mut self,
+        upower: &UPowerProxy<'_>,
+        backlight: &KbdBacklightProxy<'_>,
+    ) -> Result<()> {
+        let mut changes = upower.receive_on_battery_changed().await;
+
+        let mut on_battery = upower.on_battery().await?;
+        self.apply(backlight, on_battery).await?;
+
+        while let Some(change) = changes.next().await {
+            let changed = change.get().await?;
+            if changed != on_battery {
+                on_battery = changed;
+                self.apply(backlight, on_battery).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
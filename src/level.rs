@@ -0,0 +1,79 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::stream::{self, Stream, StreamExt};
+use std::convert::TryFrom;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use zbus::zvariant::OwnedValue;
+use zbus::PropertyStream;
+
+use crate::device::{BatteryLevel, WarningLevel};
+use crate::error::Result;
+
+/// Emits `initial`, followed by each value of `changes` which differs from the one before it.
+pub(crate) fn property_values<'a, T>(
+    initial: T,
+    changes: PropertyStream<'a, T>,
+) -> impl Stream<Item = Result<T>> + Send + 'a
+where
+    T: TryFrom<OwnedValue> + PartialEq + Clone + Send + Sync + Unpin + 'a,
+    T::Error: Into<zbus::Error>,
+{
+    let changes = changes.then(|changed| async move { Ok(changed.get().await?) });
+
+    stream::once(async move { Ok(initial) })
+        .chain(changes)
+        .scan(None, |last, value| {
+            let value = match value {
+                Ok(value) if last.as_ref() == Some(&value) => None,
+                Ok(value) => {
+                    *last = Some(value.clone());
+                    Some(Ok(value))
+                }
+                Err(why) => Some(Err(why)),
+            };
+
+            async move { Some(value) }
+        })
+        .filter_map(|value| async move { value })
+}
+
+/// A stream of `WarningLevel` values, created by `DeviceProxy::watch_warning_level`.
+pub struct WarningLevelStream<'a> {
+    pub(crate) inner: Pin<Box<dyn Stream<Item = Result<WarningLevel>> + Send + 'a>>,
+}
+
+impl fmt::Debug for WarningLevelStream<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WarningLevelStream").finish_non_exhaustive()
+    }
+}
+
+impl Stream for WarningLevelStream<'_> {
+    type Item = Result<WarningLevel>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+/// A stream of `BatteryLevel` values, created by `DeviceProxy::watch_battery_level`.
+pub struct BatteryLevelStream<'a> {
+    pub(crate) inner: Pin<Box<dyn Stream<Item = Result<BatteryLevel>> + Send + 'a>>,
+}
+
+impl fmt::Debug for BatteryLevelStream<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BatteryLevelStream").finish_non_exhaustive()
+    }
+}
+
+impl Stream for BatteryLevelStream<'_> {
+    type Item = Result<BatteryLevel>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
@@ -2,8 +2,101 @@
 // SPDX-License-Identifier: MPL-2.0
 #![doc = include_str!("../README.md")]
 
+/// The version of zbus this crate is built against, for use by downstream crates whose own
+/// `zbus` dependency might otherwise resolve to an incompatible version.
+pub use zbus;
+/// The version of zvariant used by the re-exported `zbus`.
+pub use zbus::zvariant;
+
+#[cfg(not(any(feature = "async-io", feature = "tokio")))]
+compile_error!("either the `async-io` or the `tokio` feature must be enabled");
+
+mod aggregate;
+#[cfg(feature = "battery")]
+pub mod battery;
+mod blocking;
+mod cache;
+mod capabilities;
+mod change;
+mod connection;
+mod debounce;
 mod device;
+mod error;
+mod estimator;
+mod export;
+mod facade;
+#[cfg(all(feature = "mock", feature = "serde"))]
+pub mod fixture;
+mod handle;
+mod hotplug;
+mod icon;
+mod id;
+mod kbd_backlight;
+mod level;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "mock")]
+pub mod mock;
+mod monitor;
+mod names;
+#[cfg(feature = "notify")]
+pub mod notify;
+mod polling;
+#[cfg(feature = "power-profiles")]
+pub mod power_profiles;
+mod properties;
+mod sampler;
+mod session_log;
+mod shutdown;
+mod snapshot;
+mod source;
+mod status;
+#[cfg(feature = "sysfs")]
+pub mod sysfs;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod timeout;
+mod timer;
+mod units;
 mod upower;
+mod ups;
+mod version;
+mod wakeups;
+mod watch;
+mod watcher;
 
+pub use self::aggregate::*;
+pub use self::blocking::*;
+pub use self::cache::*;
+pub use self::capabilities::*;
+pub use self::change::*;
+pub use self::connection::*;
+pub use self::debounce::*;
 pub use self::device::*;
+pub use self::error::*;
+pub use self::estimator::*;
+pub use self::export::*;
+pub use self::facade::*;
+pub use self::handle::*;
+pub use self::hotplug::*;
+pub use self::icon::*;
+pub use self::id::*;
+pub use self::kbd_backlight::*;
+pub use self::level::*;
+pub use self::monitor::*;
+pub use self::names::*;
+pub use self::polling::*;
+pub use self::properties::*;
+pub use self::sampler::*;
+pub use self::session_log::*;
+pub use self::shutdown::*;
+pub use self::snapshot::*;
+pub use self::source::*;
+pub use self::status::*;
+pub use self::timeout::*;
+pub use self::units::*;
 pub use self::upower::*;
+pub use self::ups::*;
+pub use self::version::*;
+pub use self::wakeups::*;
+pub use self::watcher::*;
@@ -0,0 +1,320 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Exports battery readings in the Prometheus text format.
+//!
+//! An `Exporter` samples any `PowerSource`, and can serve its latest sample over HTTP for a
+//! Prometheus server to scrape.
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use upower_dbus::metrics::Exporter;
+//! use upower_dbus::UPowerProxy;
+//!
+//! # fn main() -> upower_dbus::Result<()> {
+//! # futures::executor::block_on(async move {
+//! let upower = UPowerProxy::system().await?;
+//! let exporter = Exporter::new();
+//! exporter.serve("127.0.0.1:9845")?;
+//! exporter.run(&upower, Duration::from_secs(30)).await
+//! # })
+//! # }
+//! ```
+
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::device::DeviceType;
+use crate::error::Result;
+use crate::snapshot::DeviceSnapshot;
+use crate::source::PowerSource;
+use crate::timer;
+
+/// The most bytes read from a request, up to the end of its headers.
+pub const MAX_REQUEST_SIZE: u64 = 8 * 1024;
+
+/// How long a client has to send the headers of its request.
+pub const REQUEST_DEADLINE: Duration = Duration::from_secs(5);
+
+/// How many connections are answered at once, past which new ones are closed.
+const MAX_CONNECTIONS: usize = 64;
+
+/// Renders the on-battery state and a gauge per battery reading, in the Prometheus text
+/// format.
+///
+/// Batteries are labeled by their native path, vendor, and model. Temperatures and charge
+/// cycles are omitted for batteries which do not report them.
+pub fn render(on_battery: bool, devices: &[DeviceSnapshot]) -> String {
+    let batteries: Vec<&DeviceSnapshot> = devices
+        .iter()
+        .filter(|device| device.type_ == DeviceType::Battery)
+        .collect();
+
+    let mut text = String::new();
+
+    gauge(
+        &mut text,
+        "upower_on_battery",
+        "Whether the system is running on battery power.",
+    );
+    let _ = writeln!(text, "upower_on_battery {}", on_battery as u8);
+
+    let mut series = |name: &str, help: &str, value: fn(&DeviceSnapshot) -> Option<f64>| {
+        gauge(&mut text, name, help);
+        for battery in &batteries {
+            if let Some(value) = value(battery) {
+                let _ = writeln!(text, "{}{{{}}} {}", name, labels(battery), value);
+            }
+        }
+    };
+
+    series(
+        "upower_battery_percentage",
+        "The charge of the battery, in percent.",
+        |battery| Some(battery.percentage.get()),
+    );
+    series(
+        "upower_battery_energy_watt_hours",
+        "The energy stored in the battery, in Wh.",
+        |battery| Some(battery.energy.0),
+    );
+    series(
+        "upower_battery_energy_rate_watts",
+        "The rate at which the battery is charging or discharging, in W.",
+        |battery| Some(battery.energy_rate.0),
+    );
+    series(
+        "upower_battery_capacity_percent",
+        "The full energy of the battery relative to its design energy, in percent.",
+        |battery| Some(battery.capacity),
+    );
+    series(
+        "upower_battery_temperature_celsius",
+        "The temperature of the battery, in degrees Celsius.",
+        |battery| battery.temperature.map(|celsius| celsius.0),
+    );
+    series(
+        "upower_battery_charge_cycles",
+        "The number of charge cycles of the battery.",
+        |battery| battery.charge_cycles.map(f64::from),
+    );
+
+    text
+}
+
+fn gauge(text: &mut String, name: &str, help: &str) {
+    let _ = writeln!(text, "# HELP {} {}", name, help);
+    let _ = writeln!(text, "# TYPE {} gauge", name);
+}
+
+fn labels(battery: &DeviceSnapshot) -> String {
+    fn escape(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    }
+
+    format!(
+        "native_path=\"{}\",vendor=\"{}\",model=\"{}\"",
+        escape(&battery.native_path),
+        escape(&battery.vendor),
+        escape(&battery.model)
+    )
+}
+
+/// Holds the latest sample of a `PowerSource`, and serves it over HTTP.
+#[derive(Clone, Debug, Default)]
+pub struct Exporter {
+    text: Arc<Mutex<String>>,
+}
+
+impl Exporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The latest sample, in the Prometheus text format.
+    pub fn text(&self) -> String {
+        self.text
+            .lock()
+            .unwrap_or_else(|why| why.into_inner())
+            .clone()
+    }
+
+    /// Samples `source` once, replacing the previous sample.
+    pub async fn sample<S: PowerSource + ?Sized>(&self, source: &S) -> Result<()> {
+        let on_battery = source.on_battery().await?;
+        let devices = source.device_snapshots().await?;
+        *self.text.lock().unwrap_or_else(|why| why.into_inner()) = render(on_battery, &devices);
+        Ok(())
+    }
+
+    /// Samples `source` every `interval`, until sampling fails.
+    pub async fn run<S: PowerSource + ?Sized>(&self, source: &S, interval: Duration) -> Result<()> {
+        loop {
+            self.sample(source).await?;
+            timer::sleep(interval).await;
+        }
+    }
+
+    /// Serves the latest sample to every HTTP request on `address`, from a new thread.
+    ///
+    /// Each connection is answered from a thread of its own, so that a slow client does not
+    /// delay the others. A request must send its headers within `MAX_REQUEST_SIZE` bytes and
+    /// `REQUEST_DEADLINE`, or its connection is closed unanswered.
+    pub fn serve<A: ToSocketAddrs>(&self, address: A) -> io::Result<thread::JoinHandle<()>> {
+        let listener = TcpListener::bind(address)?;
+        let exporter = self.clone();
+
+        Ok(thread::spawn(move || exporter.accept(listener)))
+    }
+
+    fn accept(&self, listener: TcpListener) {
+        let connections = Arc::new(AtomicUsize::new(0));
+
+        for stream in listener.incoming().flatten() {
+            // Connections past the limit are closed at once, rather than queued behind the
+            // ones being answered.
+            if connections.fetch_add(1, Ordering::AcqRel) >= MAX_CONNECTIONS {
+                connections.fetch_sub(1, Ordering::AcqRel);
+                continue;
+            }
+
+            let exporter = self.clone();
+            let active = connections.clone();
+            let spawned = thread::Builder::new()
+                .name("metrics-connection".into())
+                .spawn(move || {
+                    let _ = exporter.respond(stream);
+                    active.fetch_sub(1, Ordering::AcqRel);
+                });
+
+            if spawned.is_err() {
+                connections.fetch_sub(1, Ordering::AcqRel);
+            }
+        }
+    }
+
+    fn respond(&self, mut stream: TcpStream) -> io::Result<()> {
+        // The request is read up to the blank line ending its headers, and otherwise ignored.
+        {
+            let deadline = Deadline {
+                stream: &stream,
+                deadline: Instant::now() + REQUEST_DEADLINE,
+            };
+
+            let mut reader = BufReader::new(deadline.take(MAX_REQUEST_SIZE));
+            let mut line = String::new();
+            loop {
+                line.clear();
+                if reader.read_line(&mut line)? == 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "request ended or exceeded the size limit before its headers did",
+                    ));
+                }
+
+                if line.trim_end().is_empty() {
+                    break;
+                }
+            }
+        }
+
+        let body = self.text();
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    }
+}
+
+/// Reads from a stream until a deadline, however slowly the peer sends.
+struct Deadline<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Read for Deadline<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self
+            .deadline
+            .checked_duration_since(Instant::now())
+            .filter(|remaining| !remaining.is_zero())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "request deadline passed"))?;
+
+        self.stream.set_read_timeout(Some(remaining))?;
+        let mut stream = self.stream;
+        stream.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Exporter;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    fn serve(text: &str) -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let exporter = Exporter::new();
+        *exporter.text.lock().unwrap() = text.to_owned();
+        thread::spawn(move || exporter.accept(listener));
+
+        address
+    }
+
+    fn get(address: std::net::SocketAddr, request: &[u8]) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        let _ = stream.write_all(request);
+
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response);
+        response
+    }
+
+    #[test]
+    fn answers_with_the_sample() {
+        let address = serve("upower_on_battery 1\n");
+        let response = get(address, b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\nupower_on_battery 1\n"));
+    }
+
+    #[test]
+    fn oversized_headers_are_not_answered() {
+        let address = serve("upower_on_battery 1\n");
+
+        let mut request = b"GET /metrics HTTP/1.1\r\n".to_vec();
+        while request.len() as u64 <= super::MAX_REQUEST_SIZE {
+            request.extend_from_slice(b"X-Padding: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\r\n");
+        }
+        request.extend_from_slice(b"\r\n");
+
+        assert_eq!(get(address, &request), "");
+    }
+
+    #[test]
+    fn slow_clients_do_not_block_others() {
+        let address = serve("upower_on_battery 1\n");
+
+        // Holds a connection open without finishing its request.
+        let mut slow = TcpStream::connect(address).unwrap();
+        slow.write_all(b"GET /metrics HTTP/1.1\r\n").unwrap();
+
+        let response = get(address, b"GET /metrics HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        drop(slow);
+    }
+}
@@ -0,0 +1,767 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! A mock UPower daemon, for testing applications without real hardware.
+//!
+//! The mock serves `org.freedesktop.UPower` and `org.freedesktop.UPower.Device` on any
+//! connection, such as one to a private `dbus-daemon`, so the crate's proxies can talk to it
+//! exactly as they would to upowerd.
+//!
+//! ```rust,no_run
+//! use upower_dbus::mock::{self, MockUPower};
+//! use upower_dbus::{DeviceType, Percentage, UPowerProxy};
+//!
+//! # fn main() -> upower_dbus::Result<()> {
+//! # futures::executor::block_on(async move {
+//! let server = zbus::ConnectionBuilder::address("unix:path=/tmp/test-bus")?.build().await?;
+//! let mock = MockUPower::new(&server).await?;
+//!
+//! let mut battery = mock::device(DeviceType::Battery);
+//! battery.percentage = Percentage::new(42.0);
+//! let path = mock.add_device("battery_BAT0", battery).await?;
+//!
+//! let client = zbus::ConnectionBuilder::address("unix:path=/tmp/test-bus")?.build().await?;
+//! let upower = UPowerProxy::new(&client).await?;
+//! assert_eq!(upower.device(path).await?.percentage().await?.get(), 42.0);
+//! # Ok(())
+//! # })
+//! # }
+//! ```
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::time::Duration;
+use zbus::fdo::Properties;
+use zbus::names::InterfaceName;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue};
+use zbus::{dbus_interface, Connection, Interface, SignalContext};
+
+use crate::capabilities::DeviceCapabilities;
+use crate::change::DeviceChange;
+use crate::device::{BatteryLevel, BatteryState, DeviceType};
+use crate::error::Result;
+use crate::monitor::PowerEventStream;
+use crate::names::{
+    DEVICES_PATH, DEVICE_INTERFACE, DISPLAY_DEVICE_PATH, UPOWER_PATH, UPOWER_SERVICE,
+};
+use crate::snapshot::DeviceSnapshot;
+use crate::source::PowerSource;
+use crate::units::{Celsius, Percentage, Volts, WattHours, Watts};
+use crate::upower::UPowerProxy;
+
+struct UPowerInterface {
+    devices: Vec<OwnedObjectPath>,
+    on_battery: bool,
+    lid_is_closed: bool,
+    lid_is_present: bool,
+}
+
+#[dbus_interface(name = "org.freedesktop.UPower")]
+impl UPowerInterface {
+    fn enumerate_devices(&self) -> Vec<OwnedObjectPath> {
+        self.devices.clone()
+    }
+
+    fn get_critical_action(&self) -> String {
+        String::from("PowerOff")
+    }
+
+    fn get_display_device(&self) -> OwnedObjectPath {
+        ObjectPath::from_static_str_unchecked(MockUPower::DISPLAY_DEVICE).into()
+    }
+
+    #[dbus_interface(signal)]
+    async fn device_added(ctxt: &SignalContext<'_>, device: ObjectPath<'_>) -> zbus::Result<()>;
+
+    #[dbus_interface(signal)]
+    async fn device_removed(ctxt: &SignalContext<'_>, device: ObjectPath<'_>) -> zbus::Result<()>;
+
+    #[dbus_interface(property)]
+    fn daemon_version(&self) -> String {
+        String::from("1.90.0")
+    }
+
+    #[dbus_interface(property)]
+    fn lid_is_closed(&self) -> bool {
+        self.lid_is_closed
+    }
+
+    #[dbus_interface(property)]
+    fn lid_is_present(&self) -> bool {
+        self.lid_is_present
+    }
+
+    #[dbus_interface(property)]
+    fn on_battery(&self) -> bool {
+        self.on_battery
+    }
+}
+
+struct DeviceInterface(DeviceSnapshot);
+
+#[dbus_interface(name = "org.freedesktop.UPower.Device")]
+impl DeviceInterface {
+    fn get_history(&self, _kind: &str, _timespan: u32, _resolution: u32) -> Vec<(u32, f64, u32)> {
+        Vec::new()
+    }
+
+    fn get_statistics(&self, _kind: &str) -> Vec<(f64, f64)> {
+        Vec::new()
+    }
+
+    async fn enable_charge_threshold(
+        &mut self,
+        enabled: bool,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> zbus::fdo::Result<()> {
+        if self.0.charge_threshold_supported != Some(true) {
+            return Err(zbus::fdo::Error::NotSupported(String::from(
+                "charge thresholds are not supported",
+            )));
+        }
+
+        self.0.charge_threshold_enabled = Some(enabled);
+        self.charge_threshold_enabled_changed(&ctxt).await?;
+        Ok(())
+    }
+
+    fn refresh(&self) {}
+
+    #[dbus_interface(property)]
+    fn battery_level(&self) -> u32 {
+        u32::from(self.0.battery_level)
+    }
+
+    #[dbus_interface(property)]
+    fn capacity(&self) -> f64 {
+        self.0.capacity
+    }
+
+    #[dbus_interface(property)]
+    fn charge_cycles(&self) -> i32 {
+        self.0.charge_cycles.unwrap_or(-1)
+    }
+
+    #[dbus_interface(property)]
+    fn charge_end_threshold(&self) -> u32 {
+        self.0.charge_end_threshold.unwrap_or(100)
+    }
+
+    #[dbus_interface(property)]
+    fn charge_start_threshold(&self) -> u32 {
+        self.0.charge_start_threshold.unwrap_or(0)
+    }
+
+    #[dbus_interface(property)]
+    fn charge_threshold_enabled(&self) -> bool {
+        self.0.charge_threshold_enabled.unwrap_or(false)
+    }
+
+    #[dbus_interface(property)]
+    fn charge_threshold_supported(&self) -> bool {
+        self.0.charge_threshold_supported.unwrap_or(false)
+    }
+
+    #[dbus_interface(property)]
+    fn energy(&self) -> f64 {
+        self.0.energy.0
+    }
+
+    #[dbus_interface(property)]
+    fn energy_empty(&self) -> f64 {
+        self.0.energy_empty.0
+    }
+
+    #[dbus_interface(property)]
+    fn energy_full(&self) -> f64 {
+        self.0.energy_full.0
+    }
+
+    #[dbus_interface(property)]
+    fn energy_full_design(&self) -> f64 {
+        self.0.energy_full_design.0
+    }
+
+    #[dbus_interface(property)]
+    fn energy_rate(&self) -> f64 {
+        self.0.energy_rate.0
+    }
+
+    #[dbus_interface(property)]
+    fn has_history(&self) -> bool {
+        self.0.has_history
+    }
+
+    #[dbus_interface(property)]
+    fn has_statistics(&self) -> bool {
+        self.0.has_statistics
+    }
+
+    #[dbus_interface(property)]
+    fn icon_name(&self) -> String {
+        self.0.icon_name.clone()
+    }
+
+    #[dbus_interface(property)]
+    fn is_present(&self) -> bool {
+        self.0.is_present
+    }
+
+    #[dbus_interface(property)]
+    fn is_rechargeable(&self) -> bool {
+        self.0.is_rechargeable
+    }
+
+    #[dbus_interface(property)]
+    fn luminosity(&self) -> f64 {
+        self.0.luminosity
+    }
+
+    #[dbus_interface(property)]
+    fn model(&self) -> String {
+        self.0.model.clone()
+    }
+
+    #[dbus_interface(property)]
+    fn native_path(&self) -> String {
+        self.0.native_path.clone()
+    }
+
+    #[dbus_interface(property)]
+    fn online(&self) -> bool {
+        self.0.online
+    }
+
+    #[dbus_interface(property)]
+    fn percentage(&self) -> f64 {
+        self.0.percentage.get()
+    }
+
+    #[dbus_interface(property)]
+    fn power_supply(&self) -> bool {
+        self.0.power_supply
+    }
+
+    #[dbus_interface(property)]
+    fn serial(&self) -> String {
+        self.0.serial.clone()
+    }
+
+    #[dbus_interface(property)]
+    fn state(&self) -> u32 {
+        u32::from(self.0.state)
+    }
+
+    #[dbus_interface(property)]
+    fn technology(&self) -> u32 {
+        u32::from(self.0.technology)
+    }
+
+    #[dbus_interface(property)]
+    fn temperature(&self) -> f64 {
+        self.0.temperature.map_or(0.0, |celsius| celsius.0)
+    }
+
+    #[dbus_interface(property)]
+    fn time_to_empty(&self) -> i64 {
+        self.0.time_to_empty
+    }
+
+    #[dbus_interface(property)]
+    fn time_to_full(&self) -> i64 {
+        self.0.time_to_full
+    }
+
+    #[dbus_interface(property, name = "Type")]
+    fn type_(&self) -> u32 {
+        u32::from(self.0.type_)
+    }
+
+    #[dbus_interface(property)]
+    fn update_time(&self) -> u64 {
+        self.0.update_time
+    }
+
+    #[dbus_interface(property)]
+    fn vendor(&self) -> String {
+        self.0.vendor.clone()
+    }
+
+    #[dbus_interface(property)]
+    fn voltage(&self) -> f64 {
+        self.0.voltage.0
+    }
+
+    #[dbus_interface(property)]
+    fn warning_level(&self) -> u32 {
+        u32::from(self.0.warning_level)
+    }
+}
+
+/// Creates the properties of a device of the given kind, with every other value unknown.
+pub fn device(type_: DeviceType) -> DeviceSnapshot {
+    DeviceSnapshot::new(type_)
+}
+
+/// Builds the properties of a mock device, for `MockUPower::add_device`.
+///
+/// ```rust
+/// use upower_dbus::mock::MockDeviceBuilder;
+/// use upower_dbus::{BatteryState, DeviceCapabilities, DeviceType, Watts};
+///
+/// let battery = MockDeviceBuilder::new(DeviceType::Battery)
+///     .percentage(50.0)
+///     .state(BatteryState::Discharging)
+///     .energy_rate(Watts(8.5))
+///     .capabilities(DeviceCapabilities::ENERGY | DeviceCapabilities::VOLTAGE)
+///     .build();
+///
+/// assert_eq!(battery.energy.0, 25.0);
+/// assert!(battery.capabilities().contains(DeviceCapabilities::VOLTAGE));
+/// ```
+#[derive(Clone, Debug)]
+pub struct MockDeviceBuilder {
+    device: DeviceSnapshot,
+    capabilities: DeviceCapabilities,
+}
+
+impl MockDeviceBuilder {
+    /// Starts from the properties created by `device`.
+    pub fn new(type_: DeviceType) -> Self {
+        Self {
+            device: device(type_),
+            capabilities: DeviceCapabilities::empty(),
+        }
+    }
+
+    pub fn percentage(mut self, percentage: f64) -> Self {
+        self.device.percentage = Percentage::new(percentage);
+        self
+    }
+
+    pub fn state(mut self, state: BatteryState) -> Self {
+        self.device.state = state;
+        self
+    }
+
+    pub fn energy_rate(mut self, rate: Watts) -> Self {
+        self.device.energy_rate = rate;
+        self
+    }
+
+    pub fn time_to_empty(mut self, time: Duration) -> Self {
+        self.device.time_to_empty = time.as_secs() as i64;
+        self
+    }
+
+    pub fn time_to_full(mut self, time: Duration) -> Self {
+        self.device.time_to_full = time.as_secs() as i64;
+        self
+    }
+
+    pub fn vendor<S: Into<String>>(mut self, vendor: S) -> Self {
+        self.device.vendor = vendor.into();
+        self
+    }
+
+    pub fn model<S: Into<String>>(mut self, model: S) -> Self {
+        self.device.model = model.into();
+        self
+    }
+
+    pub fn native_path<S: Into<String>>(mut self, native_path: S) -> Self {
+        self.device.native_path = native_path.into();
+        self
+    }
+
+    /// Gives the device the optional properties of `capabilities`, filled with plausible
+    /// values when built. Values derived from the charge, such as the stored energy, follow
+    /// the percentage given.
+    ///
+    /// A `BATTERY_LEVEL` replaces the percentage, as it does on peripherals which report one,
+    /// and a `CAPACITY` implies the `ENERGY` and `DESIGN_ENERGY` it is derived from.
+    pub fn capabilities(mut self, capabilities: DeviceCapabilities) -> Self {
+        self.capabilities |= capabilities;
+        self
+    }
+
+    /// The properties of the device.
+    pub fn build(self) -> DeviceSnapshot {
+        let Self {
+            mut device,
+            mut capabilities,
+        } = self;
+
+        if capabilities.contains(DeviceCapabilities::CAPACITY) {
+            capabilities |= DeviceCapabilities::ENERGY | DeviceCapabilities::DESIGN_ENERGY;
+        }
+
+        let percentage = device.percentage.get();
+        let has = |capability| capabilities.contains(capability);
+
+        if has(DeviceCapabilities::BATTERY_LEVEL) {
+            device.battery_level = match percentage {
+                p if p >= 95.0 => BatteryLevel::Full,
+                p if p >= 60.0 => BatteryLevel::High,
+                p if p >= 20.0 => BatteryLevel::Normal,
+                p if p >= 10.0 => BatteryLevel::Low,
+                _ => BatteryLevel::Critical,
+            };
+        }
+
+        if has(DeviceCapabilities::ENERGY) {
+            device.energy_full = WattHours(50.0);
+            device.energy = WattHours(50.0 * percentage / 100.0);
+        }
+
+        if has(DeviceCapabilities::DESIGN_ENERGY) {
+            device.energy_full_design = WattHours(55.0);
+        }
+
+        if has(DeviceCapabilities::VOLTAGE) {
+            device.voltage = Volts(12.0);
+        }
+
+        if has(DeviceCapabilities::TEMPERATURE) {
+            device.temperature = Some(Celsius(30.0));
+        }
+
+        if has(DeviceCapabilities::CHARGE_CYCLES) {
+            device.charge_cycles = Some(100);
+        }
+
+        if has(DeviceCapabilities::CAPACITY) {
+            device.capacity = device.energy_full.0 / device.energy_full_design.0 * 100.0;
+        }
+
+        if has(DeviceCapabilities::CHARGE_THRESHOLDS) {
+            device.charge_threshold_supported = Some(true);
+            device.charge_threshold_enabled = Some(false);
+            device.charge_start_threshold = Some(75);
+            device.charge_end_threshold = Some(80);
+        }
+
+        device.has_history |= has(DeviceCapabilities::HISTORY);
+        device.has_statistics |= has(DeviceCapabilities::STATISTICS);
+
+        if has(DeviceCapabilities::LUMINOSITY) {
+            device.luminosity = 100.0;
+        }
+
+        if has(DeviceCapabilities::SERIAL) {
+            device.serial = String::from("MOCK0001");
+        }
+
+        device
+    }
+}
+
+impl From<MockDeviceBuilder> for DeviceSnapshot {
+    fn from(builder: MockDeviceBuilder) -> Self {
+        builder.build()
+    }
+}
+
+/// A mock UPower daemon served on a connection.
+#[derive(Clone, Debug)]
+pub struct MockUPower {
+    connection: Connection,
+}
+
+impl MockUPower {
+    /// The object path of the mock's display device.
+    pub const DISPLAY_DEVICE: &'static str = DISPLAY_DEVICE_PATH;
+
+    /// Serves the daemon and its display device on `connection`, and requests the
+    /// `org.freedesktop.UPower` name.
+    pub async fn new(connection: &Connection) -> zbus::Result<Self> {
+        Self::with_name(connection, UPOWER_SERVICE).await
+    }
+
+    /// Like `new`, but requests `name` instead, for use with the `builder_for` constructors.
+    pub async fn with_name(connection: &Connection, name: &str) -> zbus::Result<Self> {
+        let upower = UPowerInterface {
+            devices: Vec::new(),
+            on_battery: false,
+            lid_is_closed: false,
+            lid_is_present: false,
+        };
+
+        let mut display = device(DeviceType::Battery);
+        display.power_supply = true;
+
+        let object_server = connection.object_server();
+        object_server.at(UPOWER_PATH, upower).await?;
+        object_server
+            .at(Self::DISPLAY_DEVICE, DeviceInterface(display))
+            .await?;
+
+        connection.request_name(name.to_owned()).await?;
+
+        Ok(Self {
+            connection: connection.clone(),
+        })
+    }
+
+    /// The connection the mock is served on.
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Adds a device at `/org/freedesktop/UPower/devices/{name}`, emitting `DeviceAdded`.
+    pub async fn add_device<D: Into<DeviceSnapshot>>(
+        &self,
+        name: &str,
+        device: D,
+    ) -> zbus::Result<OwnedObjectPath> {
+        let path = OwnedObjectPath::try_from(format!("{}/{}", DEVICES_PATH, name))?;
+
+        let object_server = self.connection.object_server();
+        object_server
+            .at(path.as_ref(), DeviceInterface(device.into()))
+            .await?;
+
+        let upower = object_server
+            .interface::<_, UPowerInterface>(UPOWER_PATH)
+            .await?;
+        upower.get_mut().await.devices.push(path.clone());
+        UPowerInterface::device_added(upower.signal_context(), path.as_ref()).await?;
+
+        Ok(path)
+    }
+
+    /// Removes a device previously added with `add_device`, emitting `DeviceRemoved`.
+    pub async fn remove_device(&self, path: &ObjectPath<'_>) -> zbus::Result<()> {
+        let object_server = self.connection.object_server();
+        object_server.remove::<DeviceInterface, _>(path).await?;
+
+        let upower = object_server
+            .interface::<_, UPowerInterface>(UPOWER_PATH)
+            .await?;
+        upower
+            .get_mut()
+            .await
+            .devices
+            .retain(|device| device.as_ref() != *path);
+        UPowerInterface::device_removed(upower.signal_context(), path.clone()).await
+    }
+
+    /// Sets the `OnBattery` property, emitting `PropertiesChanged`.
+    pub async fn set_on_battery(&self, on_battery: bool) -> zbus::Result<()> {
+        let upower = self.upower().await?;
+        let mut interface = upower.get_mut().await;
+        interface.on_battery = on_battery;
+        interface.on_battery_changed(upower.signal_context()).await
+    }
+
+    /// Sets the `LidIsClosed` property, emitting `PropertiesChanged`.
+    pub async fn set_lid_is_closed(&self, lid_is_closed: bool) -> zbus::Result<()> {
+        let upower = self.upower().await?;
+        let mut interface = upower.get_mut().await;
+        interface.lid_is_present = true;
+        interface.lid_is_closed = lid_is_closed;
+        interface
+            .lid_is_closed_changed(upower.signal_context())
+            .await
+    }
+
+    /// Sets the `Percentage` property of a device, emitting `PropertiesChanged`.
+    pub async fn set_percentage(&self, path: &ObjectPath<'_>, percentage: f64) -> zbus::Result<()> {
+        let device = self.device(path).await?;
+        let mut interface = device.get_mut().await;
+        interface.0.percentage = Percentage::new(percentage);
+        interface.percentage_changed(device.signal_context()).await
+    }
+
+    /// Sets the `State` property of a device, emitting `PropertiesChanged`.
+    pub async fn set_state(&self, path: &ObjectPath<'_>, state: BatteryState) -> zbus::Result<()> {
+        let device = self.device(path).await?;
+        let mut interface = device.get_mut().await;
+        interface.0.state = state;
+        interface.state_changed(device.signal_context()).await
+    }
+
+    /// Sets properties of a device, given as the daemon sends them, emitting
+    /// `PropertiesChanged` with the values given.
+    pub async fn set_device_properties(
+        &self,
+        path: &ObjectPath<'_>,
+        properties: HashMap<String, OwnedValue>,
+    ) -> zbus::Result<()> {
+        let device = self.device(path).await?;
+        let mut interface = device.get_mut().await;
+
+        let change = DeviceChange::try_from(properties.clone())
+            .map_err(|why| zbus::Error::Failure(why.to_string()))?;
+        change.apply_to(&mut interface.0);
+
+        let changed = properties
+            .iter()
+            .map(|(name, value)| (name.as_str(), &**value))
+            .collect();
+
+        Properties::properties_changed(
+            device.signal_context(),
+            InterfaceName::from_static_str_unchecked(DEVICE_INTERFACE),
+            &changed,
+            &[],
+        )
+        .await
+    }
+
+    /// Changes the properties of a device with `update`, emitting `PropertiesChanged` with
+    /// those which differ afterwards.
+    pub async fn update_device(
+        &self,
+        path: &ObjectPath<'_>,
+        update: impl FnOnce(&mut DeviceSnapshot),
+    ) -> zbus::Result<()> {
+        let device = self.device(path).await?;
+        let mut interface = device.get_mut().await;
+
+        let older = interface.0.clone();
+        update(&mut interface.0);
+        let names = DeviceChange::between(&older, &interface.0).changed_properties();
+        if names.is_empty() {
+            return Ok(());
+        }
+
+        let mut values = Vec::with_capacity(names.len());
+        for name in names {
+            if let Some(value) = Interface::get(&*interface, name).await {
+                values.push((name, value?));
+            }
+        }
+
+        let changed = values
+            .iter()
+            .map(|(name, value)| (*name, &**value))
+            .collect();
+
+        Properties::properties_changed(
+            device.signal_context(),
+            InterfaceName::from_static_str_unchecked(DEVICE_INTERFACE),
+            &changed,
+            &[],
+        )
+        .await
+    }
+
+    /// Moves the `Percentage` of a device to `to` in `steps` equal steps spread over `over`,
+    /// emitting `PropertiesChanged` for each.
+    ///
+    /// The time is faked rather than waited for: each step advances `UpdateTime` by its share
+    /// of `over`, and sets the state, energy, rate and time remaining as a device charging or
+    /// discharging at that pace would report them. Draining from 50% to 5% over ten minutes
+    /// takes only as long as sending the signals.
+    ///
+    /// A zero `over` moves the percentage in a single step, leaving the state, rate and time
+    /// remaining as they were, since no pace follows from it.
+    pub async fn ramp_percentage(
+        &self,
+        path: &ObjectPath<'_>,
+        to: f64,
+        over: Duration,
+        steps: u32,
+    ) -> zbus::Result<()> {
+        let (from, started) = {
+            let device = self.device(path).await?;
+            let interface = device.get().await;
+            (interface.0.percentage.get(), interface.0.update_time)
+        };
+
+        let seconds = over.as_secs_f64();
+        // In percentage points per second.
+        let (steps, pace) = if over.is_zero() {
+            (1, None)
+        } else {
+            (steps.max(1), Some((to - from) / seconds))
+        };
+
+        for step in 1..=steps {
+            let progress = f64::from(step) / f64::from(steps);
+            let percentage = from + (to - from) * progress;
+
+            self.update_device(path, |device| {
+                device.percentage = Percentage::new(percentage);
+                device.update_time = started + (seconds * progress).round() as u64;
+
+                if device.energy_full.0 > 0.0 {
+                    device.energy = WattHours(device.energy_full.0 * percentage / 100.0);
+                    if let Some(pace) = pace {
+                        device.energy_rate = Watts(device.energy_full.0 * pace.abs() * 36.0);
+                    }
+                }
+
+                match pace {
+                    Some(pace) if pace < 0.0 => {
+                        device.state = BatteryState::Discharging;
+                        device.time_to_empty = (percentage / -pace) as i64;
+                        device.time_to_full = 0;
+                    }
+                    Some(pace) if pace > 0.0 => {
+                        device.state = BatteryState::Charging;
+                        device.time_to_empty = 0;
+                        device.time_to_full = ((100.0 - percentage) / pace) as i64;
+                    }
+                    _ => (),
+                }
+            })
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn upower(&self) -> zbus::Result<zbus::InterfaceRef<UPowerInterface>> {
+        self.connection.object_server().interface(UPOWER_PATH).await
+    }
+
+    async fn device(
+        &self,
+        path: &ObjectPath<'_>,
+    ) -> zbus::Result<zbus::InterfaceRef<DeviceInterface>> {
+        self.connection.object_server().interface(path).await
+    }
+}
+
+/// Reads the mock's state directly, without a round-trip through the bus.
+///
+/// The event stream is that of a `UPowerProxy` on the mock's own connection.
+#[async_trait]
+impl PowerSource for MockUPower {
+    async fn on_battery(&self) -> Result<bool> {
+        Ok(self.upower().await?.get().await.on_battery)
+    }
+
+    async fn display_snapshot(&self) -> Result<DeviceSnapshot> {
+        let display = ObjectPath::from_static_str_unchecked(Self::DISPLAY_DEVICE);
+        Ok(self.device(&display).await?.get().await.0.clone())
+    }
+
+    async fn device_snapshots(&self) -> Result<Vec<DeviceSnapshot>> {
+        let paths = self.upower().await?.get().await.devices.clone();
+        let mut devices = Vec::with_capacity(paths.len());
+
+        for path in paths {
+            devices.push(self.device(&path).await?.get().await.0.clone());
+        }
+
+        Ok(devices)
+    }
+
+    async fn events(&self) -> Result<PowerEventStream<'static>> {
+        let name = self
+            .connection
+            .unique_name()
+            .ok_or(zbus::Error::MissingParameter("unique name"))?
+            .to_owned();
+
+        UPowerProxy::builder_for(&self.connection, name)?
+            .build()
+            .await?
+            .monitor()
+            .await
+    }
+}
@@ -0,0 +1,88 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::stream::Stream;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use zbus::zvariant::OwnedObjectPath;
+
+use crate::device::DeviceProxy;
+use crate::error::Result;
+use crate::snapshot::DeviceSnapshot;
+
+/// A change in the power state of the system, emitted by `UPowerProxy::monitor`.
+#[derive(Clone, Debug)]
+pub enum PowerEvent {
+    /// The system switched between AC and battery power.
+    OnBatteryChanged(bool),
+    /// A property of the display device changed.
+    DisplayDeviceChanged(Box<DeviceSnapshot>),
+    /// A device was connected.
+    DeviceAdded(DeviceProxy<'static>),
+    /// A device was disconnected.
+    DeviceRemoved(OwnedObjectPath),
+    /// The lid was opened or closed.
+    LidClosed(bool),
+    /// The daemon was restarted, and values cached by existing proxies may be stale.
+    ///
+    /// If `MonitorOptions::resync_on_restart` is set, this is followed by the current state
+    /// of the daemon.
+    ServiceRestarted,
+}
+
+/// Options for `UPowerProxy::monitor_with`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MonitorOptions {
+    resync_on_restart: bool,
+    poll_interval: Option<Duration>,
+}
+
+impl MonitorOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// After `PowerEvent::ServiceRestarted`, re-read the state of the daemon and emit it as
+    /// `OnBatteryChanged`, `LidClosed`, `DisplayDeviceChanged`, and a `DeviceAdded` for every
+    /// device present.
+    pub fn resync_on_restart(mut self, resync: bool) -> Self {
+        self.resync_on_restart = resync;
+        self
+    }
+
+    /// If `Some`, read the daemon at this interval through a `PollingMonitor` instead of
+    /// listening for signals, for environments which do not deliver them.
+    pub fn poll_interval(mut self, interval: Option<Duration>) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    pub(crate) fn resyncs_on_restart(&self) -> bool {
+        self.resync_on_restart
+    }
+
+    pub(crate) fn polls_every(&self) -> Option<Duration> {
+        self.poll_interval
+    }
+}
+
+/// A stream of `PowerEvent`s, created by `UPowerProxy::monitor`.
+pub struct PowerEventStream<'a> {
+    pub(crate) inner: Pin<Box<dyn Stream<Item = Result<PowerEvent>> + Send + 'a>>,
+}
+
+impl fmt::Debug for PowerEventStream<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PowerEventStream").finish_non_exhaustive()
+    }
+}
+
+impl Stream for PowerEventStream<'_> {
+    type Item = Result<PowerEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
@@ -0,0 +1,32 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+/// The well-known bus name of the daemon.
+pub const UPOWER_SERVICE: &str = "org.freedesktop.UPower";
+
+/// The interface of the daemon object.
+pub const UPOWER_INTERFACE: &str = "org.freedesktop.UPower";
+
+/// The object path of the daemon.
+pub const UPOWER_PATH: &str = "/org/freedesktop/UPower";
+
+/// The interface of each device object.
+pub const DEVICE_INTERFACE: &str = "org.freedesktop.UPower.Device";
+
+/// The object path below which devices are exported.
+pub const DEVICES_PATH: &str = "/org/freedesktop/UPower/devices";
+
+/// The object path of the composite display device.
+pub const DISPLAY_DEVICE_PATH: &str = "/org/freedesktop/UPower/devices/DisplayDevice";
+
+/// The interface of the keyboard backlight object.
+pub const KBD_BACKLIGHT_INTERFACE: &str = "org.freedesktop.UPower.KbdBacklight";
+
+/// The object path of the keyboard backlight.
+pub const KBD_BACKLIGHT_PATH: &str = "/org/freedesktop/UPower/KbdBacklight";
+
+/// The interface of the wakeups object.
+pub const WAKEUPS_INTERFACE: &str = "org.freedesktop.UPower.Wakeups";
+
+/// The object path of the wakeups object.
+pub const WAKEUPS_PATH: &str = "/org/freedesktop/UPower/Wakeups";
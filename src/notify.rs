@@ -0,0 +1,233 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Turns warning level changes of the display device into desktop notifications.
+//!
+//! Notifications are sent through the `org.freedesktop.Notifications` service on the session
+//! bus, so no notification library is needed.
+//!
+//! ```rust,no_run
+//! use upower_dbus::notify::{NotificationsProxy, WarningNotifier};
+//! use upower_dbus::UPowerProxy;
+//!
+//! # fn main() -> upower_dbus::Result<()> {
+//! # futures::executor::block_on(async move {
+//! let upower = UPowerProxy::system().await?;
+//! let session = zbus::Connection::session().await?;
+//! let notifications = NotificationsProxy::new(&session).await?;
+//!
+//! WarningNotifier::new()
+//!     .app_name("battery-monitor")
+//!     .run(&upower, &notifications)
+//!     .await
+//! # })
+//! # }
+//! ```
+
+use futures_util::stream::StreamExt;
+use std::collections::HashMap;
+use zbus::dbus_proxy;
+use zbus::zvariant::Value;
+
+use crate::device::WarningLevel;
+use crate::error::Result;
+use crate::snapshot::DeviceSnapshot;
+use crate::upower::UPowerProxy;
+
+#[dbus_proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    /// Notify method
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    /// CloseNotification method
+    fn close_notification(&self, id: u32) -> zbus::Result<()>;
+}
+
+/// The urgency hint of a notification.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Urgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+impl Urgency {
+    /// The value of the `urgency` hint in the notification specification.
+    pub fn as_byte(self) -> u8 {
+        match self {
+            Urgency::Low => 0,
+            Urgency::Normal => 1,
+            Urgency::Critical => 2,
+        }
+    }
+}
+
+/// The notification shown for a warning level.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Notification {
+    pub summary: String,
+    /// The body of the notification. If `None`, the remaining charge is shown.
+    pub body: Option<String>,
+    pub urgency: Urgency,
+}
+
+impl Notification {
+    /// A notification with the given summary and urgency, which shows the remaining charge.
+    pub fn new<S: Into<String>>(summary: S, urgency: Urgency) -> Self {
+        Self {
+            summary: summary.into(),
+            body: None,
+            urgency,
+        }
+    }
+
+    /// Sets the body of the notification.
+    pub fn body<S: Into<String>>(mut self, body: S) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+}
+
+/// Notifies once for each rise of the warning level to `Low`, `Critical` or `Action`.
+///
+/// Each notification replaces the previous one, which is closed once the warning level falls
+/// back below `Low`, such as when the charger is plugged in.
+#[derive(Clone, Debug)]
+pub struct WarningNotifier {
+    app_name: String,
+    low: Notification,
+    critical: Notification,
+    action: Notification,
+    level: WarningLevel,
+    id: u32,
+}
+
+impl Default for WarningNotifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WarningNotifier {
+    /// Creates a notifier with English summaries, and a critical urgency from `Critical` up.
+    pub fn new() -> Self {
+        Self {
+            app_name: String::from("upower-dbus"),
+            low: Notification::new("Battery low", Urgency::Normal),
+            critical: Notification::new("Battery critically low", Urgency::Critical),
+            action: Notification::new(
+                "Battery exhausted, the system will shut down",
+                Urgency::Critical,
+            ),
+            level: WarningLevel::None,
+            id: 0,
+        }
+    }
+
+    /// Sets the application name the notifications are sent as.
+    pub fn app_name<S: Into<String>>(mut self, app_name: S) -> Self {
+        self.app_name = app_name.into();
+        self
+    }
+
+    /// Sets the notification shown for `level`. Levels below `Low` are ignored.
+    pub fn notification(mut self, level: WarningLevel, notification: Notification) -> Self {
+        match level {
+            WarningLevel::Low => self.low = notification,
+            WarningLevel::Critical => self.critical = notification,
+            WarningLevel::Action => self.action = notification,
+            _ => (),
+        }
+        self
+    }
+
+    /// Notifies if the warning level of `device` rose since the last reading, or closes the
+    /// notification if it fell below `Low`.
+    pub async fn update(
+        &mut self,
+        notifications: &NotificationsProxy<'_>,
+        device: &DeviceSnapshot,
+    ) -> Result<()> {
+        let level = device.warning_level;
+        let previous = std::mem::replace(&mut self.level, level);
+
+        let notification = match level {
+            WarningLevel::Low => &self.low,
+            WarningLevel::Critical => &self.critical,
+            WarningLevel::Action => &self.action,
+            _ => {
+                if self.id != 0 {
+                    notifications.close_notification(self.id).await?;
+                    self.id = 0;
+                }
+                return Ok(());
+            }
+        };
+
+        if level <= previous {
+            return Ok(());
+        }
+
+        let body = match &notification.body {
+            Some(body) => body.clone(),
+            None => format!("{:.0} remaining", device.percentage),
+        };
+
+        let mut hints = HashMap::new();
+        hints.insert("urgency", Value::U8(notification.urgency.as_byte()));
+
+        self.id = notifications
+            .notify(
+                &self.app_name,
+                self.id,
+                &device.icon_name_or_fallback(),
+                &notification.summary,
+                &body,
+                &[],
+                hints,
+                -1,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Checks the display device now, and again after every change to its warning level.
+    ///
+    /// Runs until watching the display device fails.
+    pub async fn run(
+        mut self,
+        upower: &UPowerProxy<'_>,
+        notifications: &NotificationsProxy<'_>,
+    ) -> Result<()> {
+        let display = upower.display_device().await?;
+        let mut changes = display.receive_changes().await?;
+
+        self.update(notifications, &display.snapshot().await?)
+            .await?;
+
+        while let Some(change) = changes.next().await {
+            if change?.warning_level.is_some() {
+                self.update(notifications, &display.snapshot().await?)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::stream::{self, StreamExt};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::time::Duration;
+use zbus::fdo::{DBusProxy, PropertiesProxy};
+use zbus::names::OwnedUniqueName;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+
+use crate::device::fetch_snapshot;
+use crate::error::{Error, Result};
+use crate::monitor::{PowerEvent, PowerEventStream};
+use crate::snapshot::DeviceSnapshot;
+use crate::timer;
+use crate::upower::UPowerProxy;
+
+/// Produces the events of `UPowerProxy::monitor` by reading the daemon at an interval, for
+/// sandboxes and brokers which do not deliver signals.
+///
+/// Each reading is compared with the previous one, so a change which reverts between two
+/// readings is missed. A restart of the daemon is detected from its bus name changing owner.
+#[derive(Clone, Copy, Debug)]
+pub struct PollingMonitor {
+    interval: Duration,
+    resync_on_restart: bool,
+}
+
+impl PollingMonitor {
+    /// Reads the daemon every `interval`.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            resync_on_restart: false,
+        }
+    }
+
+    /// Like `MonitorOptions::resync_on_restart`, emits the complete state of the daemon after
+    /// `PowerEvent::ServiceRestarted`, instead of only what changed.
+    pub fn resync_on_restart(mut self, resync: bool) -> Self {
+        self.resync_on_restart = resync;
+        self
+    }
+
+    /// The time between two readings of the daemon.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Reads the daemon now, and emits the differences found by every following reading.
+    pub async fn monitor<'a>(&self, upower: &UPowerProxy<'a>) -> Result<PowerEventStream<'a>> {
+        let mut poller = Poller::new(upower).await?;
+        let state = poller.read().await?;
+
+        let monitor = *self;
+        let events = stream::unfold((poller, state), move |(mut poller, mut state)| async move {
+            timer::sleep(monitor.interval).await;
+
+            let events = match poller.read().await {
+                Ok(next) => {
+                    let events = poller
+                        .changes(&state, &next, monitor.resync_on_restart)
+                        .await;
+                    state = next;
+                    events
+                }
+                Err(why) => Err(why),
+            };
+
+            Some((events, (poller, state)))
+        })
+        .flat_map(|events| {
+            stream::iter(match events {
+                Ok(events) => events.into_iter().map(Ok).collect(),
+                Err(why) => vec![Err(why)],
+            })
+        });
+
+        Ok(PowerEventStream {
+            inner: Box::pin(events),
+        })
+    }
+}
+
+/// A reading of the daemon.
+struct State {
+    owner: OwnedUniqueName,
+    on_battery: bool,
+    lid_is_closed: bool,
+    display: DeviceSnapshot,
+    devices: Vec<OwnedObjectPath>,
+}
+
+struct Poller<'a> {
+    upower: UPowerProxy<'a>,
+    dbus: DBusProxy<'static>,
+    properties: PropertiesProxy<'static>,
+    /// The display device of the current owner of the daemon's name.
+    display: Option<(OwnedUniqueName, OwnedObjectPath)>,
+}
+
+impl<'a> Poller<'a> {
+    async fn new(upower: &UPowerProxy<'a>) -> Result<Poller<'a>> {
+        let properties = PropertiesProxy::builder(upower.connection())
+            .destination(upower.destination().to_owned())?
+            .path(upower.path().to_owned())?
+            .build()
+            .await?;
+
+        Ok(Poller {
+            upower: upower.clone(),
+            dbus: DBusProxy::new(upower.connection()).await?,
+            properties,
+            display: None,
+        })
+    }
+
+    /// Reads the daemon without going through cached properties, which signals keep current.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self)))]
+    async fn read(&mut self) -> Result<State> {
+        let owner = self
+            .dbus
+            .get_name_owner(self.upower.destination().clone())
+            .await?;
+
+        let mut properties = self
+            .properties
+            .get_all(self.upower.interface().to_owned())
+            .await?;
+
+        let display = match &self.display {
+            Some((display_owner, path)) if *display_owner == owner => path.clone(),
+            _ => {
+                let path: OwnedObjectPath =
+                    self.upower.inner().call("GetDisplayDevice", &()).await?;
+                self.display = Some((owner.clone(), path.clone()));
+                path
+            }
+        };
+
+        let display = fetch_snapshot(
+            self.upower.connection(),
+            self.upower.destination(),
+            &display,
+        )
+        .await?;
+
+        Ok(State {
+            owner,
+            on_battery: take(&mut properties, "OnBattery")?,
+            lid_is_closed: take(&mut properties, "LidIsClosed")?,
+            display,
+            devices: self.upower.enumerate_devices().await?,
+        })
+    }
+
+    /// The events which lead from `previous` to `next`.
+    async fn changes(
+        &self,
+        previous: &State,
+        next: &State,
+        resync_on_restart: bool,
+    ) -> Result<Vec<PowerEvent>> {
+        let mut events = Vec::new();
+
+        let restarted = previous.owner != next.owner;
+        let resync = restarted && resync_on_restart;
+        if restarted {
+            events.push(PowerEvent::ServiceRestarted);
+        }
+
+        if resync || previous.on_battery != next.on_battery {
+            events.push(PowerEvent::OnBatteryChanged(next.on_battery));
+        }
+
+        if resync || previous.lid_is_closed != next.lid_is_closed {
+            events.push(PowerEvent::LidClosed(next.lid_is_closed));
+        }
+
+        if resync || previous.display != next.display {
+            events.push(PowerEvent::DisplayDeviceChanged(Box::new(
+                next.display.clone(),
+            )));
+        }
+
+        for path in &previous.devices {
+            if !next.devices.contains(path) {
+                events.push(PowerEvent::DeviceRemoved(path.clone()));
+            }
+        }
+
+        for path in &next.devices {
+            if resync || !previous.devices.contains(path) {
+                let device = self.upower.device(path.clone()).await?;
+                events.push(PowerEvent::DeviceAdded(device));
+            }
+        }
+
+        Ok(events)
+    }
+}
+
+fn take(properties: &mut HashMap<String, OwnedValue>, name: &'static str) -> Result<bool> {
+    let value = properties
+        .remove(name)
+        .ok_or(Error::MissingProperty(name))?;
+    Ok(bool::try_from(value)?)
+}
@@ -0,0 +1,307 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Switches power profiles through `power-profiles-daemon`, such as to power-saver while the
+//! system runs on battery.
+//!
+//! ```rust,no_run
+//! use upower_dbus::power_profiles::{PowerProfilesProxy, ProfileHolder};
+//! use upower_dbus::UPowerProxy;
+//!
+//! # fn main() -> upower_dbus::Result<()> {
+//! # futures::executor::block_on(async move {
+//! let connection = zbus::Connection::system().await?;
+//! let upower = UPowerProxy::new(&connection).await?;
+//! let profiles = PowerProfilesProxy::new(&connection).await?;
+//!
+//! ProfileHolder::new("com.example.BatteryApplet")
+//!     .run(&upower, &profiles)
+//!     .await
+//! # })
+//! # }
+//! ```
+
+use futures_util::stream::{self, StreamExt};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use zbus::dbus_proxy;
+use zbus::zvariant::OwnedValue;
+
+use crate::error::Result;
+use crate::upower::UPowerProxy;
+
+#[dbus_proxy(
+    interface = "net.hadess.PowerProfiles",
+    default_service = "net.hadess.PowerProfiles",
+    default_path = "/net/hadess/PowerProfiles"
+)]
+trait PowerProfiles {
+    /// HoldProfile method
+    ///
+    /// Returns a cookie for `release_profile`. The hold is also released when the connection
+    /// which made it closes.
+    fn hold_profile(&self, profile: &str, reason: &str, application_id: &str) -> zbus::Result<u32>;
+
+    /// ReleaseProfile method
+    fn release_profile(&self, cookie: u32) -> zbus::Result<()>;
+
+    /// ProfileReleased signal
+    ///
+    /// Sent when a hold is released by the daemon, such as when the user picks another
+    /// profile.
+    #[dbus_proxy(signal)]
+    fn profile_released(&self, cookie: u32) -> zbus::Result<()>;
+
+    /// Actions property
+    #[dbus_proxy(property)]
+    fn actions(&self) -> zbus::Result<Vec<String>>;
+
+    /// ActiveProfile property
+    #[dbus_proxy(property, name = "ActiveProfile")]
+    fn active_profile_raw(&self) -> zbus::Result<String>;
+    #[dbus_proxy(property, name = "ActiveProfile")]
+    fn set_active_profile_raw(&self, value: &str) -> zbus::Result<()>;
+
+    /// ActiveProfileHolds property
+    #[dbus_proxy(property, name = "ActiveProfileHolds")]
+    fn active_profile_holds_raw(&self) -> zbus::Result<Vec<HashMap<String, OwnedValue>>>;
+
+    /// PerformanceDegraded property
+    ///
+    /// Why the performance profile is degraded, such as `"lap-detected"`, or empty if it is
+    /// not.
+    #[dbus_proxy(property)]
+    fn performance_degraded(&self) -> zbus::Result<String>;
+
+    /// Profiles property
+    #[dbus_proxy(property, name = "Profiles")]
+    fn profiles_raw(&self) -> zbus::Result<Vec<HashMap<String, OwnedValue>>>;
+}
+
+/// A power profile of `power-profiles-daemon`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Profile {
+    PowerSaver,
+    Balanced,
+    Performance,
+    /// A profile unknown to this crate, by name.
+    Other(String),
+}
+
+impl Profile {
+    /// The name of the profile on the bus, such as `"power-saver"`.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Profile::PowerSaver => "power-saver",
+            Profile::Balanced => "balanced",
+            Profile::Performance => "performance",
+            Profile::Other(name) => name,
+        }
+    }
+}
+
+impl From<&str> for Profile {
+    fn from(name: &str) -> Self {
+        match name {
+            "power-saver" => Profile::PowerSaver,
+            "balanced" => Profile::Balanced,
+            "performance" => Profile::Performance,
+            name => Profile::Other(name.to_owned()),
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A profile offered by the daemon, with the drivers which implement it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProfileInfo {
+    pub profile: Profile,
+    /// The driver of the profile, such as `"multiple"` when both drivers below are in use.
+    pub driver: String,
+    pub cpu_driver: Option<String>,
+    pub platform_driver: Option<String>,
+}
+
+impl ProfileInfo {
+    fn from_dict(mut dict: HashMap<String, OwnedValue>) -> Self {
+        Self {
+            profile: Profile::from(
+                take_string(&mut dict, "Profile")
+                    .unwrap_or_default()
+                    .as_str(),
+            ),
+            driver: take_string(&mut dict, "Driver").unwrap_or_default(),
+            cpu_driver: take_string(&mut dict, "CpuDriver"),
+            platform_driver: take_string(&mut dict, "PlatformDriver"),
+        }
+    }
+}
+
+/// A hold on a profile, which keeps it active until released.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProfileHold {
+    pub profile: Profile,
+    pub reason: String,
+    pub application_id: String,
+}
+
+impl ProfileHold {
+    fn from_dict(mut dict: HashMap<String, OwnedValue>) -> Self {
+        Self {
+            profile: Profile::from(
+                take_string(&mut dict, "Profile")
+                    .unwrap_or_default()
+                    .as_str(),
+            ),
+            reason: take_string(&mut dict, "Reason").unwrap_or_default(),
+            application_id: take_string(&mut dict, "ApplicationId").unwrap_or_default(),
+        }
+    }
+}
+
+fn take_string(dict: &mut HashMap<String, OwnedValue>, key: &str) -> Option<String> {
+    dict.remove(key)
+        .and_then(|value| String::try_from(value).ok())
+}
+
+impl<'a> PowerProfilesProxy<'a> {
+    /// The profile which is active.
+    pub async fn active_profile(&self) -> Result<Profile> {
+        Ok(Profile::from(self.active_profile_raw().await?.as_str()))
+    }
+
+    /// Switches to `profile`, as if the user picked it.
+    pub async fn set_active_profile(&self, profile: &Profile) -> Result<()> {
+        Ok(self.set_active_profile_raw(profile.as_str()).await?)
+    }
+
+    /// The profiles offered by the daemon.
+    pub async fn profiles(&self) -> Result<Vec<ProfileInfo>> {
+        let profiles = self.profiles_raw().await?;
+        Ok(profiles.into_iter().map(ProfileInfo::from_dict).collect())
+    }
+
+    /// The holds keeping a profile active, made by any application.
+    pub async fn active_profile_holds(&self) -> Result<Vec<ProfileHold>> {
+        let holds = self.active_profile_holds_raw().await?;
+        Ok(holds.into_iter().map(ProfileHold::from_dict).collect())
+    }
+
+    /// Holds `profile` until `release_profile` is called with the returned cookie.
+    pub async fn hold(&self, profile: &Profile, reason: &str, application_id: &str) -> Result<u32> {
+        Ok(self
+            .hold_profile(profile.as_str(), reason, application_id)
+            .await?)
+    }
+}
+
+/// Holds a profile while the system runs on battery, and releases it on AC.
+///
+/// Releasing the hold returns to the profile which was active before, unless the user picked
+/// another one in the meantime.
+#[derive(Clone, Debug)]
+pub struct ProfileHolder {
+    profile: Profile,
+    reason: String,
+    application_id: String,
+    cookie: Option<u32>,
+}
+
+impl ProfileHolder {
+    /// Creates a holder of `Profile::PowerSaver`, for the application `application_id`.
+    pub fn new<S: Into<String>>(application_id: S) -> Self {
+        Self {
+            profile: Profile::PowerSaver,
+            reason: String::from("Running on battery"),
+            application_id: application_id.into(),
+            cookie: None,
+        }
+    }
+
+    /// Sets the profile held on battery.
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Sets the reason given to the daemon, which shows it to the user.
+    pub fn reason<S: Into<String>>(mut self, reason: S) -> Self {
+        self.reason = reason.into();
+        self
+    }
+
+    /// Holds the profile if `on_battery`, or releases the hold otherwise.
+    pub async fn apply(
+        &mut self,
+        profiles: &PowerProfilesProxy<'_>,
+        on_battery: bool,
+    ) -> Result<()> {
+        match (on_battery, self.cookie) {
+            (true, None) => {
+                let cookie = profiles
+                    .hold(&self.profile, &self.reason, &self.application_id)
+                    .await?;
+                self.cookie = Some(cookie);
+            }
+            (false, Some(cookie)) => {
+                self.cookie = None;
+                profiles.release_profile(cookie).await?;
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /// Applies the hold for the current power state, and again after every change to it.
+    ///
+    /// Runs until watching the power state fails. A hold released by the daemon, such as
+    /// when the user picks another profile, is not taken again until the next switch to
+    /// battery.
+    pub async fn run(
+        mut self,
+        upower: &UPowerProxy<'_>,
+        profiles: &PowerProfilesProxy<'_>,
+    ) -> Result<()> {
+        let changes = upower
+            .receive_on_battery_changed()
+            .await
+            .then(
+                |changed| async move { Result::<_>::Ok(Update::OnBattery(changed.get().await?)) },
+            );
+
+        let released = profiles
+            .receive_profile_released()
+            .await?
+            .map(|signal| Result::<_>::Ok(Update::Released(signal.args()?.cookie)));
+
+        let mut updates = stream::select(changes.boxed(), released.boxed());
+
+        let mut on_battery = upower.on_battery().await?;
+        self.apply(profiles, on_battery).await?;
+
+        while let Some(update) = updates.next().await {
+            match update? {
+                Update::OnBattery(changed) if changed != on_battery => {
+                    on_battery = changed;
+                    self.apply(profiles, on_battery).await?;
+                }
+                Update::Released(cookie) if Some(cookie) == self.cookie => self.cookie = None,
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+enum Update {
+    OnBattery(bool),
+    Released(u32),
+}
@@ -0,0 +1,120 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::future;
+use futures_util::stream::{self, Stream, StreamExt};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use zbus::fdo::PropertiesProxy;
+use zbus::names::{BusName, InterfaceName};
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+
+use crate::error::Result;
+
+/// Applies `f` to a string property held in the cache of a proxy, as returned by
+/// `cached_property_raw`, without copying it into a `String`.
+pub(crate) fn cached_str<V, R>(cached: Option<V>, f: impl FnOnce(&str) -> R) -> Option<R>
+where
+    V: Deref<Target = Value<'static>>,
+{
+    let value = cached?;
+    <&str>::try_from(&*value).ok().map(f)
+}
+
+/// Decodes a property of a plain type held in the cache of `proxy`, without waiting for the
+/// cache to be ready as the generated getters do on each read.
+///
+/// Returns `None` if the property is not cached, for the caller to fall back to the getter.
+pub(crate) fn cached<T>(proxy: &zbus::Proxy<'_>, name: &str) -> Option<T>
+where
+    T: TryFrom<Value<'static>>,
+{
+    let value = proxy.cached_property_raw(name)?;
+    T::try_from(value.clone()).ok()
+}
+
+/// The properties of `interface` which changed in each `PropertiesChanged` signal of the
+/// object at `path`.
+pub(crate) async fn changed_properties(
+    connection: &zbus::Connection,
+    destination: &BusName<'_>,
+    path: &ObjectPath<'_>,
+    interface: &InterfaceName<'_>,
+) -> Result<impl Stream<Item = Result<HashMap<String, OwnedValue>>> + Send + 'static> {
+    let interface = interface.to_owned();
+    #[cfg(feature = "tracing")]
+    let path = path.to_owned();
+    let signals = PropertiesProxy::builder(connection)
+        .destination(destination.to_owned())?
+        .path(path.to_owned())?
+        .build()
+        .await?
+        .receive_properties_changed()
+        .await?;
+
+    Ok(signals.filter_map(move |signal| {
+        future::ready(match signal.args() {
+            Ok(args) if args.interface_name() == &interface => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(
+                    path = %path.as_str(),
+                    properties = args.changed_properties().len(),
+                    "PropertiesChanged"
+                );
+
+                Some(Ok(args
+                    .changed_properties()
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), OwnedValue::from(value)))
+                    .collect()))
+            }
+            Ok(_) => None,
+            Err(why) => Some(Err(why.into())),
+        })
+    }))
+}
+
+/// Splits each set of changed properties into `(name, value)` pairs.
+pub(crate) fn property_pairs<'a, S>(changes: S) -> PropertiesChangedStream<'a>
+where
+    S: Stream<Item = Result<HashMap<String, OwnedValue>>> + Send + 'a,
+{
+    let pairs = changes.flat_map(|properties| {
+        stream::iter(match properties {
+            Ok(properties) => properties.into_iter().map(Ok).collect(),
+            Err(why) => vec![Err(why)],
+        })
+    });
+
+    PropertiesChangedStream {
+        inner: Box::pin(pairs),
+    }
+}
+
+/// The name of a property, with its new value.
+type PropertyPair = (String, OwnedValue);
+
+/// A stream of the `(name, value)` pairs of changed properties, created by
+/// `UPowerProxy::receive_properties_changed` and `DeviceProxy::receive_properties_changed`.
+pub struct PropertiesChangedStream<'a> {
+    pub(crate) inner: Pin<Box<dyn Stream<Item = Result<PropertyPair>> + Send + 'a>>,
+}
+
+impl fmt::Debug for PropertiesChangedStream<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PropertiesChangedStream")
+            .finish_non_exhaustive()
+    }
+}
+
+impl Stream for PropertiesChangedStream<'_> {
+    type Item = Result<(String, OwnedValue)>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
@@ -0,0 +1,189 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::future;
+use futures_util::stream::{self, StreamExt};
+use std::collections::{vec_deque, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, SystemTime};
+
+use crate::device::DeviceProxy;
+use crate::error::Result;
+use crate::snapshot::DeviceSnapshot;
+use crate::timer;
+use crate::units::{Percentage, Watts};
+use crate::upower::UPowerProxy;
+
+/// A reading of a device, for plotting.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sample {
+    /// When the reading was taken.
+    pub time: SystemTime,
+    pub percentage: Percentage,
+    /// The rate of energy flowing into the battery, negative while discharging.
+    pub rate: Watts,
+}
+
+impl Sample {
+    /// A sample of `device`, taken now.
+    pub fn new(device: &DeviceSnapshot) -> Self {
+        Self {
+            time: SystemTime::now(),
+            percentage: device.percentage,
+            rate: device.power_flow(),
+        }
+    }
+}
+
+/// The last `capacity` samples, oldest first.
+#[derive(Clone, Debug)]
+pub struct SampleBuffer {
+    samples: VecDeque<Sample>,
+    capacity: usize,
+}
+
+impl SampleBuffer {
+    /// Creates an empty buffer, which keeps up to `capacity` samples.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// The number of samples kept.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Adds a sample, dropping the oldest one if the buffer is full.
+    pub fn push(&mut self, sample: Sample) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+
+        self.samples.push_back(sample);
+    }
+
+    /// The samples, oldest first.
+    pub fn iter(&self) -> vec_deque::Iter<'_, Sample> {
+        self.samples.iter()
+    }
+
+    /// The samples taken at or after `time`, oldest first.
+    pub fn since(&self, time: SystemTime) -> impl DoubleEndedIterator<Item = &Sample> + '_ {
+        self.samples
+            .iter()
+            .filter(move |sample| sample.time >= time)
+    }
+
+    /// The most recent sample.
+    pub fn latest(&self) -> Option<&Sample> {
+        self.samples.back()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+impl<'a> IntoIterator for &'a SampleBuffer {
+    type Item = &'a Sample;
+    type IntoIter = vec_deque::Iter<'a, Sample>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Samples the display device into a `SampleBuffer` shared with its clones, for a widget to
+/// plot while `run` fills it.
+///
+/// A sample is taken whenever the percentage, rate or state changes, and at `poll_interval`
+/// if one is set, so that a graph keeps moving while the battery is idle.
+#[derive(Clone, Debug)]
+pub struct Sampler {
+    buffer: Arc<Mutex<SampleBuffer>>,
+    poll_interval: Option<Duration>,
+}
+
+impl Sampler {
+    /// Creates a sampler which keeps the last `capacity` samples.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            buffer: Arc::new(Mutex::new(SampleBuffer::new(capacity))),
+            poll_interval: None,
+        }
+    }
+
+    /// Also takes a sample every `interval`, in addition to one after every change.
+    pub fn poll_interval(mut self, interval: Option<Duration>) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Locks the samples taken so far.
+    pub fn buffer(&self) -> MutexGuard<'_, SampleBuffer> {
+        self.buffer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// A copy of the samples taken so far, oldest first.
+    pub fn samples(&self) -> Vec<Sample> {
+        self.buffer().iter().copied().collect()
+    }
+
+    /// Takes a sample of the display device now, and whenever it changes or the poll interval
+    /// elapses.
+    ///
+    /// Runs until reading the display device fails.
+    pub async fn run(&self, upower: &UPowerProxy<'_>) -> Result<()> {
+        let display = upower.display_device().await?;
+
+        let changes = display.receive_changes().await?.filter_map(|change| {
+            future::ready(match change {
+                Ok(change)
+                    if change.percentage.is_none()
+                        && change.energy_rate.is_none()
+                        && change.state.is_none() =>
+                {
+                    None
+                }
+                change => Some(change.map(drop)),
+            })
+        });
+
+        let ticks = match self.poll_interval {
+            Some(interval) => timer::interval(interval).map(Ok).boxed(),
+            None => stream::pending().boxed(),
+        };
+
+        self.sample(&display).await?;
+
+        let mut triggers = stream::select(changes, ticks);
+        while let Some(trigger) = triggers.next().await {
+            trigger?;
+            self.sample(&display).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn sample(&self, display: &DeviceProxy<'_>) -> Result<()> {
+        let sample = Sample::new(&display.snapshot().await?);
+        self.buffer().push(sample);
+        Ok(())
+    }
+}
@@ -0,0 +1,362 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::stream::StreamExt;
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
+#[cfg(feature = "serde")]
+use std::fs;
+#[cfg(feature = "serde")]
+use std::io;
+#[cfg(feature = "serde")]
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+#[cfg(feature = "serde")]
+use std::time::UNIX_EPOCH;
+use std::time::{Duration, SystemTime};
+
+use crate::device::BatteryState;
+#[cfg(feature = "serde")]
+use crate::device::WarningLevel;
+#[cfg(feature = "serde")]
+use crate::error::Error;
+use crate::error::Result;
+use crate::monitor::PowerEvent;
+use crate::snapshot::DeviceSnapshot;
+use crate::units::Percentage;
+use crate::upower::UPowerProxy;
+use crate::watcher::{BatteryAlert, BatteryWatcher};
+
+/// A transition of the power state, recorded by a `SessionLog`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SessionEvent {
+    /// The system switched to battery power.
+    Unplugged,
+    /// The system switched to AC power.
+    PluggedIn,
+    /// The display device started charging.
+    ChargingStarted,
+    /// The display device stopped charging, such as when full or unplugged.
+    ChargingStopped,
+    /// A low battery threshold of the log's `BatteryWatcher` was crossed.
+    Alert(BatteryAlert),
+}
+
+/// A `SessionEvent`, with when it happened and the charge at the time.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SessionEntry {
+    pub time: SystemTime,
+    pub event: SessionEvent,
+    /// The charge of the display device.
+    pub percentage: Percentage,
+}
+
+/// The transitions of the power state during a session, oldest first.
+///
+/// ```rust
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use upower_dbus::{BatteryState, DeviceSnapshot, DeviceType, Percentage, SessionLog};
+///
+/// let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+/// let mut display = DeviceSnapshot::new(DeviceType::Battery);
+/// display.percentage = Percentage::new(90.0);
+///
+/// let mut log = SessionLog::new();
+/// log.update_at(start, false, &display);
+///
+/// display.state = BatteryState::Discharging;
+/// log.update_at(start + Duration::from_secs(60), true, &display);
+///
+/// let now = start + Duration::from_secs(600);
+/// assert_eq!(log.time_on_battery(now), Some(Duration::from_secs(540)));
+///
+/// # #[cfg(feature = "serde")]
+/// # {
+/// let restored = SessionLog::from_json(&log.to_json()).unwrap();
+/// assert_eq!(restored.entries(), log.entries());
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SessionLog {
+    entries: Vec<SessionEntry>,
+    watcher: BatteryWatcher,
+    on_battery: Option<bool>,
+    charging: Option<bool>,
+}
+
+impl SessionLog {
+    /// Creates an empty log, which watches the thresholds of `BatteryWatcher::default`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the watcher whose alerts are recorded.
+    pub fn watcher(mut self, watcher: BatteryWatcher) -> Self {
+        self.watcher = watcher;
+        self
+    }
+
+    /// The recorded entries, oldest first.
+    pub fn entries(&self) -> &[SessionEntry] {
+        &self.entries
+    }
+
+    /// Adds a reading of the power state taken now, returning the entries it recorded.
+    pub fn update(&mut self, on_battery: bool, display: &DeviceSnapshot) -> &[SessionEntry] {
+        self.update_at(SystemTime::now(), on_battery, display)
+    }
+
+    /// Adds a reading of the power state taken at `time`, returning the entries it recorded.
+    ///
+    /// The first reading records the power source and charging state as they are.
+    pub fn update_at(
+        &mut self,
+        time: SystemTime,
+        on_battery: bool,
+        display: &DeviceSnapshot,
+    ) -> &[SessionEntry] {
+        let recorded = self.entries.len();
+        let mut events = Vec::new();
+
+        if self.on_battery != Some(on_battery) {
+            self.on_battery = Some(on_battery);
+            events.push(if on_battery {
+                SessionEvent::Unplugged
+            } else {
+                SessionEvent::PluggedIn
+            });
+        }
+
+        let charging = display.state == BatteryState::Charging;
+        if self.charging != Some(charging) {
+            self.charging = Some(charging);
+            events.push(if charging {
+                SessionEvent::ChargingStarted
+            } else {
+                SessionEvent::ChargingStopped
+            });
+        }
+
+        events.extend(
+            self.watcher
+                .update(display)
+                .into_iter()
+                .map(SessionEvent::Alert),
+        );
+
+        let percentage = display.percentage;
+        self.entries
+            .extend(events.into_iter().map(|event| SessionEntry {
+                time,
+                event,
+                percentage,
+            }));
+
+        &self.entries[recorded..]
+    }
+
+    /// When the system was last unplugged, if it is still on battery.
+    pub fn on_battery_since(&self) -> Option<SystemTime> {
+        match self.last_power_source()? {
+            entry if entry.event == SessionEvent::Unplugged => Some(entry.time),
+            _ => None,
+        }
+    }
+
+    /// The time spent on battery between the last unplug and `now`, if still on battery.
+    pub fn time_on_battery(&self, now: SystemTime) -> Option<Duration> {
+        let since = self.on_battery_since()?;
+        Some(now.duration_since(since).unwrap_or_default())
+    }
+
+    fn last_power_source(&self) -> Option<&SessionEntry> {
+        self.entries.iter().rev().find(|entry| {
+            matches!(
+                entry.event,
+                SessionEvent::Unplugged | SessionEvent::PluggedIn
+            )
+        })
+    }
+
+    /// Serializes the entries as a JSON array, with times in milliseconds since the Unix
+    /// epoch.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        let entries: Vec<EntryJson> = self.entries.iter().map(EntryJson::from).collect();
+        serde_json::to_string(&entries).expect("entries are serializable")
+    }
+
+    /// Parses entries written by `to_json`, resuming from the power source and charging state
+    /// they end with.
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self> {
+        let entries: Vec<EntryJson> = serde_json::from_str(json).map_err(invalid)?;
+        let entries = entries
+            .into_iter()
+            .map(SessionEntry::try_from)
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut log = Self {
+            entries,
+            ..Self::default()
+        };
+
+        log.on_battery = log
+            .last_power_source()
+            .map(|entry| entry.event == SessionEvent::Unplugged);
+
+        log.charging = log
+            .entries
+            .iter()
+            .rev()
+            .find_map(|entry| match entry.event {
+                SessionEvent::ChargingStarted => Some(true),
+                SessionEvent::ChargingStopped => Some(false),
+                _ => None,
+            });
+
+        Ok(log)
+    }
+
+    /// Writes the entries to `path`, as by `to_json`.
+    #[cfg(feature = "serde")]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        Ok(fs::write(path, self.to_json())?)
+    }
+
+    /// Reads entries written by `save`.
+    #[cfg(feature = "serde")]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+}
+
+/// Records the power state of the system into a `SessionLog` shared with its clones, for a
+/// UI to read while `run` fills it.
+#[derive(Clone, Debug, Default)]
+pub struct SessionRecorder {
+    log: Arc<Mutex<SessionLog>>,
+}
+
+impl SessionRecorder {
+    /// Records into `log`, such as one restored by `SessionLog::load`.
+    pub fn new(log: SessionLog) -> Self {
+        Self {
+            log: Arc::new(Mutex::new(log)),
+        }
+    }
+
+    /// Locks the log.
+    pub fn log(&self) -> MutexGuard<'_, SessionLog> {
+        self.log.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Records the current power state, and every transition after it.
+    ///
+    /// Runs until watching the daemon fails.
+    pub async fn run(&self, upower: &UPowerProxy<'_>) -> Result<()> {
+        let mut events = upower.monitor().await?;
+
+        let mut on_battery = upower.on_battery().await?;
+        let mut display = upower.display_device().await?.snapshot().await?;
+        self.log().update(on_battery, &display);
+
+        while let Some(event) = events.next().await {
+            match event? {
+                PowerEvent::OnBatteryChanged(changed) => on_battery = changed,
+                PowerEvent::DisplayDeviceChanged(changed) => display = *changed,
+                _ => continue,
+            }
+
+            self.log().update(on_battery, &display);
+        }
+
+        Ok(())
+    }
+}
+
+/// A `SessionEntry` as written by `SessionLog::to_json`.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct EntryJson {
+    /// Milliseconds since the Unix epoch.
+    time: u64,
+    event: String,
+    percentage: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    threshold: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    level: Option<u32>,
+}
+
+#[cfg(feature = "serde")]
+impl From<&SessionEntry> for EntryJson {
+    fn from(entry: &SessionEntry) -> Self {
+        let time = entry
+            .time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+
+        let mut json = EntryJson {
+            time: u64::try_from(time).unwrap_or(u64::MAX),
+            event: String::new(),
+            percentage: entry.percentage.get(),
+            threshold: None,
+            level: None,
+        };
+
+        let event = match entry.event {
+            SessionEvent::Unplugged => "unplugged",
+            SessionEvent::PluggedIn => "plugged-in",
+            SessionEvent::ChargingStarted => "charging-started",
+            SessionEvent::ChargingStopped => "charging-stopped",
+            SessionEvent::Alert(BatteryAlert::ThresholdCrossed { threshold, .. }) => {
+                json.threshold = Some(threshold);
+                "threshold-crossed"
+            }
+            SessionEvent::Alert(BatteryAlert::WarningLevelRaised(level)) => {
+                json.level = Some(u32::from(level));
+                "warning-level-raised"
+            }
+        };
+
+        json.event = event.to_owned();
+        json
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<EntryJson> for SessionEntry {
+    type Error = Error;
+
+    fn try_from(json: EntryJson) -> Result<Self> {
+        let percentage = Percentage::new(json.percentage);
+
+        let event = match json.event.as_str() {
+            "unplugged" => SessionEvent::Unplugged,
+            "plugged-in" => SessionEvent::PluggedIn,
+            "charging-started" => SessionEvent::ChargingStarted,
+            "charging-stopped" => SessionEvent::ChargingStopped,
+            "threshold-crossed" => SessionEvent::Alert(BatteryAlert::ThresholdCrossed {
+                threshold: json.threshold.ok_or_else(|| invalid("threshold"))?,
+                percentage,
+            }),
+            "warning-level-raised" => SessionEvent::Alert(BatteryAlert::WarningLevelRaised(
+                WarningLevel::from_raw(json.level.ok_or_else(|| invalid("level"))?),
+            )),
+            _ => return Err(invalid("unknown event")),
+        };
+
+        Ok(SessionEntry {
+            time: UNIX_EPOCH + Duration::from_millis(json.time),
+            event,
+            percentage,
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(why: E) -> Error {
+    Error::from(io::Error::new(io::ErrorKind::InvalidData, why))
+}
@@ -0,0 +1,378 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::stream::{self, Stream, StreamExt};
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use zbus::dbus_proxy;
+use zbus::fdo::DBusProxy;
+
+use crate::device::WarningLevel;
+use crate::error::Result;
+use crate::upower::{CriticalAction, UPowerProxy};
+
+/// How long the daemon waits after the warning level reaches `WarningLevel::Action` before
+/// taking its critical action.
+pub const CRITICAL_ACTION_DELAY: Duration = Duration::from_secs(20);
+
+/// What the system is about to do.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PowerAction {
+    /// The daemon's critical action, taken because the battery is nearly empty.
+    Critical(CriticalAction),
+    /// What logind was configured to do when the lid was closed.
+    Lid(LidAction),
+}
+
+/// What logind does when the lid is closed, other than ignoring it or locking the session.
+///
+/// Read from `HandleLidSwitch`, `HandleLidSwitchExternalPower` or `HandleLidSwitchDocked`,
+/// according to whether the system is docked or on AC power.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LidAction {
+    Suspend,
+    Hibernate,
+    HybridSleep,
+    SuspendThenHibernate,
+    /// Suspends, hibernates, or both, as logind's `SleepOperation` chooses.
+    Sleep,
+    PowerOff,
+    Reboot,
+    Halt,
+    Kexec,
+    /// An action this crate does not know of, which is assumed to leave the session.
+    Other,
+}
+
+impl LidAction {
+    /// Parses a `HandleLidSwitch` setting, returning `None` when it keeps the session going.
+    pub(crate) fn from_setting(setting: &str) -> Option<Self> {
+        let action = match setting {
+            "ignore" | "lock" => return None,
+            "suspend" => LidAction::Suspend,
+            "hibernate" => LidAction::Hibernate,
+            "hybrid-sleep" => LidAction::HybridSleep,
+            "suspend-then-hibernate" => LidAction::SuspendThenHibernate,
+            "sleep" => LidAction::Sleep,
+            "poweroff" => LidAction::PowerOff,
+            "reboot" => LidAction::Reboot,
+            "halt" => LidAction::Halt,
+            "kexec" => LidAction::Kexec,
+            _ => LidAction::Other,
+        };
+
+        Some(action)
+    }
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+trait LoginManager {
+    /// BlockInhibited property
+    #[dbus_proxy(property)]
+    fn block_inhibited(&self) -> zbus::Result<String>;
+
+    /// Docked property
+    #[dbus_proxy(property)]
+    fn docked(&self) -> zbus::Result<bool>;
+
+    /// HandleLidSwitch property
+    #[dbus_proxy(property)]
+    fn handle_lid_switch(&self) -> zbus::Result<String>;
+
+    /// HandleLidSwitchDocked property
+    #[dbus_proxy(property)]
+    fn handle_lid_switch_docked(&self) -> zbus::Result<String>;
+
+    /// HandleLidSwitchExternalPower property
+    #[dbus_proxy(property)]
+    fn handle_lid_switch_external_power(&self) -> zbus::Result<String>;
+}
+
+/// logind's handling of the lid, read when it is closed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct LidSettings {
+    pub(crate) handle_lid_switch: String,
+    pub(crate) handle_lid_switch_external_power: String,
+    pub(crate) handle_lid_switch_docked: String,
+    pub(crate) docked: bool,
+    pub(crate) block_inhibited: String,
+}
+
+impl LidSettings {
+    async fn read(logind: &LoginManagerProxy<'_>) -> Result<Self> {
+        Ok(Self {
+            handle_lid_switch: logind.handle_lid_switch().await?,
+            handle_lid_switch_external_power: logind.handle_lid_switch_external_power().await?,
+            handle_lid_switch_docked: logind.handle_lid_switch_docked().await?,
+            docked: logind.docked().await?,
+            block_inhibited: logind.block_inhibited().await?,
+        })
+    }
+
+    /// The action logind takes when the lid closes, as it chooses between its settings.
+    ///
+    /// Nothing is done while an application holds a `handle-lid-switch` block inhibitor,
+    /// as desktops do to handle the lid themselves. An unset external power setting falls
+    /// back to `HandleLidSwitch`.
+    pub(crate) fn action(&self, on_battery: bool) -> Option<LidAction> {
+        if self
+            .block_inhibited
+            .split(':')
+            .any(|what| what == "handle-lid-switch")
+        {
+            return None;
+        }
+
+        let setting = if self.docked {
+            &self.handle_lid_switch_docked
+        } else if !on_battery && !self.handle_lid_switch_external_power.is_empty() {
+            &self.handle_lid_switch_external_power
+        } else {
+            &self.handle_lid_switch
+        };
+
+        LidAction::from_setting(setting)
+    }
+}
+
+/// A power action about to be taken, emitted by `UPowerProxy::shutdown_guard`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ImminentPowerAction {
+    pub action: PowerAction,
+    /// When the action is expected to be taken, which state should be saved before.
+    ///
+    /// For a critical action, this is `CRITICAL_ACTION_DELAY` after the level was reached,
+    /// or sooner if the battery is estimated to run out first. A lid action is taken as soon
+    /// as logind handles the lid, so its deadline is the moment it was emitted.
+    pub deadline_estimate: Instant,
+}
+
+/// A stream of `ImminentPowerAction` events, created by `UPowerProxy::shutdown_guard`.
+pub struct ImminentPowerActionStream<'a> {
+    pub(crate) inner: Pin<Box<dyn Stream<Item = Result<ImminentPowerAction>> + Send + 'a>>,
+}
+
+impl fmt::Debug for ImminentPowerActionStream<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImminentPowerActionStream")
+            .finish_non_exhaustive()
+    }
+}
+
+impl Stream for ImminentPowerActionStream<'_> {
+    type Item = Result<ImminentPowerAction>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+enum Update {
+    WarningLevel(WarningLevel),
+    LidClosed(bool),
+}
+
+/// A condition which started to hold, before the action it leads to is read.
+enum Trigger {
+    ActionLevel,
+    LidClosed,
+}
+
+/// Emits an action each time the display device reaches the action level, and each time the
+/// lid is closed while logind is configured to act on it.
+///
+/// The critical action and logind's lid settings are read as each condition starts to hold,
+/// so that changes to either since the watch began are honored. Lid events are only watched
+/// while logind runs, since the lid is otherwise left alone.
+pub(crate) async fn watch(
+    upower: UPowerProxy<'static>,
+) -> Result<ImminentPowerActionStream<'static>> {
+    let display = upower.display_device().await?;
+
+    let levels = display
+        .watch_warning_level()
+        .await?
+        .map(|level| level.map(Update::WarningLevel));
+
+    let mut updates = vec![levels.boxed()];
+
+    let logind = LoginManagerProxy::new(upower.connection()).await?;
+    let logind = if logind_is_running(&logind).await? {
+        Some(logind)
+    } else {
+        None
+    };
+
+    let mut lid_closed = false;
+    if logind.is_some() && upower.lid_is_present().await? {
+        lid_closed = upower.lid_is_closed().await?;
+
+        let lid = upower
+            .receive_lid_is_closed_changed()
+            .await
+            .then(|changed| async move { Ok(Update::LidClosed(changed.get().await?)) });
+
+        updates.push(lid.boxed());
+    }
+
+    // Each condition triggers once when it starts to hold, and again only after it stopped
+    // holding in between.
+    let triggers = stream::select_all(updates).scan(
+        (false, lid_closed),
+        move |(acting, lid_closed), update| {
+            let trigger = match update {
+                Ok(Update::WarningLevel(level)) => {
+                    let reached = level >= WarningLevel::Action;
+                    let started = reached && !*acting;
+                    *acting = reached;
+
+                    if started {
+                        Some(Ok(Trigger::ActionLevel))
+                    } else {
+                        None
+                    }
+                }
+                Ok(Update::LidClosed(closed)) => {
+                    let started = closed && !*lid_closed;
+                    *lid_closed = closed;
+
+                    if started {
+                        Some(Ok(Trigger::LidClosed))
+                    } else {
+                        None
+                    }
+                }
+                Err(why) => Some(Err(why)),
+            };
+
+            async move { Some(trigger) }
+        },
+    );
+
+    let inner = triggers
+        .filter_map(|trigger| async move { trigger })
+        .then(move |trigger| {
+            let upower = upower.clone();
+            let display = display.clone();
+            let logind = logind.clone();
+            async move {
+                let now = Instant::now();
+
+                let (action, deadline_estimate) = match trigger? {
+                    Trigger::ActionLevel => {
+                        let critical = upower.get_critical_action().await?;
+                        let remaining = display.time_to_empty_duration().await?;
+                        let deadline = now
+                            + remaining.map_or(CRITICAL_ACTION_DELAY, |remaining| {
+                                remaining.min(CRITICAL_ACTION_DELAY)
+                            });
+
+                        (PowerAction::Critical(critical), deadline)
+                    }
+                    Trigger::LidClosed => {
+                        let logind = match logind {
+                            Some(logind) => logind,
+                            None => return Ok(None),
+                        };
+
+                        let settings = LidSettings::read(&logind).await?;
+                        match settings.action(upower.on_battery().await?) {
+                            Some(action) => (PowerAction::Lid(action), now),
+                            None => return Ok(None),
+                        }
+                    }
+                };
+
+                #[cfg(feature = "tracing")]
+                tracing::debug!(?action, "power action imminent");
+
+                Ok(Some(ImminentPowerAction {
+                    action,
+                    deadline_estimate,
+                }))
+            }
+        })
+        .filter_map(
+            |action: Result<Option<ImminentPowerAction>>| async move { action.transpose() },
+        );
+
+    Ok(ImminentPowerActionStream {
+        inner: Box::pin(inner),
+    })
+}
+
+/// Whether logind owns its bus name, and so handles the lid.
+async fn logind_is_running(logind: &LoginManagerProxy<'_>) -> Result<bool> {
+    let dbus = DBusProxy::new(logind.connection()).await?;
+    Ok(dbus.name_has_owner(logind.destination().as_ref()).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LidAction, LidSettings};
+
+    fn settings() -> LidSettings {
+        LidSettings {
+            handle_lid_switch: "suspend".to_owned(),
+            handle_lid_switch_external_power: "lock".to_owned(),
+            handle_lid_switch_docked: "ignore".to_owned(),
+            ..LidSettings::default()
+        }
+    }
+
+    #[test]
+    fn lid_action_follows_the_power_source() {
+        let settings = settings();
+        assert_eq!(settings.action(true), Some(LidAction::Suspend));
+        assert_eq!(settings.action(false), None);
+
+        let unset = LidSettings {
+            handle_lid_switch_external_power: String::new(),
+            ..settings
+        };
+        assert_eq!(unset.action(false), Some(LidAction::Suspend));
+    }
+
+    #[test]
+    fn lid_action_when_docked() {
+        let mut settings = settings();
+        settings.docked = true;
+        assert_eq!(settings.action(true), None);
+
+        settings.handle_lid_switch_docked = "hibernate".to_owned();
+        assert_eq!(settings.action(false), Some(LidAction::Hibernate));
+    }
+
+    #[test]
+    fn lid_action_is_inhibited() {
+        let settings = LidSettings {
+            block_inhibited: "shutdown:sleep:handle-lid-switch".to_owned(),
+            ..settings()
+        };
+        assert_eq!(settings.action(true), None);
+    }
+
+    #[test]
+    fn lid_settings_are_parsed() {
+        assert_eq!(
+            LidAction::from_setting("poweroff"),
+            Some(LidAction::PowerOff)
+        );
+        assert_eq!(
+            LidAction::from_setting("suspend-then-hibernate"),
+            Some(LidAction::SuspendThenHibernate)
+        );
+        assert_eq!(LidAction::from_setting("lock"), None);
+        assert_eq!(
+            LidAction::from_setting("factory-reset"),
+            Some(LidAction::Other)
+        );
+    }
+}
@@ -0,0 +1,579 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::time::{Duration, SystemTime};
+use zbus::zvariant::{self, OwnedValue};
+
+use crate::aggregate::device_label;
+use crate::capabilities::DeviceCapabilities;
+use crate::change::{DeviceChange, DeviceDelta};
+use crate::device::{
+    battery_health, cycles, estimate, is_system_battery, power_flow, temperature, timestamp,
+    BatteryLevel, BatteryState, DeviceType, Technology, WarningLevel,
+};
+use crate::error::{Error, Result};
+use crate::icon::device_fallback_icon;
+use crate::units::{Celsius, Percentage, Volts, WattHours, Watts};
+
+/// The properties of a device, fetched in a single round-trip by `DeviceProxy::snapshot`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceSnapshot {
+    pub battery_level: BatteryLevel,
+    pub capacity: f64,
+    /// The number of charge cycles, if known. Older daemons do not report it.
+    pub charge_cycles: Option<i32>,
+    /// Only reported by UPower 1.90 and newer, like the other charge threshold fields.
+    pub charge_end_threshold: Option<u32>,
+    pub charge_start_threshold: Option<u32>,
+    pub charge_threshold_enabled: Option<bool>,
+    pub charge_threshold_supported: Option<bool>,
+    pub energy: WattHours,
+    pub energy_empty: WattHours,
+    pub energy_full: WattHours,
+    pub energy_full_design: WattHours,
+    pub energy_rate: Watts,
+    pub has_history: bool,
+    pub has_statistics: bool,
+    pub icon_name: String,
+    pub is_present: bool,
+    pub is_rechargeable: bool,
+    pub luminosity: f64,
+    pub model: String,
+    pub native_path: String,
+    pub online: bool,
+    pub percentage: Percentage,
+    pub power_supply: bool,
+    pub serial: String,
+    pub state: BatteryState,
+    pub technology: Technology,
+    /// The temperature of the device, if reported by the hardware.
+    pub temperature: Option<Celsius>,
+    pub time_to_empty: i64,
+    pub time_to_full: i64,
+    pub type_: DeviceType,
+    pub update_time: u64,
+    pub vendor: String,
+    pub voltage: Volts,
+    pub warning_level: WarningLevel,
+}
+
+impl DeviceSnapshot {
+    /// Creates the properties of a device of the given kind, with every other value unknown.
+    pub fn new(type_: DeviceType) -> Self {
+        DeviceSnapshot {
+            battery_level: BatteryLevel::None,
+            capacity: 100.0,
+            charge_cycles: None,
+            charge_end_threshold: None,
+            charge_start_threshold: None,
+            charge_threshold_enabled: None,
+            charge_threshold_supported: None,
+            energy: WattHours::default(),
+            energy_empty: WattHours::default(),
+            energy_full: WattHours::default(),
+            energy_full_design: WattHours::default(),
+            energy_rate: Watts::default(),
+            has_history: false,
+            has_statistics: false,
+            icon_name: String::new(),
+            is_present: true,
+            is_rechargeable: type_ != DeviceType::LinePower,
+            luminosity: 0.0,
+            model: String::new(),
+            native_path: String::new(),
+            online: false,
+            percentage: Percentage::default(),
+            power_supply: false,
+            serial: String::new(),
+            state: BatteryState::Unknown,
+            technology: Technology::Unknown,
+            temperature: None,
+            time_to_empty: 0,
+            time_to_full: 0,
+            type_,
+            update_time: 0,
+            vendor: String::new(),
+            voltage: Volts(0.0),
+            warning_level: WarningLevel::None,
+        }
+    }
+
+    /// The current full energy of the battery relative to its design energy, in percent.
+    ///
+    /// Returns `None` if the device does not report a design energy.
+    pub fn battery_health_percent(&self) -> Option<f64> {
+        battery_health(self.energy_full, self.energy_full_design)
+    }
+
+    /// The icon name reported by the daemon, or one computed from the kind, percentage and
+    /// state if it is empty.
+    pub fn icon_name_or_fallback(&self) -> String {
+        if !self.icon_name.is_empty() {
+            return self.icon_name.clone();
+        }
+
+        device_fallback_icon(self.type_, self.state, self.percentage)
+    }
+
+    /// The estimated time until the device is empty, if known.
+    pub fn time_to_empty_duration(&self) -> Option<Duration> {
+        estimate(self.time_to_empty)
+    }
+
+    /// The estimated time until the device is fully charged, if known.
+    pub fn time_to_full_duration(&self) -> Option<Duration> {
+        estimate(self.time_to_full)
+    }
+
+    /// When the daemon last read the device.
+    pub fn last_updated(&self) -> SystemTime {
+        timestamp(self.update_time)
+    }
+
+    /// When the daemon last read the device, as a `chrono` datetime.
+    #[cfg(feature = "chrono")]
+    pub fn update_time_utc(&self) -> chrono::DateTime<chrono::Utc> {
+        self.last_updated().into()
+    }
+
+    /// When the daemon last read the device, as a `time` datetime in UTC.
+    #[cfg(feature = "time")]
+    pub fn update_time_offset(&self) -> time::OffsetDateTime {
+        self.last_updated().into()
+    }
+
+    /// The signed rate of energy flowing into the battery, as by `DeviceProxy::power_flow`.
+    pub fn power_flow(&self) -> Watts {
+        power_flow(self.energy_rate, self.state)
+    }
+
+    /// How `newer`, a later snapshot of the same device, differs from this one.
+    pub fn diff(&self, newer: &DeviceSnapshot) -> DeviceDelta {
+        DeviceDelta::new(self, newer)
+    }
+
+    /// Whether the device is a battery of the system, as by `DeviceProxy::is_system_battery`.
+    pub fn is_system_battery(&self) -> bool {
+        is_system_battery(self.type_, self.power_supply, &self.native_path)
+    }
+
+    /// The optional properties which the device reports.
+    pub fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities::of(self)
+    }
+
+    /// A short label telling the batteries of a system apart, such as `BAT0`, taken from the
+    /// last component of `native_path`.
+    pub fn label(&self) -> &str {
+        device_label(&self.native_path)
+    }
+}
+
+impl TryFrom<HashMap<String, OwnedValue>> for DeviceSnapshot {
+    type Error = Error;
+
+    fn try_from(properties: HashMap<String, OwnedValue>) -> Result<Self> {
+        let mut decoder = Decoder::new(properties);
+        let decoded = decoder.decode();
+
+        if let Some(why) = decoder.error {
+            return Err(why);
+        }
+
+        let mut snapshot = DeviceSnapshot::new(decoded.type_.unwrap_or(DeviceType::Unknown));
+        decoded.apply_to(&mut snapshot);
+        Ok(snapshot)
+    }
+}
+
+/// A property which `DeviceSnapshot::decode_lenient` could not decode, and left as `None`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeWarning {
+    /// The daemon did not send the property, as older daemons do for newer properties.
+    Missing(&'static str),
+    /// The value of the property has an unexpected type.
+    Malformed {
+        property: &'static str,
+        reason: String,
+    },
+}
+
+impl DecodeWarning {
+    /// The name of the property, such as `Percentage`.
+    pub fn property(&self) -> &'static str {
+        match self {
+            DecodeWarning::Missing(property) => property,
+            DecodeWarning::Malformed { property, .. } => property,
+        }
+    }
+}
+
+impl fmt::Display for DecodeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeWarning::Missing(property) => write!(f, "{} is missing", property),
+            DecodeWarning::Malformed { property, reason } => {
+                write!(f, "{} is malformed: {}", property, reason)
+            }
+        }
+    }
+}
+
+impl DeviceSnapshot {
+    /// Like `DeviceSnapshot::try_from`, but decodes each property on its own, leaving those
+    /// which are missing or have an unexpected type as `None` and reporting them as warnings.
+    ///
+    /// Useful against older daemons and vendor patches, where a single odd property would
+    /// otherwise fail the whole snapshot. Properties which older daemons do not send, such as
+    /// `ChargeCycles`, are `None` without a warning when missing. `DeviceChange::apply_to`
+    /// fills a snapshot with the properties which were decoded.
+    ///
+    /// ```rust
+    /// use std::collections::HashMap;
+    /// use std::convert::TryFrom;
+    /// use upower_dbus::{DecodeWarning, DeviceSnapshot, DeviceType, Percentage};
+    /// use upower_dbus::zvariant::{OwnedValue, Value};
+    ///
+    /// // A truncated reply, with a garbled percentage.
+    /// let mut properties = HashMap::new();
+    /// properties.insert("Type".to_owned(), OwnedValue::from(2u32));
+    /// properties.insert("Percentage".to_owned(), OwnedValue::from(Value::from("84%")));
+    /// properties.insert("Vendor".to_owned(), OwnedValue::from(Value::from("ACME")));
+    ///
+    /// assert!(DeviceSnapshot::try_from(properties.clone()).is_err());
+    ///
+    /// let (battery, warnings) = DeviceSnapshot::decode_lenient(properties);
+    /// assert_eq!(battery.type_, Some(DeviceType::Battery));
+    /// assert_eq!(battery.vendor.as_deref(), Some("ACME"));
+    /// assert_eq!(battery.percentage, None);
+    ///
+    /// assert!(warnings.iter().any(|warning| matches!(
+    ///     warning,
+    ///     DecodeWarning::Malformed { property: "Percentage", .. }
+    /// )));
+    /// assert!(warnings.contains(&DecodeWarning::Missing("State")));
+    /// assert!(!warnings.iter().any(|warning| warning.property() == "ChargeCycles"));
+    ///
+    /// let mut snapshot = DeviceSnapshot::new(DeviceType::Battery);
+    /// battery.apply_to(&mut snapshot);
+    /// assert_eq!(snapshot.vendor, "ACME");
+    /// assert_eq!(snapshot.percentage, Percentage::new(0.0));
+    /// ```
+    pub fn decode_lenient(
+        properties: HashMap<String, OwnedValue>,
+    ) -> (DeviceChange, Vec<DecodeWarning>) {
+        let mut decoder = Decoder::new(properties);
+        let decoded = decoder.decode();
+        (decoded, decoder.warnings)
+    }
+}
+
+/// Decodes each property on its own, recording a warning for each which is missing or
+/// malformed, and the error of the first.
+struct Decoder {
+    properties: HashMap<String, OwnedValue>,
+    warnings: Vec<DecodeWarning>,
+    error: Option<Error>,
+}
+
+impl Decoder {
+    fn new(properties: HashMap<String, OwnedValue>) -> Self {
+        Self {
+            properties,
+            warnings: Vec::new(),
+            error: None,
+        }
+    }
+
+    fn decode(&mut self) -> DeviceChange {
+        DeviceChange {
+            type_: self.required("Type"),
+            battery_level: self.required("BatteryLevel"),
+            capacity: self.required("Capacity"),
+            charge_cycles: self.optional("ChargeCycles").map(cycles),
+            charge_end_threshold: self.optional("ChargeEndThreshold"),
+            charge_start_threshold: self.optional("ChargeStartThreshold"),
+            charge_threshold_enabled: self.optional("ChargeThresholdEnabled"),
+            charge_threshold_supported: self.optional("ChargeThresholdSupported"),
+            energy: self.required("Energy"),
+            energy_empty: self.required("EnergyEmpty"),
+            energy_full: self.required("EnergyFull"),
+            energy_full_design: self.required("EnergyFullDesign"),
+            energy_rate: self.required("EnergyRate"),
+            has_history: self.required("HasHistory"),
+            has_statistics: self.required("HasStatistics"),
+            icon_name: self.required("IconName"),
+            is_present: self.required("IsPresent"),
+            is_rechargeable: self.required("IsRechargeable"),
+            luminosity: self.required("Luminosity"),
+            model: self.required("Model"),
+            native_path: self.required("NativePath"),
+            online: self.required("Online"),
+            percentage: self.required("Percentage"),
+            power_supply: self.required("PowerSupply"),
+            serial: self.required("Serial"),
+            state: self.required("State"),
+            technology: self.required("Technology"),
+            temperature: self.required("Temperature").map(temperature),
+            time_to_empty: self.required("TimeToEmpty"),
+            time_to_full: self.required("TimeToFull"),
+            update_time: self.required("UpdateTime"),
+            vendor: self.required("Vendor"),
+            voltage: self.required("Voltage"),
+            warning_level: self.required("WarningLevel"),
+        }
+    }
+
+    /// Decodes a property which every supported daemon sends.
+    fn required<T>(&mut self, name: &'static str) -> Option<T>
+    where
+        T: TryFrom<OwnedValue, Error = zvariant::Error>,
+    {
+        match self.properties.remove(name) {
+            Some(value) => self.decoded(name, T::try_from(value)),
+            None => {
+                self.fail(DecodeWarning::Missing(name), Error::MissingProperty(name));
+                None
+            }
+        }
+    }
+
+    /// Decodes a property which older daemons do not send.
+    fn optional<T>(&mut self, name: &'static str) -> Option<T>
+    where
+        T: TryFrom<OwnedValue, Error = zvariant::Error>,
+    {
+        let value = self.properties.remove(name)?;
+        self.decoded(name, T::try_from(value))
+    }
+
+    fn decoded<T>(&mut self, name: &'static str, value: Result<T, zvariant::Error>) -> Option<T> {
+        match value {
+            Ok(value) => Some(value),
+            Err(why) => {
+                let warning = DecodeWarning::Malformed {
+                    property: name,
+                    reason: why.to_string(),
+                };
+
+                self.fail(warning, Error::from(why));
+                None
+            }
+        }
+    }
+
+    fn fail(&mut self, warning: DecodeWarning, error: Error) {
+        self.warnings.push(warning);
+        self.error.get_or_insert(error);
+    }
+}
+
+pub(crate) fn take_optional<T>(
+    properties: &mut HashMap<String, OwnedValue>,
+    name: &'static str,
+) -> Result<Option<T>>
+where
+    T: TryFrom<OwnedValue, Error = zvariant::Error>,
+{
+    match properties.remove(name) {
+        Some(value) => Ok(Some(T::try_from(value)?)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DecodeWarning, DeviceSnapshot};
+    use crate::device::{BatteryLevel, BatteryState, DeviceType, Technology, WarningLevel};
+    use crate::error::Error;
+    use crate::units::{Celsius, Percentage, Volts, WattHours, Watts};
+    use std::collections::HashMap;
+    use std::convert::TryFrom;
+    use zbus::zvariant::{OwnedValue, Value};
+
+    /// Every property sent by the daemon, each with a value of its own, so that decoding one
+    /// property into the field of another fails the tests. The booleans alternate instead.
+    fn properties() -> HashMap<String, OwnedValue> {
+        let properties = [
+            ("BatteryLevel", Value::U32(1)),
+            ("Capacity", Value::F64(91.5)),
+            ("Energy", Value::F64(40.25)),
+            ("EnergyEmpty", Value::F64(0.5)),
+            ("EnergyFull", Value::F64(44.0)),
+            ("EnergyFullDesign", Value::F64(48.0)),
+            ("EnergyRate", Value::F64(9.75)),
+            ("HasHistory", Value::Bool(true)),
+            ("HasStatistics", Value::Bool(false)),
+            ("IconName", Value::from("battery-good-charging-symbolic")),
+            ("IsPresent", Value::Bool(true)),
+            ("IsRechargeable", Value::Bool(false)),
+            ("Luminosity", Value::F64(3.0)),
+            ("Model", Value::from("5B10W13930")),
+            ("NativePath", Value::from("BAT0")),
+            ("Online", Value::Bool(false)),
+            ("Percentage", Value::F64(84.0)),
+            ("PowerSupply", Value::Bool(true)),
+            ("Serial", Value::from("1234")),
+            ("State", Value::U32(5)),
+            ("Technology", Value::U32(3)),
+            ("Temperature", Value::F64(31.5)),
+            ("TimeToEmpty", Value::I64(3600)),
+            ("TimeToFull", Value::I64(5400)),
+            ("Type", Value::U32(2)),
+            ("UpdateTime", Value::U64(1_700_000_000)),
+            ("Vendor", Value::from("SMP")),
+            ("Voltage", Value::F64(12.25)),
+            ("WarningLevel", Value::U32(4)),
+        ];
+
+        properties
+            .iter()
+            .map(|(name, value)| (name.to_string(), OwnedValue::from(value.clone())))
+            .collect()
+    }
+
+    #[test]
+    fn complete_properties_decode() {
+        let snapshot = DeviceSnapshot::try_from(properties()).unwrap();
+        assert_eq!(
+            snapshot,
+            DeviceSnapshot {
+                battery_level: BatteryLevel::None,
+                capacity: 91.5,
+                charge_cycles: None,
+                charge_end_threshold: None,
+                charge_start_threshold: None,
+                charge_threshold_enabled: None,
+                charge_threshold_supported: None,
+                energy: WattHours(40.25),
+                energy_empty: WattHours(0.5),
+                energy_full: WattHours(44.0),
+                energy_full_design: WattHours(48.0),
+                energy_rate: Watts(9.75),
+                has_history: true,
+                has_statistics: false,
+                icon_name: "battery-good-charging-symbolic".to_owned(),
+                is_present: true,
+                is_rechargeable: false,
+                luminosity: 3.0,
+                model: "5B10W13930".to_owned(),
+                native_path: "BAT0".to_owned(),
+                online: false,
+                percentage: Percentage::new(84.0),
+                power_supply: true,
+                serial: "1234".to_owned(),
+                state: BatteryState::PendingCharge,
+                technology: Technology::LithiumIronPhosphate,
+                temperature: Some(Celsius(31.5)),
+                time_to_empty: 3600,
+                time_to_full: 5400,
+                type_: DeviceType::Battery,
+                update_time: 1_700_000_000,
+                vendor: "SMP".to_owned(),
+                voltage: Volts(12.25),
+                warning_level: WarningLevel::Critical,
+            }
+        );
+
+        let (decoded, warnings) = DeviceSnapshot::decode_lenient(properties());
+        assert_eq!(warnings, Vec::new());
+
+        let mut lenient = DeviceSnapshot::new(DeviceType::Unknown);
+        decoded.apply_to(&mut lenient);
+        assert_eq!(lenient, snapshot);
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn update_time_as_chrono() {
+        let snapshot = DeviceSnapshot::try_from(properties()).unwrap();
+        assert_eq!(snapshot.update_time_utc().timestamp(), 1_700_000_000);
+    }
+
+    #[test]
+    #[cfg(feature = "time")]
+    fn update_time_as_time() {
+        let snapshot = DeviceSnapshot::try_from(properties()).unwrap();
+        let updated = snapshot.update_time_offset();
+        assert_eq!(updated.unix_timestamp(), 1_700_000_000);
+        assert_eq!(updated.offset(), time::UtcOffset::UTC);
+    }
+
+    #[test]
+    fn wrong_variant_type() {
+        let mut properties = properties();
+        properties.insert(
+            "State".to_owned(),
+            OwnedValue::from(Value::from("charging")),
+        );
+
+        let error = DeviceSnapshot::try_from(properties.clone()).unwrap_err();
+        assert!(matches!(error, Error::Transport(_)), "{:?}", error);
+
+        let (decoded, warnings) = DeviceSnapshot::decode_lenient(properties);
+        assert_eq!(decoded.state, None);
+        assert_eq!(decoded.percentage, Some(Percentage::new(84.0)));
+        assert!(matches!(
+            warnings.as_slice(),
+            [DecodeWarning::Malformed {
+                property: "State",
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn truncated_properties() {
+        let mut properties = properties();
+        properties.retain(|name, _| name == "Type" || name == "Percentage");
+
+        let error = DeviceSnapshot::try_from(properties.clone()).unwrap_err();
+        assert!(matches!(error, Error::MissingProperty("BatteryLevel")));
+
+        let (decoded, warnings) = DeviceSnapshot::decode_lenient(properties);
+        assert_eq!(decoded.type_, Some(DeviceType::Battery));
+        assert_eq!(decoded.percentage, Some(Percentage::new(84.0)));
+        assert_eq!(decoded.vendor, None);
+        assert_eq!(decoded.changed_properties(), ["Percentage", "Type"]);
+
+        assert_eq!(warnings.len(), 27);
+        assert!(warnings
+            .iter()
+            .all(|warning| matches!(warning, DecodeWarning::Missing(_))));
+    }
+
+    #[test]
+    fn missing_required_property() {
+        let mut properties = properties();
+        properties.remove("Type");
+
+        let error = DeviceSnapshot::try_from(properties.clone()).unwrap_err();
+        assert!(matches!(error, Error::MissingProperty("Type")));
+
+        let (decoded, warnings) = DeviceSnapshot::decode_lenient(properties);
+        assert_eq!(decoded.type_, None);
+        assert_eq!(decoded.state, Some(BatteryState::PendingCharge));
+        assert_eq!(warnings, [DecodeWarning::Missing("Type")]);
+    }
+
+    #[test]
+    fn malformed_optional_property() {
+        let mut properties = properties();
+        properties.insert(
+            "ChargeCycles".to_owned(),
+            OwnedValue::from(Value::from("many")),
+        );
+
+        assert!(DeviceSnapshot::try_from(properties.clone()).is_err());
+
+        let (decoded, warnings) = DeviceSnapshot::decode_lenient(properties);
+        assert_eq!(decoded.charge_cycles, None);
+        assert_eq!(decoded.charge_end_threshold, None);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].property(), "ChargeCycles");
+    }
+}
@@ -0,0 +1,69 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use async_trait::async_trait;
+
+use crate::device::BatteryState;
+use crate::error::Result;
+use crate::monitor::PowerEventStream;
+use crate::snapshot::DeviceSnapshot;
+use crate::units::Percentage;
+use crate::upower::UPowerProxy;
+
+/// A source of power information.
+///
+/// Implemented by `UPowerProxy`, and by the sysfs backend and the mock daemon when their
+/// features are enabled, so that application logic can be written once and tested against
+/// any of them.
+#[async_trait]
+pub trait PowerSource: Send + Sync {
+    /// Whether the system is running on battery power.
+    async fn on_battery(&self) -> Result<bool>;
+
+    /// The properties of the composite display device.
+    async fn display_snapshot(&self) -> Result<DeviceSnapshot>;
+
+    /// The properties of every device.
+    async fn device_snapshots(&self) -> Result<Vec<DeviceSnapshot>>;
+
+    /// Changes in the power state of the system.
+    async fn events(&self) -> Result<PowerEventStream<'static>>;
+
+    /// The charge of the display device, in percent.
+    async fn percentage(&self) -> Result<Percentage> {
+        Ok(self.display_snapshot().await?.percentage)
+    }
+
+    /// The state of the display device.
+    async fn state(&self) -> Result<BatteryState> {
+        Ok(self.display_snapshot().await?.state)
+    }
+}
+
+#[async_trait]
+impl PowerSource for UPowerProxy<'static> {
+    async fn on_battery(&self) -> Result<bool> {
+        Ok(UPowerProxy::on_battery(self).await?)
+    }
+
+    async fn display_snapshot(&self) -> Result<DeviceSnapshot> {
+        self.display_device().await?.snapshot().await
+    }
+
+    async fn device_snapshots(&self) -> Result<Vec<DeviceSnapshot>> {
+        let devices = self.snapshot_all_devices().await?;
+        Ok(devices.into_iter().map(|(_, snapshot)| snapshot).collect())
+    }
+
+    async fn events(&self) -> Result<PowerEventStream<'static>> {
+        self.monitor().await
+    }
+
+    async fn percentage(&self) -> Result<Percentage> {
+        Ok(self.display_device().await?.percentage().await?)
+    }
+
+    async fn state(&self) -> Result<BatteryState> {
+        Ok(self.display_device().await?.state().await?)
+    }
+}
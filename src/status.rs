@@ -0,0 +1,231 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::future;
+use futures_util::stream::StreamExt;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+use zbus::Task;
+
+use crate::device::{BatteryState, DeviceType};
+use crate::error::{Error, Result};
+use crate::id::DeviceId;
+use crate::monitor::{PowerEvent, PowerEventStream};
+use crate::snapshot::DeviceSnapshot;
+use crate::units::Percentage;
+use crate::upower::UPowerProxy;
+use crate::watch::WatchCell;
+
+/// Everything a status bar typically shows about the power state, fetched by
+/// `UPowerProxy::power_status`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PowerStatus {
+    pub on_battery: bool,
+    pub lid_is_closed: bool,
+    /// The charge of the display device.
+    pub percentage: Percentage,
+    /// The state of the display device.
+    pub state: BatteryState,
+    pub time_to_empty: Option<Duration>,
+    pub time_to_full: Option<Duration>,
+    /// The number of batteries which power the system.
+    pub batteries: usize,
+}
+
+impl PowerStatus {
+    /// Takes the values of the display device from its snapshot.
+    pub(crate) fn apply_display(&mut self, display: &DeviceSnapshot) {
+        self.percentage = display.percentage;
+        self.state = display.state;
+        self.time_to_empty = display.time_to_empty_duration();
+        self.time_to_full = display.time_to_full_duration();
+    }
+
+    /// The time until the display device is empty while discharging, or full while charging.
+    pub fn time_remaining(&self) -> Option<Duration> {
+        match self.state {
+            BatteryState::Charging => self.time_to_full,
+            BatteryState::Discharging => self.time_to_empty,
+            _ => None,
+        }
+    }
+}
+
+/// The receiving end of `UPowerProxy::watch_power_status`, which always holds the latest
+/// `PowerStatus`.
+///
+/// It behaves like a `tokio::sync::watch::Receiver`, on any executor, and with the `tokio`
+/// feature `into_watch` turns it into one. The status is kept
+/// current by a task on the executor of the connection, which applies each event of
+/// `UPowerProxy::monitor` to it and stops once every clone of the receiver is dropped. Only
+/// the latest value is kept, so a receiver which falls behind skips intermediate values.
+#[derive(Clone)]
+pub struct PowerStatusReceiver {
+    status: Arc<WatchCell<PowerStatus>>,
+    seen: u64,
+    _task: Arc<Task<()>>,
+}
+
+impl fmt::Debug for PowerStatusReceiver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PowerStatusReceiver")
+            .field("status", &self.get())
+            .finish_non_exhaustive()
+    }
+}
+
+impl PowerStatusReceiver {
+    /// The latest status, marking it as seen.
+    pub fn borrow_and_update(&mut self) -> PowerStatus {
+        self.status
+            .read_and_mark_seen(&mut self.seen, PowerStatus::clone)
+    }
+
+    /// The latest status, without marking it as seen.
+    pub fn get(&self) -> PowerStatus {
+        self.status.read(PowerStatus::clone)
+    }
+
+    /// Whether the status changed since it was last seen.
+    pub fn has_changed(&self) -> bool {
+        self.status.has_changed(self.seen)
+    }
+
+    /// Waits until the status changes from the one last seen, and marks it as seen.
+    ///
+    /// Fails with the error which stopped the status from being updated, such as the
+    /// connection closing.
+    pub async fn changed(&mut self) -> Result<()> {
+        self.status.changed(&mut self.seen).await
+    }
+
+    /// A `tokio::sync::watch::Receiver` of the status, for code built around tokio's channels.
+    ///
+    /// The status is forwarded to it by a task spawned on the current tokio runtime, which
+    /// stops once every clone of the returned receiver is dropped. When the status stops being
+    /// updated, the sender is dropped, so `changed` on the receiver fails without the error.
+    ///
+    /// # Panics
+    ///
+    /// If called outside of a tokio runtime.
+    #[cfg(feature = "tokio")]
+    pub fn into_watch(mut self) -> tokio::sync::watch::Receiver<PowerStatus> {
+        let (sender, receiver) = tokio::sync::watch::channel(self.borrow_and_update());
+
+        tokio::spawn(async move {
+            loop {
+                let changed = {
+                    let changed = self.changed();
+                    let closed = sender.closed();
+                    futures_util::pin_mut!(changed, closed);
+                    matches!(
+                        future::select(changed, closed).await,
+                        future::Either::Left((Ok(()), _))
+                    )
+                };
+
+                if !changed {
+                    break;
+                }
+
+                sender.send_replace(self.borrow_and_update());
+            }
+        });
+
+        receiver
+    }
+
+    pub(crate) async fn spawn(upower: UPowerProxy<'static>) -> Result<Self> {
+        let events = upower.monitor().await?;
+        let (status, batteries) = upower.power_status_and_batteries().await?;
+
+        let status = Arc::new(WatchCell::new(status));
+
+        let task = upower.connection().executor().spawn(
+            update(upower.clone(), events, status.clone(), batteries),
+            "upower-dbus power status",
+        );
+
+        Ok(Self {
+            status,
+            seen: 0,
+            _task: Arc::new(task),
+        })
+    }
+}
+
+/// Applies each event to the status, until watching the daemon fails.
+///
+/// Only a restart of the daemon reads the whole status again.
+async fn update(
+    upower: UPowerProxy<'static>,
+    mut events: PowerEventStream<'static>,
+    status: Arc<WatchCell<PowerStatus>>,
+    mut batteries: BTreeSet<DeviceId>,
+) {
+    let why = loop {
+        let event = match events.next().await {
+            Some(Ok(event)) => event,
+            Some(Err(why)) => break why,
+            None => {
+                break Error::Transport(zbus::Error::InputOutput(Arc::new(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "the connection was closed",
+                ))))
+            }
+        };
+
+        let count = batteries.len();
+
+        match event {
+            PowerEvent::OnBatteryChanged(on_battery) => {
+                apply(&status, |status| status.on_battery = on_battery)
+            }
+            PowerEvent::LidClosed(closed) => apply(&status, |status| status.lid_is_closed = closed),
+            PowerEvent::DisplayDeviceChanged(display) => {
+                apply(&status, |status| status.apply_display(&display))
+            }
+            PowerEvent::DeviceAdded(device) => {
+                let is_battery = future::try_join(device.type_(), device.power_supply()).await;
+
+                match is_battery.map_err(Error::from) {
+                    Ok((DeviceType::Battery, true)) => {
+                        batteries.insert(DeviceId::from(device.path().to_owned()));
+                    }
+                    // The device was removed again before it could be read.
+                    Ok(_) | Err(Error::NoSuchDevice) => (),
+                    Err(why) => break why,
+                }
+            }
+            PowerEvent::DeviceRemoved(path) => {
+                batteries.remove(&DeviceId::from(path));
+            }
+            PowerEvent::ServiceRestarted => match upower.power_status_and_batteries().await {
+                Ok((current, current_batteries)) => {
+                    batteries = current_batteries;
+                    apply(&status, |status| *status = current);
+                }
+                Err(why) => break why,
+            },
+        }
+
+        if batteries.len() != count {
+            apply(&status, |status| status.batteries = batteries.len());
+        }
+    };
+
+    status.close(why);
+}
+
+/// Applies `update` to the status, waking receivers if it changed.
+fn apply(status: &WatchCell<PowerStatus>, update: impl FnOnce(&mut PowerStatus)) {
+    status.modify(|status| {
+        let previous = status.clone();
+        update(status);
+        *status != previous
+    });
+}
@@ -0,0 +1,521 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Reads power supplies from sysfs directly, for systems without the UPower daemon.
+//!
+//! Devices are described with the same `DeviceSnapshot` type as the D-Bus proxies, with
+//! their values converted to the units used by the daemon.
+//!
+//! ```rust,no_run
+//! use upower_dbus::sysfs::Sysfs;
+//!
+//! # fn main() -> upower_dbus::Result<()> {
+//! let sysfs = Sysfs::new();
+//!
+//! for device in sysfs.devices()? {
+//!     println!("{}: {}", device.native_path, device.percentage);
+//! }
+//!
+//! println!("On Battery: {}", sysfs.on_battery()?);
+//! # Ok(())
+//! # }
+//! ```
+
+use async_trait::async_trait;
+use futures_util::stream::{self, StreamExt};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::aggregate::{estimate_times, warning_level, BatteryAggregate};
+use crate::device::{battery_health, cycles, temperature};
+use crate::device::{BatteryState, DeviceType, Technology};
+use crate::error::{Error, Result};
+use crate::icon::fallback_icon_name;
+use crate::monitor::{PowerEvent, PowerEventStream};
+use crate::snapshot::DeviceSnapshot;
+use crate::source::PowerSource;
+use crate::timer;
+use crate::units::{Percentage, Volts, WattHours, Watts};
+
+/// Where the kernel exposes power supplies.
+pub const POWER_SUPPLY_PATH: &str = "/sys/class/power_supply";
+
+/// How often `PowerSource::events` reads sysfs, by default.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The power supplies found in a sysfs directory.
+#[derive(Clone, Debug)]
+pub struct Sysfs {
+    root: PathBuf,
+    poll_interval: Duration,
+}
+
+impl Default for Sysfs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sysfs {
+    /// Reads the power supplies in `/sys/class/power_supply`.
+    pub fn new() -> Self {
+        Self::with_root(POWER_SUPPLY_PATH)
+    }
+
+    /// Reads the power supplies in `root`, which is laid out like `/sys/class/power_supply`.
+    pub fn with_root<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+
+    /// Sets how often `PowerSource::events` reads sysfs, as the kernel does not signal
+    /// changes.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// The directory the power supplies are read from.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The names of every power supply, such as `AC` and `BAT0`, in sorted order.
+    pub fn names(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+
+        for entry in fs::read_dir(&self.root)? {
+            if let Ok(name) = entry?.file_name().into_string() {
+                names.push(name);
+            }
+        }
+
+        names.sort();
+        Ok(names)
+    }
+
+    /// Reads every power supply.
+    pub fn devices(&self) -> Result<Vec<DeviceSnapshot>> {
+        self.names()?.iter().map(|name| self.device(name)).collect()
+    }
+
+    /// Reads the power supply named `name`, failing with `Error::NoSuchDevice` if it does not
+    /// exist.
+    pub fn device(&self, name: &str) -> Result<DeviceSnapshot> {
+        let path = self.root.join(name);
+        if !path.is_dir() {
+            return Err(Error::NoSuchDevice);
+        }
+
+        Ok(read_device(name, &path))
+    }
+
+    /// Whether the system is running on battery power.
+    ///
+    /// Like the daemon, this is the case when no line power supply is online and a system
+    /// battery is discharging.
+    pub fn on_battery(&self) -> Result<bool> {
+        Ok(on_battery(&self.devices()?))
+    }
+
+    /// A composite of every system battery, like the daemon's display device.
+    pub fn display_device(&self) -> Result<DeviceSnapshot> {
+        Ok(display_device(&self.devices()?))
+    }
+}
+
+/// Reads sysfs directly from the calling task, which is quick enough not to need a thread.
+///
+/// The event stream polls, and only emits `OnBatteryChanged` and `DisplayDeviceChanged`.
+#[async_trait]
+impl PowerSource for Sysfs {
+    async fn on_battery(&self) -> Result<bool> {
+        Sysfs::on_battery(self)
+    }
+
+    async fn display_snapshot(&self) -> Result<DeviceSnapshot> {
+        self.display_device()
+    }
+
+    async fn device_snapshots(&self) -> Result<Vec<DeviceSnapshot>> {
+        self.devices()
+    }
+
+    async fn events(&self) -> Result<PowerEventStream<'static>> {
+        let sysfs = self.clone();
+        let devices = self.devices()?;
+        let initial = (on_battery(&devices), display_device(&devices));
+
+        let events = stream::unfold(initial, move |(was_on_battery, was_display)| {
+            let sysfs = sysfs.clone();
+            async move {
+                timer::sleep(sysfs.poll_interval).await;
+
+                let devices = match sysfs.devices() {
+                    Ok(devices) => devices,
+                    Err(why) => return Some((vec![Err(why)], (was_on_battery, was_display))),
+                };
+
+                let on_battery = on_battery(&devices);
+                let display = display_device(&devices);
+                let mut events = Vec::new();
+
+                if on_battery != was_on_battery {
+                    events.push(Ok(PowerEvent::OnBatteryChanged(on_battery)));
+                }
+
+                if !same_reading(&display, &was_display) {
+                    events.push(Ok(PowerEvent::DisplayDeviceChanged(Box::new(
+                        display.clone(),
+                    ))));
+                }
+
+                Some((events, (on_battery, display)))
+            }
+        });
+
+        Ok(PowerEventStream {
+            inner: Box::pin(events.flat_map(stream::iter)),
+        })
+    }
+}
+
+/// Whether two readings are equal, ignoring when they were taken.
+fn same_reading(a: &DeviceSnapshot, b: &DeviceSnapshot) -> bool {
+    DeviceSnapshot {
+        update_time: b.update_time,
+        ..a.clone()
+    } == *b
+}
+
+/// Whether `devices` describe a system running on battery power.
+pub(crate) fn on_battery(devices: &[DeviceSnapshot]) -> bool {
+    let line_power = devices
+        .iter()
+        .any(|device| device.type_ == DeviceType::LinePower && device.online);
+
+    let discharging = devices.iter().any(|device| {
+        device.type_ == DeviceType::Battery
+            && device.power_supply
+            && device.state == BatteryState::Discharging
+    });
+
+    !line_power && discharging
+}
+
+/// Combines the system batteries in `devices` into one device.
+fn display_device(devices: &[DeviceSnapshot]) -> DeviceSnapshot {
+    let mut display = BatteryAggregate::new(devices.to_vec()).combined;
+    display.update_time = now();
+    display
+}
+
+fn read_device(name: &str, path: &Path) -> DeviceSnapshot {
+    let kind = read_string(path, "type").unwrap_or_default();
+
+    let type_ = match kind.as_str() {
+        "Battery" => DeviceType::Battery,
+        "Mains" | "USB" => DeviceType::LinePower,
+        "UPS" => DeviceType::Ups,
+        _ => DeviceType::Unknown,
+    };
+
+    let mut device = DeviceSnapshot::new(type_);
+    device.native_path = name.to_owned();
+    device.update_time = now();
+    device.vendor = read_string(path, "manufacturer").unwrap_or_default();
+    device.model = read_string(path, "model_name").unwrap_or_default();
+    device.serial = read_string(path, "serial_number").unwrap_or_default();
+    device.is_present = read_number::<u8>(path, "present") != Some(0);
+    device.online = read_number(path, "online").is_some_and(|online: u8| online != 0);
+
+    if type_ == DeviceType::LinePower {
+        device.power_supply = true;
+        device.icon_name = String::from("ac-adapter-symbolic");
+        return device;
+    }
+
+    // Peripherals mark themselves with a `Device` scope.
+    device.power_supply = read_string(path, "scope").as_deref() != Some("Device");
+
+    device.state = match read_string(path, "status").as_deref() {
+        Some("Charging") => BatteryState::Charging,
+        Some("Discharging") => BatteryState::Discharging,
+        Some("Full") => BatteryState::FullyCharged,
+        Some("Not charging") => BatteryState::PendingCharge,
+        Some("Empty") => BatteryState::Empty,
+        _ => BatteryState::Unknown,
+    };
+
+    device.technology = match read_string(path, "technology").as_deref() {
+        Some("Li-ion") => Technology::LithiumIon,
+        Some("Li-poly") => Technology::LithiumPolymer,
+        Some("LiFe") => Technology::LithiumIronPhosphate,
+        Some("Pb") | Some("PbAc") => Technology::LeadAcid,
+        Some("NiCd") => Technology::NickelCadmium,
+        Some("NiMH") => Technology::NickelMetalHydride,
+        _ => Technology::Unknown,
+    };
+
+    let voltage = micro(path, "voltage_now").unwrap_or(0.0);
+    let design_voltage = micro(path, "voltage_min_design")
+        .or_else(|| micro(path, "voltage_max_design"))
+        .unwrap_or(voltage);
+    device.voltage = Volts(voltage);
+
+    // Batteries report either energy in µWh, or charge in µAh which is converted with the
+    // design voltage.
+    let energy = |name: &str| {
+        micro(path, &["energy_", name].concat())
+            .or_else(|| {
+                micro(path, &["charge_", name].concat()).map(|charge| charge * design_voltage)
+            })
+            .map_or(WattHours::default(), WattHours)
+    };
+
+    device.energy = energy("now");
+    device.energy_full = energy("full");
+    device.energy_full_design = energy("full_design");
+    device.energy_empty = energy("empty");
+    device.energy_rate = Watts(
+        micro(path, "power_now")
+            .or_else(|| micro(path, "current_now").map(|current| current * voltage))
+            .unwrap_or(0.0)
+            .abs(),
+    );
+
+    device.percentage = Percentage::new(match read_number::<f64>(path, "capacity") {
+        Some(capacity) => capacity,
+        None if device.energy_full.0 > 0.0 => device.energy.0 / device.energy_full.0 * 100.0,
+        None => 0.0,
+    });
+
+    device.capacity = battery_health(device.energy_full, device.energy_full_design)
+        .map_or(100.0, |health| health.clamp(0.0, 100.0));
+
+    device.charge_cycles = read_number(path, "cycle_count").and_then(cycles);
+    device.temperature = read_number(path, "temp").and_then(|temp: f64| temperature(temp / 10.0));
+
+    let start = read_number(path, "charge_control_start_threshold");
+    let end = read_number(path, "charge_control_end_threshold");
+    device.charge_start_threshold = start;
+    device.charge_end_threshold = end;
+    device.charge_threshold_supported = Some(start.is_some() && end.is_some());
+
+    estimate_times(&mut device);
+    device.warning_level = warning_level(&device);
+    device.icon_name = fallback_icon_name(device.percentage, device.state);
+    device
+}
+
+fn read_string(path: &Path, attribute: &str) -> Option<String> {
+    let value = fs::read_to_string(path.join(attribute)).ok()?;
+    Some(value.trim().to_owned())
+}
+
+fn read_number<T: FromStr>(path: &Path, attribute: &str) -> Option<T> {
+    read_string(path, attribute)?.parse().ok()
+}
+
+/// Reads an attribute in millionths of a unit, such as µWh or µV.
+fn micro(path: &Path, attribute: &str) -> Option<f64> {
+    read_number::<f64>(path, attribute).map(|value| value / 1_000_000.0)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sysfs;
+    use crate::device::{BatteryState, DeviceType, Technology};
+    use crate::units::{WattHours, Watts};
+    use std::fs;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A directory laid out like `/sys/class/power_supply`, removed when dropped.
+    struct Root(PathBuf);
+
+    impl Root {
+        fn new() -> Self {
+            static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+            let path = std::env::temp_dir().join(format!(
+                "upower-dbus-sysfs-{}-{}",
+                std::process::id(),
+                NEXT.fetch_add(1, Ordering::Relaxed)
+            ));
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn supply(&self, name: &str, attributes: &[(&str, &str)]) -> &Self {
+            let path = self.0.join(name);
+            fs::create_dir_all(&path).unwrap();
+            for (attribute, value) in attributes {
+                fs::write(path.join(attribute), format!("{}\n", value)).unwrap();
+            }
+            self
+        }
+
+        fn sysfs(&self) -> Sysfs {
+            Sysfs::with_root(&self.0)
+        }
+    }
+
+    impl Drop for Root {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn charge_is_converted_with_the_design_voltage() {
+        let root = Root::new();
+        root.supply(
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("charge_now", "2000000"),
+                ("charge_full", "4000000"),
+                ("charge_full_design", "5000000"),
+                ("voltage_min_design", "11000000"),
+                ("voltage_now", "12000000"),
+            ],
+        );
+
+        let battery = root.sysfs().device("BAT0").unwrap();
+        assert!(close(battery.energy.0, 22.0));
+        assert!(close(battery.energy_full.0, 44.0));
+        assert!(close(battery.energy_full_design.0, 55.0));
+        assert!(close(battery.percentage.get(), 50.0));
+        assert!(close(battery.capacity, 80.0));
+    }
+
+    #[test]
+    fn energy_is_read_in_watt_hours() {
+        let root = Root::new();
+        root.supply(
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("energy_now", "30000000"),
+                ("energy_full", "60000000"),
+                ("power_now", "7500000"),
+            ],
+        );
+
+        let battery = root.sysfs().device("BAT0").unwrap();
+        assert_eq!(battery.energy, WattHours(30.0));
+        assert_eq!(battery.energy_rate, Watts(7.5));
+    }
+
+    #[test]
+    fn current_is_converted_with_the_voltage() {
+        let root = Root::new();
+        root.supply(
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("current_now", "-1500000"),
+                ("voltage_now", "12000000"),
+            ],
+        );
+
+        let battery = root.sysfs().device("BAT0").unwrap();
+        assert!(close(battery.energy_rate.0, 18.0));
+        assert!(close(battery.voltage.0, 12.0));
+    }
+
+    #[test]
+    fn status_and_technology_are_mapped() {
+        let cases = [
+            ("Charging", BatteryState::Charging),
+            ("Discharging", BatteryState::Discharging),
+            ("Full", BatteryState::FullyCharged),
+            ("Not charging", BatteryState::PendingCharge),
+            ("Empty", BatteryState::Empty),
+            ("Bogus", BatteryState::Unknown),
+        ];
+
+        for (status, state) in cases {
+            let root = Root::new();
+            root.supply("BAT0", &[("type", "Battery"), ("status", status)]);
+            assert_eq!(
+                root.sysfs().device("BAT0").unwrap().state,
+                state,
+                "{}",
+                status
+            );
+        }
+
+        let cases = [
+            ("Li-ion", Technology::LithiumIon),
+            ("Li-poly", Technology::LithiumPolymer),
+            ("LiFe", Technology::LithiumIronPhosphate),
+            ("Pb", Technology::LeadAcid),
+            ("PbAc", Technology::LeadAcid),
+            ("NiCd", Technology::NickelCadmium),
+            ("NiMH", Technology::NickelMetalHydride),
+            ("Unknown", Technology::Unknown),
+        ];
+
+        for (name, technology) in cases {
+            let root = Root::new();
+            root.supply("BAT0", &[("type", "Battery"), ("technology", name)]);
+            let battery = root.sysfs().device("BAT0").unwrap();
+            assert_eq!(battery.technology, technology, "{}", name);
+        }
+    }
+
+    #[test]
+    fn device_scope_is_not_a_power_supply() {
+        let root = Root::new();
+        root.supply("BAT0", &[("type", "Battery")]).supply(
+            "hidpp_battery_0",
+            &[("type", "Battery"), ("scope", "Device")],
+        );
+
+        let sysfs = root.sysfs();
+        assert!(sysfs.device("BAT0").unwrap().power_supply);
+        assert!(!sysfs.device("hidpp_battery_0").unwrap().power_supply);
+        assert_eq!(sysfs.names().unwrap(), ["BAT0", "hidpp_battery_0"]);
+    }
+
+    #[test]
+    fn on_battery_needs_a_discharging_system_battery_and_no_line_power() {
+        let root = Root::new();
+        root.supply("AC", &[("type", "Mains"), ("online", "0")])
+            .supply("BAT0", &[("type", "Battery"), ("status", "Discharging")]);
+        let sysfs = root.sysfs();
+        assert_eq!(sysfs.device("AC").unwrap().type_, DeviceType::LinePower);
+        assert!(sysfs.on_battery().unwrap());
+
+        root.supply("AC", &[("online", "1")]);
+        assert!(!sysfs.on_battery().unwrap());
+
+        // A discharging peripheral does not power the system.
+        let root = Root::new();
+        root.supply(
+            "hidpp_battery_0",
+            &[
+                ("type", "Battery"),
+                ("scope", "Device"),
+                ("status", "Discharging"),
+            ],
+        );
+        assert!(!root.sysfs().on_battery().unwrap());
+    }
+}
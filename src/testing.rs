@@ -0,0 +1,113 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Runs the mock daemon on a private bus, so tests never touch the system bus.
+//!
+//! Each `TestBus` spawns its own `dbus-daemon`, which must be installed, and stops it when
+//! dropped. Tests may run in parallel, as they do not share a bus.
+//!
+//! ```rust,no_run
+//! use upower_dbus::testing::TestUPower;
+//! use upower_dbus::{mock, DeviceType};
+//!
+//! # fn main() -> upower_dbus::Result<()> {
+//! # futures::executor::block_on(async move {
+//! let test = TestUPower::new().await?;
+//! test.mock.add_device("battery_BAT0", mock::device(DeviceType::Battery)).await?;
+//!
+//! test.mock.set_on_battery(true).await?;
+//! assert!(test.upower.on_battery().await?);
+//! assert_eq!(test.upower.devices().await?.len(), 1);
+//! # Ok(())
+//! # })
+//! # }
+//! ```
+
+use std::io::{self, BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+
+use crate::error::Result;
+use crate::mock::MockUPower;
+use crate::upower::UPowerProxy;
+
+/// A `dbus-daemon` serving a private bus, which is stopped when dropped.
+#[derive(Debug)]
+pub struct TestBus {
+    daemon: Child,
+    address: String,
+}
+
+impl TestBus {
+    /// Spawns a `dbus-daemon` with the session bus configuration, and waits until it listens.
+    pub fn new() -> Result<Self> {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let mut address = String::new();
+        if let Some(stdout) = daemon.stdout.take() {
+            BufReader::new(stdout).read_line(&mut address)?;
+        }
+
+        let address = address.trim().to_owned();
+        if address.is_empty() {
+            let _ = daemon.kill();
+            let _ = daemon.wait();
+            let why = io::Error::new(
+                io::ErrorKind::Other,
+                "dbus-daemon exited without printing its address",
+            );
+            return Err(why.into());
+        }
+
+        Ok(Self { daemon, address })
+    }
+
+    /// The address of the bus, such as `unix:path=/tmp/dbus-...`.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Opens a new connection to the bus.
+    pub async fn connect(&self) -> Result<zbus::Connection> {
+        Ok(zbus::ConnectionBuilder::address(self.address.as_str())?
+            .build()
+            .await?)
+    }
+}
+
+impl Drop for TestBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+/// The mock daemon on a private bus, with a proxy to it from a separate connection.
+#[derive(Debug)]
+pub struct TestUPower {
+    pub mock: MockUPower,
+    pub upower: UPowerProxy<'static>,
+    /// Declared last, so that the bus outlives the connections to it.
+    pub bus: TestBus,
+}
+
+impl TestUPower {
+    /// Spawns a private bus, serves the mock daemon on it and connects a proxy to it.
+    pub async fn new() -> Result<Self> {
+        let bus = TestBus::new()?;
+
+        let mock = MockUPower::new(&bus.connect().await?).await?;
+        let upower = UPowerProxy::new(&bus.connect().await?).await?;
+
+        Ok(Self { mock, upower, bus })
+    }
+
+    /// The connection of the proxy, for creating other proxies on the same bus.
+    pub fn connection(&self) -> &zbus::Connection {
+        self.upower.connection()
+    }
+}
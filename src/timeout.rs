@@ -0,0 +1,50 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::future::{self, Either};
+use std::future::Future;
+use std::time::Duration;
+
+use crate::error::{Error, Result};
+use crate::timer;
+
+/// Fails with `Error::Timeout` if `future` does not complete within `duration`.
+///
+/// Wraps any call on the crate's proxies, so that a status bar does not hang for the default
+/// timeout of the bus when the daemon is wedged. The call is abandoned, and any reply arriving
+/// later is dropped.
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use upower_dbus::{with_timeout, UPowerProxy};
+///
+/// # fn main() -> upower_dbus::Result<()> {
+/// # futures::executor::block_on(async move {
+/// let upower = UPowerProxy::system().await?;
+/// let on_battery = with_timeout(Duration::from_millis(500), upower.on_battery()).await?;
+/// # Ok(())
+/// # })
+/// # }
+/// ```
+///
+/// With `zbus::blocking`, block on the async proxy's call instead, through `zbus::block_on`.
+pub async fn with_timeout<F, T, E>(duration: Duration, future: F) -> Result<T>
+where
+    F: Future<Output = Result<T, E>>,
+    Error: From<E>,
+{
+    futures_util::pin_mut!(future);
+
+    let expired = timer::sleep(duration);
+    futures_util::pin_mut!(expired);
+
+    match future::select(future, expired).await {
+        Either::Left((result, _)) => Ok(result?),
+        Either::Right(_) => {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(?duration, "UPower did not reply in time");
+
+            Err(Error::Timeout)
+        }
+    }
+}
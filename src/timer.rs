@@ -0,0 +1,35 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::stream::Stream;
+use std::time::Duration;
+
+/// Completes after `duration`, on the timers of tokio if the `tokio` feature is enabled, and
+/// of async-io otherwise, in the same way zbus picks its runtime.
+pub(crate) async fn sleep(duration: Duration) {
+    #[cfg(feature = "tokio")]
+    tokio::time::sleep(duration).await;
+
+    #[cfg(not(feature = "tokio"))]
+    async_io::Timer::after(duration).await;
+}
+
+/// Ticks every `period`, starting one period from now.
+pub(crate) fn interval(period: Duration) -> impl Stream<Item = ()> + Send {
+    #[cfg(feature = "tokio")]
+    {
+        let start = tokio::time::Instant::now() + period;
+        let interval = tokio::time::interval_at(start, period);
+
+        futures_util::stream::unfold(interval, |mut interval| async move {
+            interval.tick().await;
+            Some(((), interval))
+        })
+    }
+
+    #[cfg(not(feature = "tokio"))]
+    {
+        use futures_util::stream::StreamExt;
+        async_io::Timer::interval(period).map(drop)
+    }
+}
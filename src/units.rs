@@ -0,0 +1,232 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, Neg, Sub};
+use std::time::Duration;
+use zbus::zvariant::{self, OwnedValue};
+
+/// An amount of energy, in watt-hours.
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct WattHours(pub f64);
+
+impl WattHours {
+    pub fn from_joules(joules: f64) -> Self {
+        WattHours(joules / 3600.0)
+    }
+
+    pub fn as_joules(self) -> f64 {
+        self.0 * 3600.0
+    }
+
+    /// How long this energy lasts at `rate`, or `None` if the rate is not positive.
+    pub fn time_at(self, rate: Watts) -> Option<Duration> {
+        if rate.0 > 0.0 && self.0 >= 0.0 {
+            Some(Duration::from_secs_f64(self.0 / rate.0 * 3600.0))
+        } else {
+            None
+        }
+    }
+}
+
+impl Add for WattHours {
+    type Output = WattHours;
+
+    fn add(self, other: WattHours) -> WattHours {
+        WattHours(self.0 + other.0)
+    }
+}
+
+impl Sub for WattHours {
+    type Output = WattHours;
+
+    fn sub(self, other: WattHours) -> WattHours {
+        WattHours(self.0 - other.0)
+    }
+}
+
+impl Sum for WattHours {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        WattHours(iter.map(|energy| energy.0).sum())
+    }
+}
+
+impl fmt::Display for WattHours {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} Wh", self.0)
+    }
+}
+
+impl TryFrom<OwnedValue> for WattHours {
+    type Error = zvariant::Error;
+
+    fn try_from(value: OwnedValue) -> Result<Self, Self::Error> {
+        f64::try_from(value).map(WattHours)
+    }
+}
+
+/// A rate of energy, in watts.
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Watts(pub f64);
+
+impl Watts {
+    /// The energy transferred at this rate over `duration`.
+    pub fn over(self, duration: Duration) -> WattHours {
+        WattHours(self.0 * duration.as_secs_f64() / 3600.0)
+    }
+}
+
+impl Add for Watts {
+    type Output = Watts;
+
+    fn add(self, other: Watts) -> Watts {
+        Watts(self.0 + other.0)
+    }
+}
+
+impl Neg for Watts {
+    type Output = Watts;
+
+    fn neg(self) -> Watts {
+        Watts(-self.0)
+    }
+}
+
+impl Sum for Watts {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        Watts(iter.map(|rate| rate.0).sum())
+    }
+}
+
+impl fmt::Display for Watts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} W", self.0)
+    }
+}
+
+impl TryFrom<OwnedValue> for Watts {
+    type Error = zvariant::Error;
+
+    fn try_from(value: OwnedValue) -> Result<Self, Self::Error> {
+        f64::try_from(value).map(Watts)
+    }
+}
+
+/// An electric potential, in volts.
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Volts(pub f64);
+
+impl fmt::Display for Volts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} V", self.0)
+    }
+}
+
+impl TryFrom<OwnedValue> for Volts {
+    type Error = zvariant::Error;
+
+    fn try_from(value: OwnedValue) -> Result<Self, Self::Error> {
+        f64::try_from(value).map(Volts)
+    }
+}
+
+/// A temperature, in degrees Celsius.
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Celsius(pub f64);
+
+impl Celsius {
+    pub fn as_fahrenheit(self) -> f64 {
+        self.0 * 9.0 / 5.0 + 32.0
+    }
+
+    pub fn as_kelvin(self) -> f64 {
+        self.0 + 273.15
+    }
+}
+
+impl fmt::Display for Celsius {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} °C", self.0)
+    }
+}
+
+impl TryFrom<OwnedValue> for Celsius {
+    type Error = zvariant::Error;
+
+    fn try_from(value: OwnedValue) -> Result<Self, Self::Error> {
+        f64::try_from(value).map(Celsius)
+    }
+}
+
+/// A charge level, in percent, which is always between 0 and 100.
+///
+/// ```rust
+/// use upower_dbus::Percentage;
+///
+/// let percentage = Percentage::new(83.6);
+/// assert_eq!(percentage.as_u8_rounded(), 84);
+/// assert_eq!(format!("{:.0}", percentage), "84%");
+/// assert_eq!(Percentage::new(104.0).get(), 100.0);
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "f64", into = "f64"))]
+pub struct Percentage(f64);
+
+impl Percentage {
+    /// Clamps `value` to the range of 0 to 100, where NaN becomes 0.
+    pub fn new(value: f64) -> Self {
+        if value.is_nan() {
+            Percentage(0.0)
+        } else {
+            Percentage(value.clamp(0.0, 100.0))
+        }
+    }
+
+    pub fn get(self) -> f64 {
+        self.0
+    }
+
+    /// The percentage rounded to the nearest whole number.
+    pub fn as_u8_rounded(self) -> u8 {
+        self.0.round() as u8
+    }
+}
+
+impl From<f64> for Percentage {
+    fn from(value: f64) -> Self {
+        Percentage::new(value)
+    }
+}
+
+impl From<Percentage> for f64 {
+    fn from(percentage: Percentage) -> Self {
+        percentage.0
+    }
+}
+
+/// Formats the value followed by `%`, honoring the precision of the format string.
+impl fmt::Display for Percentage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)?;
+        f.write_str("%")
+    }
+}
+
+impl TryFrom<OwnedValue> for Percentage {
+    type Error = zvariant::Error;
+
+    fn try_from(value: OwnedValue) -> Result<Self, Self::Error> {
+        f64::try_from(value).map(Percentage::new)
+    }
+}
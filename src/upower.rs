@@ -1,18 +1,116 @@
 // Copyright 2021 System76 <info@system76.com>
 // SPDX-License-Identifier: MPL-2.0
 
-use zbus::dbus_proxy;
+use futures_util::future;
+use futures_util::stream::{self, Stream, StreamExt};
+use std::collections::{BTreeSet, HashMap};
+use std::convert::{TryFrom, TryInto};
+use std::time::Duration;
+use zbus::fdo::{DBusProxy, PropertiesProxy};
+use zbus::names::BusName;
+use zbus::zvariant::{self, ObjectPath, OwnedObjectPath, OwnedValue, Type, Value};
+use zbus::{dbus_proxy, CacheProperties, MatchRule, MessageStream, MessageType};
 
-use crate::device::{DeviceProxy, DeviceProxyBlocking};
+use crate::aggregate::{device_label, native_path_order, BatteryAggregate};
+use crate::blocking::BlockingIter;
+use crate::cache::DeviceCache;
+use crate::change::{AllDeviceChangesStream, DeviceChange};
+use crate::connection::{system_connection, system_connection_blocking};
+use crate::debounce::{debounce, DebouncedOnBatteryStream};
+use crate::device::{fetch_property, fetch_snapshot, DeviceProxy, DeviceProxyBlocking, DeviceType};
+use crate::error::{Error, Result};
+use crate::hotplug::{DeviceEvent, DeviceStream};
+use crate::id::DeviceId;
+use crate::level::WarningLevelStream;
+use crate::monitor::{MonitorOptions, PowerEvent, PowerEventStream};
+use crate::names::{DEVICES_PATH, DEVICE_INTERFACE, DISPLAY_DEVICE_PATH};
+use crate::polling::PollingMonitor;
+use crate::properties::{changed_properties, property_pairs, PropertiesChangedStream};
+use crate::shutdown::{self, ImminentPowerActionStream};
+use crate::snapshot::DeviceSnapshot;
+use crate::status::{PowerStatus, PowerStatusReceiver};
+use crate::version::UPowerVersion;
+
+/// The action the daemon takes when the battery reaches the action warning level.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Type)]
+#[zvariant(signature = "s")]
+pub enum CriticalAction {
+    PowerOff,
+    Hibernate,
+    HybridSleep,
+    Unknown,
+}
+
+impl CriticalAction {
+    /// The name of the action, as used by the daemon.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CriticalAction::PowerOff => "PowerOff",
+            CriticalAction::Hibernate => "Hibernate",
+            CriticalAction::HybridSleep => "HybridSleep",
+            CriticalAction::Unknown => "Unknown",
+        }
+    }
+}
+
+impl From<&str> for CriticalAction {
+    fn from(action: &str) -> Self {
+        match action {
+            "PowerOff" => CriticalAction::PowerOff,
+            "Hibernate" => CriticalAction::Hibernate,
+            "HybridSleep" => CriticalAction::HybridSleep,
+            _ => CriticalAction::Unknown,
+        }
+    }
+}
+
+impl TryFrom<OwnedValue> for CriticalAction {
+    type Error = zvariant::Error;
+
+    fn try_from(value: OwnedValue) -> Result<Self, Self::Error> {
+        <&str>::try_from(&value).map(CriticalAction::from)
+    }
+}
+
+impl<'a> TryFrom<Value<'a>> for CriticalAction {
+    type Error = zvariant::Error;
+
+    fn try_from(value: Value<'a>) -> Result<Self, Self::Error> {
+        <&str>::try_from(&value).map(CriticalAction::from)
+    }
+}
+
+impl From<CriticalAction> for Value<'_> {
+    fn from(action: CriticalAction) -> Self {
+        Value::from(action.as_str())
+    }
+}
+
+impl From<CriticalAction> for OwnedValue {
+    fn from(action: CriticalAction) -> OwnedValue {
+        OwnedValue::from(Value::from(action))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CriticalAction {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|action| CriticalAction::from(action.as_str()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for CriticalAction {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
 
 #[dbus_proxy(interface = "org.freedesktop.UPower", assume_defaults = true)]
 trait UPower {
     /// EnumerateDevices method
     fn enumerate_devices(&self) -> zbus::Result<Vec<zbus::zvariant::OwnedObjectPath>>;
 
-    /// GetCriticalAction method
-    fn get_critical_action(&self) -> zbus::Result<String>;
-
     /// GetDisplayDevice method
     #[dbus_proxy(object = "Device")]
     fn get_display_device(&self);
@@ -30,14 +128,890 @@ trait UPower {
     fn daemon_version(&self) -> zbus::Result<String>;
 
     /// LidIsClosed property
+    ///
+    /// Changes may be watched with `receive_lid_is_closed_changed`.
     #[dbus_proxy(property)]
     fn lid_is_closed(&self) -> zbus::Result<bool>;
 
     /// LidIsPresent property
+    ///
+    /// If `false`, the system has no lid and `lid_is_closed` is meaningless.
     #[dbus_proxy(property)]
     fn lid_is_present(&self) -> zbus::Result<bool>;
 
     /// OnBattery property
+    ///
+    /// Changes may be watched with `receive_on_battery_changed`, which yields a stream on
+    /// `UPowerProxy` and an iterator on `UPowerProxyBlocking`.
     #[dbus_proxy(property)]
     fn on_battery(&self) -> zbus::Result<bool>;
 }
+
+impl UPowerProxy<'static> {
+    /// Creates a proxy on the shared system bus connection from [`system_connection`].
+    ///
+    /// Use `UPowerProxy::new` to supply a connection of your own.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug"))]
+    pub async fn system() -> Result<Self> {
+        Ok(Self::new(&system_connection().await?).await?)
+    }
+}
+
+impl<'a> UPowerProxy<'a> {
+    /// Creates a proxy which reads properties from the daemon on every call, rather than
+    /// caching them.
+    ///
+    /// Values are always fresh, but `receive_*_changed` streams are unavailable.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(connection))
+    )]
+    pub async fn new_uncached(connection: &zbus::Connection) -> Result<Self> {
+        let proxy = Self::builder(connection)
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+        Ok(proxy)
+    }
+
+    /// GetCriticalAction method
+    pub async fn get_critical_action(&self) -> zbus::Result<CriticalAction> {
+        let action: String = self.inner().call("GetCriticalAction", &()).await?;
+        Ok(CriticalAction::from(action.as_str()))
+    }
+
+    /// A builder for the daemon served by `destination` rather than `org.freedesktop.UPower`.
+    ///
+    /// Useful against a private bus or a renamed service, such as in tests and sandboxes.
+    /// Devices created through this proxy use the same destination.
+    pub fn builder_for<D>(
+        connection: &zbus::Connection,
+        destination: D,
+    ) -> Result<zbus::ProxyBuilder<'a, Self>>
+    where
+        D: TryInto<BusName<'a>>,
+        D::Error: Into<zbus::Error>,
+    {
+        Ok(Self::builder(connection).destination(destination)?)
+    }
+
+    /// Creates a `DeviceProxy` for the device at `path`, on the same connection and destination.
+    ///
+    /// Accepts the object paths returned by `enumerate_devices` and carried by the
+    /// `DeviceAdded` and `DeviceRemoved` signals.
+    pub async fn device<P>(&self, path: P) -> Result<DeviceProxy<'static>>
+    where
+        P: Into<OwnedObjectPath>,
+    {
+        let path = path.into();
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(path = %path.as_str(), "creating device proxy");
+
+        let device = DeviceProxy::builder(self.connection())
+            .destination(self.destination().to_owned())?
+            .path(path)?
+            .build()
+            .await?;
+        Ok(device)
+    }
+
+    /// Enumerates all devices, creating their proxies concurrently.
+    pub async fn devices(&self) -> Result<Vec<DeviceProxy<'static>>> {
+        let paths = self.enumerate_devices().await?;
+        future::try_join_all(paths.into_iter().map(|path| self.device(path))).await
+    }
+
+    /// Enumerates all devices and fetches their properties, with the `GetAll` calls issued
+    /// concurrently so that the whole list takes a single round-trip.
+    ///
+    /// No proxies are created, so no property caches are set up for the devices.
+    pub async fn snapshot_all_devices(&self) -> Result<Vec<(DeviceId, DeviceSnapshot)>> {
+        let paths = self.enumerate_devices().await?;
+
+        future::try_join_all(paths.into_iter().map(|path| async move {
+            let snapshot = fetch_snapshot(self.connection(), self.destination(), &path).await?;
+            Result::<_>::Ok((DeviceId::from(path), snapshot))
+        }))
+        .await
+    }
+
+    /// Fetches the properties of the daemon and its display device, and the kind of every
+    /// device, concurrently, and summarizes them.
+    ///
+    /// Only the `Type` and `PowerSupply` properties of each device are read, to count the
+    /// batteries.
+    pub async fn power_status(&self) -> Result<PowerStatus> {
+        let (status, _) = self.power_status_and_batteries().await?;
+        Ok(status)
+    }
+
+    /// Like `power_status`, also returning the batteries counted.
+    pub(crate) async fn power_status_and_batteries(
+        &self,
+    ) -> Result<(PowerStatus, BTreeSet<DeviceId>)> {
+        let properties = async {
+            let properties = PropertiesProxy::builder(self.connection())
+                .destination(self.destination().to_owned())?
+                .path(self.path().to_owned())?
+                .cache_properties(CacheProperties::No)
+                .build()
+                .await?;
+            Result::<_>::Ok(properties.get_all(self.interface().to_owned()).await?)
+        };
+
+        let display_path = ObjectPath::from_static_str_unchecked(DISPLAY_DEVICE_PATH);
+        let display = fetch_snapshot(self.connection(), self.destination(), &display_path);
+
+        let (mut properties, display, batteries) =
+            future::try_join3(properties, display, self.battery_ids()).await?;
+
+        let mut take = |name: &'static str| {
+            let value = properties
+                .remove(name)
+                .ok_or(Error::MissingProperty(name))?;
+            Result::<_>::Ok(bool::try_from(value)?)
+        };
+
+        let mut status = PowerStatus {
+            on_battery: take("OnBattery")?,
+            lid_is_closed: take("LidIsClosed")?,
+            percentage: display.percentage,
+            state: display.state,
+            time_to_empty: None,
+            time_to_full: None,
+            batteries: batteries.len(),
+        };
+
+        status.apply_display(&display);
+        Ok((status, batteries))
+    }
+
+    /// The batteries which power the system, as counted by `power_status`.
+    ///
+    /// Devices removed while they are read are left out.
+    async fn battery_ids(&self) -> Result<BTreeSet<DeviceId>> {
+        let paths = self.enumerate_devices().await?;
+
+        let batteries = future::try_join_all(paths.into_iter().map(|path| async move {
+            let type_ = fetch_property(self.connection(), self.destination(), &path, "Type");
+            let power_supply =
+                fetch_property(self.connection(), self.destination(), &path, "PowerSupply");
+
+            let is_battery = match future::try_join(type_, power_supply).await {
+                Ok((type_, power_supply)) => {
+                    DeviceType::try_from(type_)? == DeviceType::Battery
+                        && bool::try_from(power_supply)?
+                }
+                Err(Error::NoSuchDevice) => false,
+                Err(why) => return Err(why),
+            };
+
+            Ok(if is_battery {
+                Some(DeviceId::from(path))
+            } else {
+                None
+            })
+        }))
+        .await?;
+
+        Ok(batteries.into_iter().flatten().collect())
+    }
+
+    /// Keeps the result of `power_status` current in the background, for consumers which
+    /// prefer reading the latest value over handling a stream of events.
+    ///
+    /// Reading the receiver only takes a lock, so a status bar may do so on every frame.
+    pub async fn watch_power_status(&self) -> Result<PowerStatusReceiver> {
+        let upower = UPowerProxy::builder(self.connection())
+            .destination(self.destination().to_owned())?
+            .path(self.path().to_owned())?
+            .build()
+            .await?;
+
+        PowerStatusReceiver::spawn(upower).await
+    }
+
+    /// Keeps a snapshot of every device current in the background, for consumers which must
+    /// read device state without waiting on the bus.
+    pub async fn device_cache(&self) -> Result<DeviceCache> {
+        let upower = UPowerProxy::builder(self.connection())
+            .destination(self.destination().to_owned())?
+            .path(self.path().to_owned())?
+            .build()
+            .await?;
+
+        DeviceCache::spawn(upower).await
+    }
+
+    /// Watches for the system being about to power off, hibernate or suspend, so that an
+    /// application can save its state first.
+    ///
+    /// An action is emitted when the display device reaches `WarningLevel::Action`, after
+    /// which the daemon takes the action returned by `get_critical_action`, and when the lid
+    /// is closed while logind is configured to suspend or power off on it. Each is emitted
+    /// again only once its condition cleared in between, such as by plugging in AC power or
+    /// opening the lid.
+    ///
+    /// The critical action and logind's `HandleLidSwitch` settings are read each time, so
+    /// that a changed configuration is honored. The lid is not watched if logind is not
+    /// running on the daemon's bus.
+    pub async fn shutdown_guard(&self) -> Result<ImminentPowerActionStream<'static>> {
+        let upower = UPowerProxy::builder(self.connection())
+            .destination(self.destination().to_owned())?
+            .path(self.path().to_owned())?
+            .build()
+            .await?;
+
+        shutdown::watch(upower).await
+    }
+
+    /// Enumerates the devices which are batteries.
+    ///
+    /// If `power_supply_only` is `true`, batteries of peripherals are excluded, leaving those
+    /// which power the system.
+    pub async fn batteries(&self, power_supply_only: bool) -> Result<Vec<DeviceProxy<'static>>> {
+        let devices = self.devices().await?;
+
+        let filters = future::try_join_all(devices.iter().map(|device| async move {
+            let is_battery = device.type_().await? == DeviceType::Battery;
+            Result::<_>::Ok(is_battery && (!power_supply_only || device.power_supply().await?))
+        }))
+        .await?;
+
+        Ok(devices
+            .into_iter()
+            .zip(filters)
+            .filter_map(|(device, keep)| if keep { Some(device) } else { None })
+            .collect())
+    }
+
+    /// Enumerates the batteries which power the system, ordered by `NativePath` as by
+    /// `native_path_order`, so that `BAT0` always comes before `BAT1`.
+    pub async fn system_batteries(&self) -> Result<Vec<DeviceProxy<'static>>> {
+        let batteries = self.batteries(true).await?;
+        let native_paths =
+            future::try_join_all(batteries.iter().map(|battery| battery.native_path())).await?;
+
+        let mut batteries: Vec<_> = native_paths.into_iter().zip(batteries).collect();
+        batteries.sort_by(|(a, _), (b, _)| native_path_order(a, b));
+        Ok(batteries.into_iter().map(|(_, battery)| battery).collect())
+    }
+
+    /// Combines the system batteries the way the display device does, while keeping the
+    /// readings of each battery.
+    pub async fn aggregate_batteries(&self) -> Result<BatteryAggregate> {
+        let batteries = self.batteries(true).await?;
+        let snapshots =
+            future::try_join_all(batteries.iter().map(|battery| battery.snapshot())).await?;
+        Ok(BatteryAggregate::new(snapshots))
+    }
+
+    /// Finds the device whose `NativePath` is `native_path`, such as `BAT0` or
+    /// `/sys/class/power_supply/BAT0`.
+    ///
+    /// An empty `native_path` matches no device.
+    pub async fn device_by_native_path(
+        &self,
+        native_path: &str,
+    ) -> Result<Option<DeviceProxy<'static>>> {
+        if native_path.is_empty() {
+            return Ok(None);
+        }
+
+        let devices = self.devices().await?;
+        let paths = future::try_join_all(devices.iter().map(|device| device.native_path())).await?;
+
+        Ok(devices
+            .into_iter()
+            .zip(paths)
+            .find(|(_, path)| native_path_matches(path, native_path))
+            .map(|(device, _)| device))
+    }
+
+    /// Finds the first device whose snapshot satisfies `predicate`, such as one matching a
+    /// vendor and model stored in a configuration file.
+    ///
+    /// Every device is snapshotted, as by `snapshot_all_devices`, before `predicate` is called.
+    pub async fn find_device<F>(&self, mut predicate: F) -> Result<Option<DeviceProxy<'static>>>
+    where
+        F: FnMut(&DeviceSnapshot) -> bool,
+    {
+        let found = self
+            .snapshot_all_devices()
+            .await?
+            .into_iter()
+            .find(|(_, snapshot)| predicate(snapshot));
+
+        match found {
+            Some((id, _)) => Ok(Some(self.device(id).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Finds the device with the serial number `serial`. Devices without one never match.
+    pub async fn device_by_serial(&self, serial: &str) -> Result<Option<DeviceProxy<'static>>> {
+        if serial.is_empty() {
+            return Ok(None);
+        }
+
+        self.find_device(|device| device.serial == serial).await
+    }
+
+    /// Returns the composite display device, on the same connection and destination.
+    ///
+    /// Unlike `get_display_device`, the proxy returned is not tied to the lifetime of `self`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self)))]
+    pub async fn display_device(&self) -> Result<DeviceProxy<'static>> {
+        let path: OwnedObjectPath = self.inner().call("GetDisplayDevice", &()).await?;
+        self.device(path).await
+    }
+
+    /// Watches the warning level of the display device, which is what a battery warning
+    /// should be based on.
+    pub async fn watch_display_warning_level(&self) -> Result<WarningLevelStream<'static>> {
+        self.display_device().await?.watch_warning_level().await
+    }
+
+    /// The parsed version of the daemon.
+    pub async fn version(&self) -> Result<UPowerVersion> {
+        Ok(self.daemon_version().await?.parse()?)
+    }
+
+    /// Watches for devices being connected and disconnected.
+    ///
+    /// Every device which is already present is emitted as `DeviceEvent::Added` before any
+    /// hot-plug events. A device connected while the stream is being set up may be reported
+    /// twice.
+    pub async fn watch_devices(&self) -> Result<DeviceStream<'a>> {
+        let added = self.receive_device_added().await?;
+        let removed = self.receive_device_removed().await?;
+        let present = self.enumerate_devices().await?;
+
+        let upower = self.clone();
+        let present = stream::iter(present).then(move |path| {
+            let upower = upower.clone();
+            async move { upower.device(path).await.map(DeviceEvent::Added) }
+        });
+
+        let added = self
+            .added_devices(added)
+            .map(|device| device.map(DeviceEvent::Added));
+        let removed = removed_paths(removed).map(|path| path.map(DeviceEvent::Removed));
+
+        Ok(DeviceStream {
+            inner: Box::pin(present.chain(stream::select(added, removed))),
+        })
+    }
+
+    /// Watches the properties of the daemon through a single subscription, yielding the name
+    /// and new value of each property as it changes, for dispatching by hand.
+    pub async fn receive_properties_changed(&self) -> Result<PropertiesChangedStream<'a>> {
+        let changes = changed_properties(
+            self.connection(),
+            self.destination(),
+            self.path(),
+            self.interface(),
+        )
+        .await?;
+
+        Ok(property_pairs(changes))
+    }
+
+    /// Watches the properties of every device through a single stream, including devices
+    /// connected later, with each item holding the values which changed in one
+    /// `PropertiesChanged` signal.
+    ///
+    /// A single match rule covers every device, so no subscription is added per device. The
+    /// display device is left out, as it merely combines the batteries.
+    pub async fn receive_all_device_changes(&self) -> Result<AllDeviceChangesStream<'a>> {
+        let rule = MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .sender(self.destination().to_owned())?
+            .interface("org.freedesktop.DBus.Properties")?
+            .member("PropertiesChanged")?
+            .path_namespace(DEVICES_PATH)?
+            .arg(0, DEVICE_INTERFACE)?
+            .build();
+
+        let signals = MessageStream::for_match_rule(rule, self.connection(), None).await?;
+
+        let changes = signals.filter_map(|message| {
+            future::ready(
+                (|| -> Result<Option<_>> {
+                    let message = message?;
+                    let path = match message.path() {
+                        Some(path) if path.as_str() != DISPLAY_DEVICE_PATH => path.to_owned(),
+                        _ => return Ok(None),
+                    };
+
+                    let (_, properties, _): (String, HashMap<String, OwnedValue>, Vec<String>) =
+                        message.body()?;
+
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(
+                        path = %path.as_str(),
+                        properties = properties.len(),
+                        "PropertiesChanged"
+                    );
+
+                    match DeviceChange::try_from(properties)? {
+                        change if change.is_empty() => Ok(None),
+                        change => Ok(Some((DeviceId::from(path), change))),
+                    }
+                })()
+                .transpose(),
+            )
+        });
+
+        Ok(AllDeviceChangesStream {
+            inner: Box::pin(changes),
+        })
+    }
+
+    /// Like `receive_on_battery_changed`, but only emits once the value has been stable for
+    /// `delay`, so that a bouncing connector produces a single change.
+    ///
+    /// A switch which reverts within `delay` is not reported at all.
+    pub async fn receive_on_battery_changed_debounced(
+        &self,
+        delay: Duration,
+    ) -> Result<DebouncedOnBatteryStream<'a>> {
+        let changes = self
+            .receive_on_battery_changed()
+            .await
+            .then(|changed| async move { Ok(changed.get().await?) });
+
+        let initial = self.on_battery().await?;
+
+        Ok(DebouncedOnBatteryStream {
+            inner: Box::pin(debounce(changes, delay, Some(initial))),
+        })
+    }
+
+    /// Watches the power state of the system through a single stream of events.
+    pub async fn monitor(&self) -> Result<PowerEventStream<'a>> {
+        self.monitor_with(MonitorOptions::default()).await
+    }
+
+    /// Like `monitor`, with `options` controlling how a restart of the daemon is handled, and
+    /// whether signals or polling are used.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self)))]
+    pub async fn monitor_with(&self, options: MonitorOptions) -> Result<PowerEventStream<'a>> {
+        if let Some(interval) = options.polls_every() {
+            return PollingMonitor::new(interval)
+                .resync_on_restart(options.resyncs_on_restart())
+                .monitor(self)
+                .await;
+        }
+
+        let display = self.display_device().await?;
+
+        let display_changes = PropertiesProxy::builder(self.connection())
+            .destination(display.destination().to_owned())?
+            .path(display.path().to_owned())?
+            .build()
+            .await?
+            .receive_properties_changed()
+            .await?
+            .then(move |_| {
+                let display = display.clone();
+                async move {
+                    let snapshot = display.snapshot().await?;
+                    Result::<_>::Ok(PowerEvent::DisplayDeviceChanged(Box::new(snapshot)))
+                }
+            });
+
+        let on_battery = self
+            .receive_on_battery_changed()
+            .await
+            .then(|changed| async move { Ok(PowerEvent::OnBatteryChanged(changed.get().await?)) });
+
+        let lid_closed = self
+            .receive_lid_is_closed_changed()
+            .await
+            .then(|changed| async move { Ok(PowerEvent::LidClosed(changed.get().await?)) });
+
+        let added = self
+            .added_devices(self.receive_device_added().await?)
+            .map(|device| device.map(PowerEvent::DeviceAdded));
+
+        let removed = removed_paths(self.receive_device_removed().await?)
+            .map(|path| path.map(PowerEvent::DeviceRemoved));
+
+        let upower = self.clone();
+        let restarted = self
+            .receive_restarts()
+            .await?
+            .then(move |restarted| {
+                let upower = upower.clone();
+                async move {
+                    restarted?;
+                    let mut events = vec![PowerEvent::ServiceRestarted];
+
+                    if options.resyncs_on_restart() {
+                        events.extend(upower.current_state().await?);
+                    }
+
+                    Ok(events)
+                }
+            })
+            .flat_map(|events: Result<Vec<PowerEvent>>| {
+                stream::iter(match events {
+                    Ok(events) => events.into_iter().map(Ok).collect(),
+                    Err(why) => vec![Err(why)],
+                })
+            });
+
+        Ok(PowerEventStream {
+            inner: Box::pin(stream::select_all(vec![
+                display_changes.boxed(),
+                on_battery.boxed(),
+                lid_closed.boxed(),
+                added.boxed(),
+                removed.boxed(),
+                restarted.boxed(),
+            ])),
+        })
+    }
+
+    /// Emits an item whenever a new owner acquires the daemon's bus name.
+    pub(crate) async fn receive_restarts(
+        &self,
+    ) -> Result<impl Stream<Item = Result<()>> + Send + 'a> {
+        let dbus = DBusProxy::new(self.connection()).await?;
+        let changes = dbus
+            .receive_name_owner_changed_with_args(&[(0, self.destination().as_str())])
+            .await?;
+
+        Ok(changes.filter_map(|signal| async move {
+            match signal.args() {
+                Ok(args) if args.new_owner().is_some() => Some(Ok(())),
+                Ok(_) => None,
+                Err(why) => Some(Err(Error::from(why))),
+            }
+        }))
+    }
+
+    /// Reads the current state of the daemon without going through cached properties.
+    async fn current_state(&self) -> Result<Vec<PowerEvent>> {
+        let properties = PropertiesProxy::builder(self.connection())
+            .destination(self.destination().to_owned())?
+            .path(self.path().to_owned())?
+            .build()
+            .await?;
+
+        let interface = self.interface().to_owned();
+        let on_battery = properties.get(interface.clone(), "OnBattery").await?;
+        let lid_is_closed = properties.get(interface, "LidIsClosed").await?;
+        let display = self.display_device().await?.snapshot().await?;
+
+        let mut events = vec![
+            PowerEvent::OnBatteryChanged(bool::try_from(on_battery)?),
+            PowerEvent::LidClosed(bool::try_from(lid_is_closed)?),
+            PowerEvent::DisplayDeviceChanged(Box::new(display)),
+        ];
+
+        events.extend(
+            self.devices()
+                .await?
+                .into_iter()
+                .map(PowerEvent::DeviceAdded),
+        );
+
+        Ok(events)
+    }
+
+    /// Creates a proxy for every device announced by `DeviceAdded` signals.
+    fn added_devices(
+        &self,
+        signals: DeviceAddedStream<'a>,
+    ) -> impl Stream<Item = Result<DeviceProxy<'static>>> + Send + 'a {
+        let upower = self.clone();
+        signals.then(move |signal| {
+            let upower = upower.clone();
+            async move {
+                let path = OwnedObjectPath::from(signal.args()?.device);
+
+                #[cfg(feature = "tracing")]
+                tracing::debug!(path = %path.as_str(), "DeviceAdded");
+
+                upower.device(path).await
+            }
+        })
+    }
+}
+
+/// Whether a device's `NativePath` refers to `query`.
+///
+/// The daemon reports some native paths as a bare sysfs name, and others as a full path, so
+/// the final components are compared when either is a path.
+/// An empty query matches no device, although the line power and display devices report an
+/// empty `NativePath`.
+fn native_path_matches(native_path: &str, query: &str) -> bool {
+    if native_path.is_empty() || query.is_empty() {
+        return false;
+    }
+
+    native_path == query || device_label(native_path) == device_label(query)
+}
+
+/// Extracts the path of every device announced by `DeviceRemoved` signals.
+fn removed_paths(
+    signals: DeviceRemovedStream<'_>,
+) -> impl Stream<Item = Result<OwnedObjectPath>> + Send + '_ {
+    signals.map(|signal| {
+        let path = OwnedObjectPath::from(signal.args()?.device);
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(path = %path.as_str(), "DeviceRemoved");
+
+        Ok(path)
+    })
+}
+
+impl UPowerProxyBlocking<'static> {
+    /// Creates a proxy on the shared system bus connection from
+    /// [`system_connection_blocking`].
+    ///
+    /// Use `UPowerProxyBlocking::new` to supply a connection of your own.
+    pub fn system() -> Result<Self> {
+        Ok(Self::new(&system_connection_blocking()?)?)
+    }
+}
+
+impl<'a> UPowerProxyBlocking<'a> {
+    /// Creates a proxy which reads properties from the daemon on every call, rather than
+    /// caching them.
+    ///
+    /// Values are always fresh, but `receive_*_changed` streams are unavailable.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(connection))
+    )]
+    pub fn new_uncached(connection: &zbus::blocking::Connection) -> Result<Self> {
+        let proxy = Self::builder(connection)
+            .cache_properties(CacheProperties::No)
+            .build()?;
+        Ok(proxy)
+    }
+
+    /// GetCriticalAction method
+    pub fn get_critical_action(&self) -> zbus::Result<CriticalAction> {
+        let action: String = self.inner().call("GetCriticalAction", &())?;
+        Ok(CriticalAction::from(action.as_str()))
+    }
+
+    /// A builder for the daemon served by `destination` rather than `org.freedesktop.UPower`.
+    ///
+    /// Useful against a private bus or a renamed service, such as in tests and sandboxes.
+    /// Devices created through this proxy use the same destination.
+    pub fn builder_for<D>(
+        connection: &zbus::blocking::Connection,
+        destination: D,
+    ) -> Result<zbus::blocking::ProxyBuilder<'a, Self>>
+    where
+        D: TryInto<BusName<'a>>,
+        D::Error: Into<zbus::Error>,
+    {
+        Ok(Self::builder(connection).destination(destination)?)
+    }
+
+    /// Creates a `DeviceProxyBlocking` for the device at `path`, on the same connection and
+    /// destination.
+    ///
+    /// Accepts the object paths returned by `enumerate_devices` and carried by the
+    /// `DeviceAdded` and `DeviceRemoved` signals.
+    pub fn device<P>(&self, path: P) -> Result<DeviceProxyBlocking<'static>>
+    where
+        P: Into<OwnedObjectPath>,
+    {
+        let device = DeviceProxyBlocking::builder(self.connection())
+            .destination(self.destination().to_owned())?
+            .path(path.into())?
+            .build()?;
+        Ok(device)
+    }
+
+    /// Enumerates all devices, creating a proxy for each of them.
+    pub fn devices(&self) -> Result<Vec<DeviceProxyBlocking<'static>>> {
+        let paths = self.enumerate_devices()?;
+        paths.into_iter().map(|path| self.device(path)).collect()
+    }
+
+    /// Enumerates the devices which are batteries.
+    ///
+    /// If `power_supply_only` is `true`, batteries of peripherals are excluded, leaving those
+    /// which power the system.
+    pub fn batteries(&self, power_supply_only: bool) -> Result<Vec<DeviceProxyBlocking<'static>>> {
+        let mut batteries = Vec::new();
+
+        for device in self.devices()? {
+            if device.type_()? == DeviceType::Battery
+                && (!power_supply_only || device.power_supply()?)
+            {
+                batteries.push(device);
+            }
+        }
+
+        Ok(batteries)
+    }
+
+    /// Enumerates the batteries which power the system, ordered by `NativePath` as by
+    /// `native_path_order`, so that `BAT0` always comes before `BAT1`.
+    pub fn system_batteries(&self) -> Result<Vec<DeviceProxyBlocking<'static>>> {
+        let mut batteries = self
+            .batteries(true)?
+            .into_iter()
+            .map(|battery| Ok((battery.native_path()?, battery)))
+            .collect::<Result<Vec<_>>>()?;
+
+        batteries.sort_by(|(a, _), (b, _)| native_path_order(a, b));
+        Ok(batteries.into_iter().map(|(_, battery)| battery).collect())
+    }
+
+    /// Combines the system batteries the way the display device does, while keeping the
+    /// readings of each battery.
+    pub fn aggregate_batteries(&self) -> Result<BatteryAggregate> {
+        let batteries = self.batteries(true)?;
+        let snapshots = batteries
+            .iter()
+            .map(|battery| battery.snapshot())
+            .collect::<Result<Vec<_>>>()?;
+        Ok(BatteryAggregate::new(snapshots))
+    }
+
+    /// Finds the device whose `NativePath` is `native_path`, such as `BAT0` or
+    /// `/sys/class/power_supply/BAT0`.
+    ///
+    /// An empty `native_path` matches no device.
+    pub fn device_by_native_path(
+        &self,
+        native_path: &str,
+    ) -> Result<Option<DeviceProxyBlocking<'static>>> {
+        if native_path.is_empty() {
+            return Ok(None);
+        }
+
+        for device in self.devices()? {
+            if native_path_matches(&device.native_path()?, native_path) {
+                return Ok(Some(device));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Finds the first device whose snapshot satisfies `predicate`, such as one matching a
+    /// vendor and model stored in a configuration file.
+    pub fn find_device<F>(&self, mut predicate: F) -> Result<Option<DeviceProxyBlocking<'static>>>
+    where
+        F: FnMut(&DeviceSnapshot) -> bool,
+    {
+        for device in self.devices()? {
+            if predicate(&device.snapshot()?) {
+                return Ok(Some(device));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Finds the device with the serial number `serial`. Devices without one never match.
+    pub fn device_by_serial(&self, serial: &str) -> Result<Option<DeviceProxyBlocking<'static>>> {
+        if serial.is_empty() {
+            return Ok(None);
+        }
+
+        self.find_device(|device| device.serial == serial)
+    }
+
+    /// Returns the composite display device, on the same connection and destination.
+    ///
+    /// Unlike `get_display_device`, the proxy returned is not tied to the lifetime of `self`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self)))]
+    pub fn display_device(&self) -> Result<DeviceProxyBlocking<'static>> {
+        let path: OwnedObjectPath = self.inner().call("GetDisplayDevice", &())?;
+        self.device(path)
+    }
+
+    /// The parsed version of the daemon.
+    pub fn version(&self) -> Result<UPowerVersion> {
+        Ok(self.daemon_version()?.parse()?)
+    }
+
+    /// Like `UPowerProxy::watch_display_warning_level`, blocking on each level.
+    pub fn watch_display_warning_level(&self) -> Result<BlockingIter<WarningLevelStream<'static>>> {
+        zbus::block_on(self.asynchronous().watch_display_warning_level()).map(BlockingIter::new)
+    }
+
+    /// Like `UPowerProxy::watch_devices`, blocking on each event.
+    pub fn watch_devices(&self) -> Result<BlockingIter<DeviceStream<'a>>> {
+        zbus::block_on(self.asynchronous().watch_devices()).map(BlockingIter::new)
+    }
+
+    /// Like `UPowerProxy::receive_properties_changed`, blocking on each property.
+    pub fn receive_properties_changed(&self) -> Result<BlockingIter<PropertiesChangedStream<'a>>> {
+        zbus::block_on(self.asynchronous().receive_properties_changed()).map(BlockingIter::new)
+    }
+
+    /// Like `UPowerProxy::receive_all_device_changes`, blocking on each change.
+    pub fn receive_all_device_changes(&self) -> Result<BlockingIter<AllDeviceChangesStream<'a>>> {
+        zbus::block_on(self.asynchronous().receive_all_device_changes()).map(BlockingIter::new)
+    }
+
+    /// Like `UPowerProxy::receive_on_battery_changed_debounced`, blocking on each change.
+    pub fn receive_on_battery_changed_debounced(
+        &self,
+        delay: Duration,
+    ) -> Result<BlockingIter<DebouncedOnBatteryStream<'a>>> {
+        zbus::block_on(
+            self.asynchronous()
+                .receive_on_battery_changed_debounced(delay),
+        )
+        .map(BlockingIter::new)
+    }
+
+    /// Like `UPowerProxy::shutdown_guard`, blocking on each action.
+    pub fn shutdown_guard(&self) -> Result<BlockingIter<ImminentPowerActionStream<'static>>> {
+        zbus::block_on(self.asynchronous().shutdown_guard()).map(BlockingIter::new)
+    }
+
+    /// Like `UPowerProxy::monitor`, blocking on each event.
+    pub fn monitor(&self) -> Result<BlockingIter<PowerEventStream<'a>>> {
+        self.monitor_with(MonitorOptions::default())
+    }
+
+    /// Like `UPowerProxy::monitor_with`, blocking on each event.
+    pub fn monitor_with(
+        &self,
+        options: MonitorOptions,
+    ) -> Result<BlockingIter<PowerEventStream<'a>>> {
+        zbus::block_on(self.asynchronous().monitor_with(options)).map(BlockingIter::new)
+    }
+
+    /// The async proxy sharing the connection and cache of this one.
+    fn asynchronous(&self) -> UPowerProxy<'a> {
+        UPowerProxy::from(self.inner().inner().clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::native_path_matches;
+
+    #[test]
+    fn native_paths_match_by_final_component() {
+        assert!(native_path_matches("BAT0", "BAT0"));
+        assert!(native_path_matches("/sys/class/power_supply/BAT0", "BAT0"));
+        assert!(native_path_matches("BAT0", "/sys/class/power_supply/BAT0"));
+        assert!(!native_path_matches("BAT0", "BAT1"));
+    }
+
+    #[test]
+    fn empty_native_paths_match_nothing() {
+        assert!(!native_path_matches("", ""));
+        assert!(!native_path_matches("BAT0", ""));
+        assert!(!native_path_matches("", "BAT0"));
+    }
+}
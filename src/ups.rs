@@ -0,0 +1,155 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::stream::Stream;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::device::BatteryState;
+use crate::error::Result;
+use crate::snapshot::DeviceSnapshot;
+use crate::units::Percentage;
+
+/// The properties of an uninterruptible power supply which matter for monitoring it.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UpsStatus {
+    pub percentage: Percentage,
+    pub state: BatteryState,
+    /// The estimated runtime left on battery power, if known.
+    pub time_to_empty: Option<Duration>,
+    pub vendor: String,
+    pub model: String,
+}
+
+impl UpsStatus {
+    /// Whether the UPS is powering its load from its battery.
+    pub fn on_battery(&self) -> bool {
+        self.state == BatteryState::Discharging
+    }
+}
+
+impl From<&DeviceSnapshot> for UpsStatus {
+    fn from(device: &DeviceSnapshot) -> Self {
+        UpsStatus {
+            percentage: device.percentage,
+            state: device.state,
+            time_to_empty: device.time_to_empty_duration(),
+            vendor: device.vendor.clone(),
+            model: device.model.clone(),
+        }
+    }
+}
+
+/// A change in the power of a UPS, reported by a `UpsWatcher`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UpsEvent {
+    /// The UPS lost line power and is running from its battery.
+    OnBatteryPower(UpsStatus),
+    /// Line power was restored after the UPS ran from its battery.
+    OnLinePower(UpsStatus),
+    /// The runtime left on battery fell to or below the watcher's threshold.
+    LowRuntime(UpsStatus),
+}
+
+/// Reports a UPS switching between line and battery power, and its runtime running low.
+///
+/// `LowRuntime` is reported once for each time the UPS runs from its battery.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use upower_dbus::{BatteryState, DeviceSnapshot, DeviceType, UpsEvent, UpsWatcher};
+///
+/// let mut watcher = UpsWatcher::new(Duration::from_secs(300));
+/// let mut ups = DeviceSnapshot::new(DeviceType::Ups);
+///
+/// ups.state = BatteryState::FullyCharged;
+/// assert!(watcher.update(&ups).is_empty());
+///
+/// ups.state = BatteryState::Discharging;
+/// ups.time_to_empty = 1200;
+/// assert!(matches!(watcher.update(&ups)[..], [UpsEvent::OnBatteryPower(_)]));
+///
+/// ups.time_to_empty = 240;
+/// assert!(matches!(watcher.update(&ups)[..], [UpsEvent::LowRuntime(_)]));
+/// assert!(watcher.update(&ups).is_empty());
+/// ```
+#[derive(Clone, Debug)]
+pub struct UpsWatcher {
+    low_runtime: Duration,
+    on_battery: bool,
+    low_reported: bool,
+}
+
+impl Default for UpsWatcher {
+    /// Reports a low runtime at five minutes.
+    fn default() -> Self {
+        Self::new(Duration::from_secs(5 * 60))
+    }
+}
+
+impl UpsWatcher {
+    /// Creates a watcher which reports `LowRuntime` once the runtime left is at most
+    /// `low_runtime`.
+    pub fn new(low_runtime: Duration) -> Self {
+        Self {
+            low_runtime,
+            on_battery: false,
+            low_reported: false,
+        }
+    }
+
+    /// The runtime at which `LowRuntime` is reported.
+    pub fn low_runtime(&self) -> Duration {
+        self.low_runtime
+    }
+
+    /// Adds a reading of the UPS, returning the events it raises.
+    pub fn update(&mut self, device: &DeviceSnapshot) -> Vec<UpsEvent> {
+        let status = UpsStatus::from(device);
+        let mut events = Vec::new();
+
+        if status.on_battery() != self.on_battery {
+            self.on_battery = status.on_battery();
+            self.low_reported = false;
+
+            events.push(if self.on_battery {
+                UpsEvent::OnBatteryPower(status.clone())
+            } else {
+                UpsEvent::OnLinePower(status.clone())
+            });
+        }
+
+        let low = status
+            .time_to_empty
+            .is_some_and(|runtime| runtime <= self.low_runtime);
+
+        if self.on_battery && low && !self.low_reported {
+            self.low_reported = true;
+            events.push(UpsEvent::LowRuntime(status));
+        }
+
+        events
+    }
+}
+
+/// A stream of `UpsEvent`s, created by `DeviceProxy::receive_ups_events`.
+pub struct UpsEventStream<'a> {
+    pub(crate) inner: Pin<Box<dyn Stream<Item = Result<UpsEvent>> + Send + 'a>>,
+}
+
+impl fmt::Debug for UpsEventStream<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UpsEventStream").finish_non_exhaustive()
+    }
+}
+
+impl Stream for UpsEventStream<'_> {
+    type Item = Result<UpsEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
@@ -0,0 +1,90 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::Error;
+
+/// The version of the UPower daemon, as reported by the `DaemonVersion` property.
+///
+/// ```
+/// use upower_dbus::UPowerVersion;
+///
+/// let version: UPowerVersion = "1.90.2".parse().unwrap();
+/// assert!(version >= UPowerVersion::CHARGE_THRESHOLDS);
+/// assert!(!version.is_at_least(1, 91, 0));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UPowerVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub micro: u32,
+}
+
+impl UPowerVersion {
+    /// The first release with charge threshold support.
+    pub const CHARGE_THRESHOLDS: UPowerVersion = UPowerVersion::new(1, 90, 0);
+
+    /// Creates a version from its components.
+    pub const fn new(major: u32, minor: u32, micro: u32) -> Self {
+        Self {
+            major,
+            minor,
+            micro,
+        }
+    }
+
+    /// Whether this version is the same as or newer than `major.minor.micro`.
+    pub fn is_at_least(self, major: u32, minor: u32, micro: u32) -> bool {
+        self >= UPowerVersion::new(major, minor, micro)
+    }
+}
+
+impl fmt::Display for UPowerVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.micro)
+    }
+}
+
+/// The error returned when parsing an invalid `UPowerVersion`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseVersionError(String);
+
+impl fmt::Display for ParseVersionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid UPower version: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseVersionError {}
+
+impl From<ParseVersionError> for Error {
+    fn from(why: ParseVersionError) -> Self {
+        Error::InvalidVersion(why.0)
+    }
+}
+
+impl FromStr for UPowerVersion {
+    type Err = ParseVersionError;
+
+    /// Parses versions such as `1.90.2`. A missing micro version is treated as 0.
+    fn from_str(version: &str) -> Result<Self, Self::Err> {
+        let error = || ParseVersionError(version.to_owned());
+        let mut parts = version.trim().split('.');
+
+        let mut next = |required: bool| match parts.next() {
+            Some(part) => part.parse::<u32>().map_err(|_| error()),
+            None if required => Err(error()),
+            None => Ok(0),
+        };
+
+        let version = UPowerVersion::new(next(true)?, next(true)?, next(false)?);
+
+        if parts.next().is_some() {
+            return Err(error());
+        }
+
+        Ok(version)
+    }
+}
@@ -0,0 +1,72 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use zbus::dbus_proxy;
+
+/// A single entry returned by `WakeupsProxy::get_data`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WakeupData {
+    /// Whether the wakeup originated from userspace rather than the kernel.
+    pub is_userspace: bool,
+    /// The process ID for userspace wakeups, or the IRQ number for kernel wakeups.
+    pub id: u32,
+    /// The number of wakeups per second.
+    pub value: f64,
+    /// The command line of the process, or the IRQ name.
+    pub cmdline: String,
+    /// Additional details, such as the kernel function responsible.
+    pub details: String,
+}
+
+type WakeupMessage = (bool, u32, f64, String, String);
+
+impl WakeupData {
+    fn from_message((is_userspace, id, value, cmdline, details): WakeupMessage) -> Self {
+        Self {
+            is_userspace,
+            id,
+            value,
+            cmdline,
+            details,
+        }
+    }
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.UPower.Wakeups",
+    default_service = "org.freedesktop.UPower",
+    default_path = "/org/freedesktop/UPower/Wakeups"
+)]
+trait Wakeups {
+    /// GetTotal method
+    fn get_total(&self) -> zbus::Result<u32>;
+
+    /// DataChanged signal
+    #[dbus_proxy(signal)]
+    fn data_changed(&self) -> zbus::Result<()>;
+
+    /// TotalChanged signal
+    #[dbus_proxy(signal)]
+    fn total_changed(&self, value: u32) -> zbus::Result<()>;
+
+    /// HasCapability property
+    #[dbus_proxy(property)]
+    fn has_capability(&self) -> zbus::Result<bool>;
+}
+
+impl WakeupsProxy<'_> {
+    /// GetData method
+    pub async fn get_data(&self) -> zbus::Result<Vec<WakeupData>> {
+        let data: Vec<WakeupMessage> = self.inner().call("GetData", &()).await?;
+        Ok(data.into_iter().map(WakeupData::from_message).collect())
+    }
+}
+
+impl WakeupsProxyBlocking<'_> {
+    /// GetData method
+    pub fn get_data(&self) -> zbus::Result<Vec<WakeupData>> {
+        let data: Vec<WakeupMessage> = self.inner().call("GetData", &())?;
+        Ok(data.into_iter().map(WakeupData::from_message).collect())
+    }
+}
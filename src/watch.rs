@@ -0,0 +1,197 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::future;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::task::{Poll, Waker};
+
+use crate::error::{Error, Result};
+
+/// A value kept current by a background task and read by its handles, such as the devices of
+/// a `DeviceCache`.
+///
+/// Each change bumps a version, which a handle compares with the version it last saw to learn
+/// whether the value changed. Once the task stops, the error which stopped it is kept and
+/// returned to every waiter.
+pub(crate) struct WatchCell<T> {
+    state: Mutex<State<T>>,
+}
+
+struct State<T> {
+    value: T,
+    version: u64,
+    error: Option<Error>,
+    wakers: Vec<Waker>,
+}
+
+impl<T> WatchCell<T> {
+    /// A cell holding `value` at version 0.
+    pub(crate) fn new(value: T) -> Self {
+        Self {
+            state: Mutex::new(State {
+                value,
+                version: 0,
+                error: None,
+                wakers: Vec::new(),
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Reads the value.
+    pub(crate) fn read<R>(&self, read: impl FnOnce(&T) -> R) -> R {
+        read(&self.lock().value)
+    }
+
+    /// Reads the value, and marks its version as seen.
+    pub(crate) fn read_and_mark_seen<R>(&self, seen: &mut u64, read: impl FnOnce(&T) -> R) -> R {
+        let state = self.lock();
+        *seen = state.version;
+        read(&state.value)
+    }
+
+    /// Whether the value changed since the version `seen`.
+    pub(crate) fn has_changed(&self, seen: u64) -> bool {
+        self.lock().version != seen
+    }
+
+    /// Applies `update` to the value, waking every waiter if it returns `true` for a change.
+    pub(crate) fn modify(&self, update: impl FnOnce(&mut T) -> bool) {
+        let wakers = {
+            let mut state = self.lock();
+            if !update(&mut state.value) {
+                return;
+            }
+
+            state.version += 1;
+            std::mem::take(&mut state.wakers)
+        };
+
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    /// Records the error which stopped the task, waking every waiter.
+    pub(crate) fn close(&self, why: Error) {
+        let wakers = {
+            let mut state = self.lock();
+            state.error = Some(why);
+            std::mem::take(&mut state.wakers)
+        };
+
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    /// Waits until the value changes from the version `seen`, and marks the new version as
+    /// seen.
+    ///
+    /// Fails with the error given to `close`, once every change has been seen.
+    pub(crate) async fn changed(&self, seen: &mut u64) -> Result<()> {
+        future::poll_fn(|cx| {
+            let mut state = self.lock();
+
+            if state.version != *seen {
+                *seen = state.version;
+                return Poll::Ready(Ok(()));
+            }
+
+            if let Some(why) = &state.error {
+                return Poll::Ready(Err(why.clone()));
+            }
+
+            if !state.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                state.wakers.push(cx.waker().clone());
+            }
+
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WatchCell;
+    use crate::error::Error;
+    use futures_util::FutureExt;
+
+    #[test]
+    fn changes_are_seen_once() {
+        let cell = WatchCell::new(1);
+        let mut seen = 0;
+
+        assert!(!cell.has_changed(seen));
+        assert!(cell.changed(&mut seen).now_or_never().is_none());
+
+        cell.modify(|value| {
+            *value = 2;
+            true
+        });
+        cell.modify(|value| {
+            *value = 3;
+            true
+        });
+        assert!(cell.has_changed(seen));
+
+        assert!(matches!(
+            cell.changed(&mut seen).now_or_never(),
+            Some(Ok(()))
+        ));
+        assert!(cell.changed(&mut seen).now_or_never().is_none());
+        assert_eq!(cell.read(|value| *value), 3);
+    }
+
+    #[test]
+    fn unchanged_values_do_not_wake() {
+        let cell = WatchCell::new(1);
+        let mut seen = 0;
+
+        cell.modify(|value| *value != 1);
+        assert!(!cell.has_changed(seen));
+
+        assert_eq!(cell.read_and_mark_seen(&mut seen, |value| *value), 1);
+        assert!(cell.changed(&mut seen).now_or_never().is_none());
+    }
+
+    #[test]
+    fn close_fails_after_the_last_change() {
+        let cell = WatchCell::new(1);
+        let mut seen = 0;
+
+        cell.modify(|value| {
+            *value = 2;
+            true
+        });
+        cell.close(Error::ServiceNotRunning);
+
+        assert!(matches!(
+            cell.changed(&mut seen).now_or_never(),
+            Some(Ok(()))
+        ));
+        assert!(matches!(
+            cell.changed(&mut seen).now_or_never(),
+            Some(Err(Error::ServiceNotRunning))
+        ));
+    }
+
+    #[test]
+    fn waiters_are_woken() {
+        let cell = std::sync::Arc::new(WatchCell::new(false));
+
+        let writer = cell.clone();
+        let thread = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            writer.modify(|value| {
+                *value = true;
+                true
+            });
+        });
+
+        let mut seen = 0;
+        futures::executor::block_on(cell.changed(&mut seen)).unwrap();
+        assert!(cell.read(|value| *value));
+        thread.join().unwrap();
+    }
+}
@@ -0,0 +1,146 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use futures_util::stream::Stream;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::device::{BatteryState, WarningLevel};
+use crate::error::Result;
+use crate::snapshot::DeviceSnapshot;
+use crate::units::Percentage;
+
+/// A low battery condition reported by a `BatteryWatcher`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BatteryAlert {
+    /// The charge fell to or below one of the watcher's thresholds while discharging.
+    ThresholdCrossed {
+        /// The threshold which was crossed, in percent.
+        threshold: f64,
+        /// The charge of the device, in percent.
+        percentage: Percentage,
+    },
+    /// The warning level reported by the daemon rose to `Low`, `Critical` or `Action`.
+    WarningLevelRaised(WarningLevel),
+}
+
+/// Reports each low battery threshold once as the charge falls through it.
+///
+/// Thresholds are re-armed once the device is no longer discharging and its charge is back
+/// above them. If a single reading falls through several thresholds, only the lowest is
+/// reported.
+///
+/// ```rust
+/// use upower_dbus::{
+///     BatteryAlert, BatteryState, BatteryWatcher, DeviceSnapshot, DeviceType, Percentage,
+/// };
+///
+/// let mut watcher = BatteryWatcher::new(vec![20.0, 10.0, 5.0]);
+/// let mut battery = DeviceSnapshot::new(DeviceType::Battery);
+/// battery.state = BatteryState::Discharging;
+///
+/// battery.percentage = Percentage::new(19.0);
+/// let alert = BatteryAlert::ThresholdCrossed {
+///     threshold: 20.0,
+///     percentage: Percentage::new(19.0),
+/// };
+/// assert_eq!(watcher.update(&battery), vec![alert]);
+///
+/// battery.percentage = Percentage::new(18.0);
+/// assert!(watcher.update(&battery).is_empty());
+/// ```
+#[derive(Clone, Debug)]
+pub struct BatteryWatcher {
+    /// Thresholds in descending order, and whether each may still be reported.
+    thresholds: Vec<(f64, bool)>,
+    warning_level: WarningLevel,
+}
+
+impl Default for BatteryWatcher {
+    /// Watches the thresholds of 20%, 10%, and 5%.
+    fn default() -> Self {
+        Self::new(vec![20.0, 10.0, 5.0])
+    }
+}
+
+impl BatteryWatcher {
+    /// Creates a watcher for the given thresholds, in percent.
+    pub fn new<I: IntoIterator<Item = f64>>(thresholds: I) -> Self {
+        let mut thresholds: Vec<(f64, bool)> = thresholds
+            .into_iter()
+            .filter(|threshold| threshold.is_finite())
+            .map(|threshold| (threshold, true))
+            .collect();
+
+        thresholds.sort_by(|a, b| b.0.total_cmp(&a.0));
+        thresholds.dedup_by(|a, b| a.0 == b.0);
+
+        Self {
+            thresholds,
+            warning_level: WarningLevel::None,
+        }
+    }
+
+    /// The thresholds being watched, in descending order.
+    pub fn thresholds(&self) -> impl Iterator<Item = f64> + '_ {
+        self.thresholds.iter().map(|&(threshold, _)| threshold)
+    }
+
+    /// Adds a reading of the device, returning the alerts it raises.
+    pub fn update(&mut self, device: &DeviceSnapshot) -> Vec<BatteryAlert> {
+        let mut alerts = Vec::new();
+        let percentage = device.percentage;
+
+        if device.state == BatteryState::Discharging {
+            let mut crossed = None;
+
+            for (threshold, armed) in &mut self.thresholds {
+                if *armed && percentage.get() <= *threshold {
+                    *armed = false;
+                    crossed = Some(*threshold);
+                }
+            }
+
+            if let Some(threshold) = crossed {
+                alerts.push(BatteryAlert::ThresholdCrossed {
+                    threshold,
+                    percentage,
+                });
+            }
+        } else {
+            for (threshold, armed) in &mut self.thresholds {
+                if percentage.get() > *threshold {
+                    *armed = true;
+                }
+            }
+        }
+
+        let level = device.warning_level;
+        if level >= WarningLevel::Low && level > self.warning_level {
+            alerts.push(BatteryAlert::WarningLevelRaised(level));
+        }
+        self.warning_level = level;
+
+        alerts
+    }
+}
+
+/// A stream of `BatteryAlert`s, created by `DeviceProxy::receive_battery_alerts`.
+pub struct BatteryAlertStream<'a> {
+    pub(crate) inner: Pin<Box<dyn Stream<Item = Result<BatteryAlert>> + Send + 'a>>,
+}
+
+impl fmt::Debug for BatteryAlertStream<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BatteryAlertStream").finish_non_exhaustive()
+    }
+}
+
+impl Stream for BatteryAlertStream<'_> {
+    type Item = Result<BatteryAlert>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
@@ -0,0 +1,39 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+#![allow(dead_code)]
+
+use futures::stream::{Stream, StreamExt};
+use std::future::Future;
+use std::time::Duration;
+use upower_dbus::{with_timeout, Error, Result};
+
+/// How long a test waits for a signal before failing, rather than hanging.
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Runs a test body on the current thread, inside a tokio runtime if zbus uses tokio.
+pub fn run<F: Future<Output = Result<()>>>(test: F) -> Result<()> {
+    #[cfg(feature = "tokio")]
+    {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        runtime.block_on(test)
+    }
+
+    #[cfg(not(feature = "tokio"))]
+    futures::executor::block_on(test)
+}
+
+/// The next item of `stream`, failing if none arrives within `TIMEOUT`.
+pub async fn next<S, T>(stream: &mut S) -> Result<T>
+where
+    S: Stream<Item = Result<T>> + Unpin,
+{
+    let item = async { Ok::<_, Error>(stream.next().await) };
+
+    match with_timeout(TIMEOUT, item).await? {
+        Some(item) => item,
+        None => panic!("the stream ended"),
+    }
+}
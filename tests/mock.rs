@@ -0,0 +1,195 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+mod common;
+
+use futures::stream::StreamExt;
+use std::time::Duration;
+use upower_dbus::mock::MockDeviceBuilder;
+use upower_dbus::testing::TestUPower;
+use upower_dbus::zvariant::{ObjectPath, OwnedObjectPath};
+use upower_dbus::{
+    BatteryState, DeviceCapabilities, DeviceProxy, DeviceType, Percentage, Result, WattHours, Watts,
+};
+
+use common::{next, run};
+
+#[test]
+fn ramp_percentage_emits_each_step() -> Result<()> {
+    run(async {
+        let test = TestUPower::new().await?;
+
+        let battery = MockDeviceBuilder::new(DeviceType::Battery)
+            .percentage(64.0)
+            .capabilities(DeviceCapabilities::ENERGY);
+        let path = test.mock.add_device("battery_BAT0", battery).await?;
+        let mut changes = test
+            .upower
+            .device(path.clone())
+            .await?
+            .receive_changes()
+            .await?;
+
+        test.mock
+            .ramp_percentage(&path.as_ref(), 32.0, Duration::from_secs(512), 2)
+            .await?;
+
+        let first = next(&mut changes).await?;
+        assert_eq!(first.percentage, Some(Percentage::new(48.0)));
+        assert_eq!(first.update_time, Some(256));
+        assert_eq!(first.energy, Some(WattHours(24.0)));
+        assert_eq!(first.energy_rate, Some(Watts(112.5)));
+        assert_eq!(first.state, Some(BatteryState::Discharging));
+        assert_eq!(first.time_to_empty, Some(768));
+
+        let second = next(&mut changes).await?;
+        assert_eq!(second.percentage, Some(Percentage::new(32.0)));
+        assert_eq!(second.update_time, Some(512));
+        assert_eq!(second.energy, Some(WattHours(16.0)));
+        // Unchanged since the first step, so not sent again.
+        assert_eq!(second.energy_rate, None);
+        assert_eq!(second.state, None);
+        assert_eq!(second.time_to_empty, Some(512));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn ramp_percentage_over_no_time_jumps() -> Result<()> {
+    run(async {
+        let test = TestUPower::new().await?;
+
+        let battery = MockDeviceBuilder::new(DeviceType::Battery)
+            .percentage(64.0)
+            .state(BatteryState::Discharging)
+            .capabilities(DeviceCapabilities::ENERGY);
+        let path = test.mock.add_device("battery_BAT0", battery).await?;
+        let mut changes = test
+            .upower
+            .device(path.clone())
+            .await?
+            .receive_changes()
+            .await?;
+
+        test.mock
+            .ramp_percentage(&path.as_ref(), 10.0, Duration::ZERO, 5)
+            .await?;
+
+        let jump = next(&mut changes).await?;
+        assert_eq!(jump.percentage, Some(Percentage::new(10.0)));
+        assert_eq!(jump.energy, Some(WattHours(5.0)));
+        assert_eq!(jump.energy_rate, None);
+        assert_eq!(jump.state, None);
+        assert_eq!(jump.time_to_empty, None);
+        assert_eq!(jump.update_time, None);
+
+        // Nothing was sent between the jump and this change.
+        test.mock
+            .set_state(&path.as_ref(), BatteryState::Charging)
+            .await?;
+        let marker = next(&mut changes).await?;
+        assert_eq!(marker.state, Some(BatteryState::Charging));
+        assert_eq!(marker.percentage, None);
+
+        Ok(())
+    })
+}
+
+/// A proxy to `path` which reads each property from the mock, rather than from a cache.
+async fn uncached(test: &TestUPower, path: &ObjectPath<'_>) -> Result<DeviceProxy<'static>> {
+    Ok(DeviceProxy::builder(test.connection())
+        .path(path.to_owned())?
+        .cache_properties(zbus::CacheProperties::No)
+        .build()
+        .await?)
+}
+
+#[test]
+fn setters_are_read_by_device_proxies() -> Result<()> {
+    run(async {
+        let test = TestUPower::new().await?;
+
+        let battery = MockDeviceBuilder::new(DeviceType::Battery).percentage(80.0);
+        let path = test.mock.add_device("battery_BAT0", battery).await?;
+        let device = uncached(&test, &path.as_ref()).await?;
+        assert_eq!(device.percentage().await?, Percentage::new(80.0));
+        assert_eq!(device.state().await?, BatteryState::Unknown);
+
+        test.mock.set_percentage(&path.as_ref(), 55.0).await?;
+        test.mock
+            .set_state(&path.as_ref(), BatteryState::Discharging)
+            .await?;
+        assert_eq!(device.percentage().await?, Percentage::new(55.0));
+        assert_eq!(device.state().await?, BatteryState::Discharging);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn added_and_removed_devices_are_signaled() -> Result<()> {
+    run(async {
+        let test = TestUPower::new().await?;
+
+        let mut added = test
+            .upower
+            .receive_device_added()
+            .await?
+            .map(|signal| Ok(OwnedObjectPath::from(signal.args()?.device().to_owned())));
+        let mut removed = test
+            .upower
+            .receive_device_removed()
+            .await?
+            .map(|signal| Ok(OwnedObjectPath::from(signal.args()?.device().to_owned())));
+
+        let battery = MockDeviceBuilder::new(DeviceType::Battery);
+        let path = test.mock.add_device("battery_BAT0", battery).await?;
+        assert_eq!(next(&mut added).await?, path);
+        assert_eq!(
+            test.upower.enumerate_devices().await?,
+            std::slice::from_ref(&path)
+        );
+
+        test.mock.remove_device(&path.as_ref()).await?;
+        assert_eq!(next(&mut removed).await?, path);
+        assert!(test.upower.enumerate_devices().await?.is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn update_device_signals_only_changed_properties() -> Result<()> {
+    run(async {
+        let test = TestUPower::new().await?;
+
+        let battery = MockDeviceBuilder::new(DeviceType::Battery)
+            .percentage(40.0)
+            .vendor("ACME");
+        let path = test.mock.add_device("battery_BAT0", battery).await?;
+        let mut changes = test
+            .upower
+            .device(path.clone())
+            .await?
+            .receive_changes()
+            .await?;
+
+        test.mock
+            .update_device(&path.as_ref(), |device| {
+                device.percentage = Percentage::new(39.0);
+                device.energy_rate = Watts(7.5);
+                device.vendor = "ACME".to_owned();
+            })
+            .await?;
+
+        let change = next(&mut changes).await?;
+        assert_eq!(change.percentage, Some(Percentage::new(39.0)));
+        assert_eq!(change.energy_rate, Some(Watts(7.5)));
+        // Set to the value it had, so not sent.
+        assert_eq!(change.vendor, None);
+        assert_eq!(change.state, None);
+
+        Ok(())
+    })
+}
@@ -0,0 +1,139 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+mod common;
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::{Arc, Mutex};
+use upower_dbus::notify::{NotificationsProxy, WarningNotifier};
+use upower_dbus::testing::TestBus;
+use upower_dbus::zvariant::OwnedValue;
+use upower_dbus::{DeviceSnapshot, DeviceType, Percentage, Result, WarningLevel};
+use zbus::dbus_interface;
+
+use common::run;
+
+/// A call received by `Notifications`.
+#[derive(Debug, PartialEq)]
+enum Call {
+    Notify {
+        replaces_id: u32,
+        summary: String,
+        urgency: u8,
+    },
+    Close(u32),
+}
+
+/// A notification server which records its calls.
+struct Notifications {
+    calls: Arc<Mutex<Vec<Call>>>,
+    next_id: u32,
+}
+
+#[dbus_interface(name = "org.freedesktop.Notifications")]
+impl Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &mut self,
+        _app_name: String,
+        replaces_id: u32,
+        _app_icon: String,
+        summary: String,
+        _body: String,
+        _actions: Vec<String>,
+        hints: HashMap<String, OwnedValue>,
+        _expire_timeout: i32,
+    ) -> u32 {
+        let urgency = hints
+            .get("urgency")
+            .and_then(|urgency| u8::try_from(urgency).ok())
+            .unwrap_or(u8::MAX);
+
+        self.calls.lock().unwrap().push(Call::Notify {
+            replaces_id,
+            summary,
+            urgency,
+        });
+
+        if replaces_id != 0 {
+            return replaces_id;
+        }
+
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn close_notification(&mut self, id: u32) {
+        self.calls.lock().unwrap().push(Call::Close(id));
+    }
+}
+
+fn battery(warning_level: WarningLevel) -> DeviceSnapshot {
+    let mut battery = DeviceSnapshot::new(DeviceType::Battery);
+    battery.percentage = Percentage::new(10.0);
+    battery.warning_level = warning_level;
+    battery
+}
+
+#[test]
+fn warning_levels_raise_replace_and_close_notifications() -> Result<()> {
+    run(async {
+        let bus = TestBus::new()?;
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let server = bus.connect().await?;
+        server
+            .object_server()
+            .at(
+                "/org/freedesktop/Notifications",
+                Notifications {
+                    calls: calls.clone(),
+                    next_id: 0,
+                },
+            )
+            .await?;
+        server.request_name("org.freedesktop.Notifications").await?;
+
+        let client = bus.connect().await?;
+        let notifications = NotificationsProxy::new(&client).await?;
+        let mut notifier = WarningNotifier::new();
+
+        notifier
+            .update(&notifications, &battery(WarningLevel::Low))
+            .await?;
+        notifier
+            .update(&notifications, &battery(WarningLevel::Critical))
+            .await?;
+        // A level which did not rise is not notified again.
+        notifier
+            .update(&notifications, &battery(WarningLevel::Critical))
+            .await?;
+        notifier
+            .update(&notifications, &battery(WarningLevel::None))
+            .await?;
+        // Nothing is left to close.
+        notifier
+            .update(&notifications, &battery(WarningLevel::None))
+            .await?;
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [
+                Call::Notify {
+                    replaces_id: 0,
+                    summary: "Battery low".to_owned(),
+                    urgency: 1,
+                },
+                Call::Notify {
+                    replaces_id: 1,
+                    summary: "Battery critically low".to_owned(),
+                    urgency: 2,
+                },
+                Call::Close(1),
+            ]
+        );
+
+        Ok(())
+    })
+}
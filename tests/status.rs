@@ -0,0 +1,142 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+mod common;
+
+use upower_dbus::mock::{MockDeviceBuilder, MockUPower};
+use upower_dbus::testing::TestUPower;
+use upower_dbus::zvariant::ObjectPath;
+use upower_dbus::{
+    with_timeout, BatteryState, DeviceSnapshot, DeviceType, Percentage, PowerStatus,
+    PowerStatusReceiver, Result,
+};
+
+use common::{run, TIMEOUT};
+
+fn battery(power_supply: bool) -> DeviceSnapshot {
+    let mut battery = MockDeviceBuilder::new(DeviceType::Battery)
+        .percentage(50.0)
+        .build();
+    battery.power_supply = power_supply;
+    battery
+}
+
+#[test]
+fn power_status_counts_system_batteries() -> Result<()> {
+    run(async {
+        let test = TestUPower::new().await?;
+
+        test.mock.add_device("battery_BAT0", battery(true)).await?;
+        test.mock.add_device("battery_BAT1", battery(true)).await?;
+        test.mock
+            .add_device("battery_hidpp", battery(false))
+            .await?;
+        test.mock
+            .add_device(
+                "line_power_AC",
+                MockDeviceBuilder::new(DeviceType::LinePower),
+            )
+            .await?;
+
+        let display = ObjectPath::from_static_str_unchecked(MockUPower::DISPLAY_DEVICE);
+        test.mock.set_percentage(&display, 42.0).await?;
+        test.mock
+            .set_state(&display, BatteryState::Discharging)
+            .await?;
+        test.mock.set_on_battery(true).await?;
+
+        let status = test.upower.power_status().await?;
+        assert!(status.on_battery);
+        assert!(!status.lid_is_closed);
+        assert_eq!(status.percentage, Percentage::new(42.0));
+        assert_eq!(status.state, BatteryState::Discharging);
+        assert_eq!(status.batteries, 2);
+
+        Ok(())
+    })
+}
+
+/// Waits until the status satisfies `done`, failing after `TIMEOUT`.
+async fn wait_for(
+    receiver: &mut PowerStatusReceiver,
+    done: impl Fn(&PowerStatus) -> bool,
+) -> Result<PowerStatus> {
+    let wait = async {
+        loop {
+            let status = receiver.borrow_and_update();
+            if done(&status) {
+                return Result::<_>::Ok(status);
+            }
+
+            receiver.changed().await?;
+        }
+    };
+
+    with_timeout(TIMEOUT, wait).await
+}
+
+#[test]
+fn watch_power_status_applies_events() -> Result<()> {
+    run(async {
+        let test = TestUPower::new().await?;
+        test.mock.add_device("battery_BAT0", battery(true)).await?;
+
+        let mut receiver = test.upower.watch_power_status().await?;
+        let status = receiver.borrow_and_update();
+        assert!(!status.on_battery);
+        assert_eq!(status.batteries, 1);
+        assert!(!receiver.has_changed());
+
+        test.mock.set_on_battery(true).await?;
+        wait_for(&mut receiver, |status| status.on_battery).await?;
+
+        let display = ObjectPath::from_static_str_unchecked(MockUPower::DISPLAY_DEVICE);
+        test.mock.set_percentage(&display, 30.0).await?;
+        let status = wait_for(&mut receiver, |status| {
+            status.percentage == Percentage::new(30.0)
+        })
+        .await?;
+        assert!(status.on_battery);
+
+        let second = test.mock.add_device("battery_BAT1", battery(true)).await?;
+        test.mock
+            .add_device("battery_hidpp", battery(false))
+            .await?;
+        wait_for(&mut receiver, |status| status.batteries == 2).await?;
+
+        test.mock.remove_device(&second.as_ref()).await?;
+        let status = wait_for(&mut receiver, |status| status.batteries == 1).await?;
+        assert_eq!(status, test.upower.power_status().await?);
+
+        Ok(())
+    })
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn into_watch_forwards_the_status() -> Result<()> {
+    run(async {
+        let test = TestUPower::new().await?;
+        test.mock.add_device("battery_BAT0", battery(true)).await?;
+
+        let mut receiver = test.upower.watch_power_status().await?.into_watch();
+        assert!(!receiver.borrow_and_update().on_battery);
+        assert_eq!(receiver.borrow().batteries, 1);
+
+        test.mock.set_on_battery(true).await?;
+
+        let wait = async {
+            while !receiver.borrow_and_update().on_battery {
+                receiver
+                    .changed()
+                    .await
+                    .expect("the status stopped being forwarded");
+            }
+
+            Result::<_>::Ok(())
+        };
+        with_timeout(TIMEOUT, wait).await?;
+
+        Ok(())
+    })
+}
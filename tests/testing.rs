@@ -0,0 +1,254 @@
+// Copyright 2021 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+mod common;
+
+use std::time::Duration;
+use upower_dbus::mock::{MockDeviceBuilder, MockUPower};
+use upower_dbus::testing::TestUPower;
+use upower_dbus::zvariant::ObjectPath;
+use upower_dbus::{
+    with_timeout, BatteryState, Device, DeviceCache, DeviceId, DeviceType, Error, Percentage,
+    PollingMonitor, PowerEvent, PowerEventStream, Result,
+};
+
+use common::{next, run, TIMEOUT};
+
+fn display() -> ObjectPath<'static> {
+    ObjectPath::from_static_str_unchecked(MockUPower::DISPLAY_DEVICE)
+}
+
+/// Skips events until one for which `wanted` returns a value, failing after `TIMEOUT` each.
+///
+/// Events for the state as it was when monitoring began may come first, so they are skipped.
+async fn next_event<T>(
+    events: &mut PowerEventStream<'_>,
+    wanted: impl Fn(PowerEvent) -> Option<T>,
+) -> Result<T> {
+    loop {
+        if let Some(found) = wanted(next(events).await?) {
+            return Ok(found);
+        }
+    }
+}
+
+/// Waits until the cache satisfies `done`, failing after `TIMEOUT`.
+async fn wait_for(cache: &mut DeviceCache, done: impl Fn(&DeviceCache) -> bool) -> Result<()> {
+    let wait = async {
+        while !done(cache) {
+            cache.changed().await?;
+        }
+
+        Result::<_>::Ok(())
+    };
+
+    with_timeout(TIMEOUT, wait).await
+}
+
+#[test]
+fn reads_the_display_device_and_stops_its_bus() -> Result<()> {
+    run(async {
+        let test = TestUPower::new().await?;
+        let address = test.bus.address().to_owned();
+
+        let display = test.upower.display_device().await?.snapshot().await?;
+        assert_eq!(display.type_, DeviceType::Battery);
+
+        drop(test);
+
+        // The daemon is gone along with the bus, so nothing may connect to it.
+        let connected = zbus::ConnectionBuilder::address(address.as_str())?
+            .build()
+            .await;
+        assert!(connected.is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn receive_changes_follows_the_display_device() -> Result<()> {
+    run(async {
+        let test = TestUPower::new().await?;
+
+        let mut changes = test
+            .upower
+            .display_device()
+            .await?
+            .receive_changes()
+            .await?;
+        test.mock.set_percentage(&display(), 25.0).await?;
+        test.mock
+            .set_state(&display(), BatteryState::Discharging)
+            .await?;
+
+        let change = next(&mut changes).await?;
+        assert_eq!(change.percentage, Some(Percentage::new(25.0)));
+
+        let change = match change.state {
+            Some(_) => change,
+            None => next(&mut changes).await?,
+        };
+        assert_eq!(change.state, Some(BatteryState::Discharging));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn monitor_emits_each_kind_of_event() -> Result<()> {
+    run(async {
+        let test = TestUPower::new().await?;
+        let mut events = test.upower.monitor().await?;
+
+        test.mock.set_on_battery(true).await?;
+        next_event(&mut events, |event| match event {
+            PowerEvent::OnBatteryChanged(true) => Some(()),
+            _ => None,
+        })
+        .await?;
+
+        test.mock.set_percentage(&display(), 60.0).await?;
+        next_event(&mut events, |event| match event {
+            PowerEvent::DisplayDeviceChanged(display)
+                if display.percentage == Percentage::new(60.0) =>
+            {
+                Some(())
+            }
+            _ => None,
+        })
+        .await?;
+
+        let battery = MockDeviceBuilder::new(DeviceType::Battery);
+        let path = test.mock.add_device("battery_BAT0", battery).await?;
+        let added = next_event(&mut events, |event| match event {
+            PowerEvent::DeviceAdded(device) => Some(device.path().to_owned()),
+            _ => None,
+        })
+        .await?;
+        assert_eq!(added, *path);
+
+        test.mock.remove_device(&path.as_ref()).await?;
+        let removed = next_event(&mut events, |event| match event {
+            PowerEvent::DeviceRemoved(path) => Some(path),
+            _ => None,
+        })
+        .await?;
+        assert_eq!(removed, path);
+
+        test.mock.set_lid_is_closed(true).await?;
+        next_event(&mut events, |event| match event {
+            PowerEvent::LidClosed(true) => Some(()),
+            _ => None,
+        })
+        .await?;
+
+        Ok(())
+    })
+}
+
+#[test]
+fn device_cache_follows_devices() -> Result<()> {
+    run(async {
+        let test = TestUPower::new().await?;
+        let mut cache = test.upower.device_cache().await?;
+        assert!(cache.is_empty());
+
+        let battery = MockDeviceBuilder::new(DeviceType::Battery).percentage(70.0);
+        let path = test.mock.add_device("battery_BAT0", battery).await?;
+        let id = DeviceId::from(path.clone());
+        wait_for(&mut cache, |cache| cache.len() == 1).await?;
+        assert_eq!(
+            cache.get(&id).map(|battery| battery.percentage),
+            Some(Percentage::new(70.0))
+        );
+
+        test.mock.set_percentage(&path.as_ref(), 65.0).await?;
+        wait_for(&mut cache, |cache| {
+            cache.get(&id).map(|battery| battery.percentage) == Some(Percentage::new(65.0))
+        })
+        .await?;
+
+        test.mock.remove_device(&path.as_ref()).await?;
+        wait_for(&mut cache, DeviceCache::is_empty).await?;
+
+        Ok(())
+    })
+}
+
+#[test]
+fn device_handles_close_on_removal() -> Result<()> {
+    run(async {
+        let test = TestUPower::new().await?;
+
+        let battery = MockDeviceBuilder::new(DeviceType::Battery);
+        let path = test.mock.add_device("battery_BAT0", battery).await?;
+        let device = Device::new(test.connection().clone(), path.clone());
+        assert!(device.is_alive());
+        device.snapshot().await?;
+
+        test.mock.remove_device(&path.as_ref()).await?;
+        with_timeout(TIMEOUT, async {
+            device.closed().await;
+            Result::<_>::Ok(())
+        })
+        .await?;
+
+        assert!(!device.is_alive());
+        assert!(matches!(device.snapshot().await, Err(Error::DeviceGone)));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn polling_monitor_reports_changes() -> Result<()> {
+    run(async {
+        let test = TestUPower::new().await?;
+        let mut events = PollingMonitor::new(Duration::from_millis(20))
+            .monitor(&test.upower)
+            .await?;
+
+        test.mock.set_on_battery(true).await?;
+        next_event(&mut events, |event| match event {
+            PowerEvent::OnBatteryChanged(true) => Some(()),
+            _ => None,
+        })
+        .await?;
+
+        let battery = MockDeviceBuilder::new(DeviceType::Battery);
+        let path = test.mock.add_device("battery_BAT0", battery).await?;
+        let added = next_event(&mut events, |event| match event {
+            PowerEvent::DeviceAdded(device) => Some(device.path().to_owned()),
+            _ => None,
+        })
+        .await?;
+        assert_eq!(added, *path);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn debounced_on_battery_drops_bounces() -> Result<()> {
+    run(async {
+        let test = TestUPower::new().await?;
+        let mut changes = test
+            .upower
+            .receive_on_battery_changed_debounced(Duration::from_millis(200))
+            .await?;
+
+        // Reverts within the delay, so is never reported.
+        test.mock.set_on_battery(true).await?;
+        test.mock.set_on_battery(false).await?;
+
+        // Settles, so is the first value reported.
+        test.mock.set_on_battery(true).await?;
+        assert!(next(&mut changes).await?);
+
+        test.mock.set_on_battery(false).await?;
+        assert!(!next(&mut changes).await?);
+
+        Ok(())
+    })
+}